@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
+use lumis::languages::Language;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -12,7 +13,15 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    GenSamples,
+    GenSamples {
+        /// Only regenerate samples for these languages (comma-separated, e.g. "rust,elixir")
+        #[arg(long, value_delimiter = ',')]
+        languages: Vec<String>,
+
+        /// Only regenerate samples for these themes (comma-separated, e.g. "dracula")
+        #[arg(long, value_delimiter = ',')]
+        themes: Vec<String>,
+    },
     GenCss,
 }
 
@@ -20,12 +29,12 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::GenSamples => gen_samples(),
+        Commands::GenSamples { languages, themes } => gen_samples(&languages, &themes),
         Commands::GenCss => gen_css(),
     }
 }
 
-fn gen_samples() -> Result<()> {
+fn gen_samples(languages: &[String], theme_filter: &[String]) -> Result<()> {
     let samples_path = PathBuf::from("./samples");
 
     let themes = [
@@ -88,14 +97,33 @@ fn gen_samples() -> Result<()> {
         "zenburn",
     ];
 
-    let entries = collect_sample_entries(&samples_path)?;
+    for name in theme_filter {
+        if !themes.contains(&name.as_str()) {
+            anyhow::bail!("unknown sample theme: {name}");
+        }
+    }
+
+    let themes = themes
+        .into_iter()
+        .filter(|theme| theme_filter.is_empty() || theme_filter.iter().any(|t| t == theme))
+        .collect::<Vec<_>>();
+
+    let languages = languages
+        .iter()
+        .map(|name| name.parse::<Language>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let entries = collect_sample_entries(&samples_path, &languages)?;
 
     gen_samples_entries(&themes, &samples_path, &entries)?;
 
     Ok(())
 }
 
-fn collect_sample_entries(samples_path: &Path) -> Result<Vec<fs::DirEntry>> {
+fn collect_sample_entries(
+    samples_path: &Path,
+    languages: &[Language],
+) -> Result<Vec<fs::DirEntry>> {
     let entries = fs::read_dir(samples_path)
         .context("failed to read samples")?
         .filter_map(|entry| {
@@ -103,7 +131,11 @@ fn collect_sample_entries(samples_path: &Path) -> Result<Vec<fs::DirEntry>> {
             let path = e.path();
             let file_name = path.file_name().and_then(|n| n.to_str())?;
 
-            if file_name == "README.md" || file_name == "LICENSE.md" {
+            if file_name == "README.md"
+                || file_name == "LICENSE.md"
+                || (!languages.is_empty()
+                    && !languages.contains(&Language::guess(Some(file_name), "")))
+            {
                 None
             } else if file_name == "html.html"
                 || path.extension().and_then(|ext| ext.to_str()) != Some("html")
@@ -137,7 +169,7 @@ fn gen_samples_entries(
             let contents = fs::read_to_string(&path)
                 .with_context(|| format!("failed to read sample file: {file_name}"))?;
 
-            let lang = lumis::languages::Language::guess(Some(file_name), &contents);
+            let lang = Language::guess(Some(file_name), &contents);
             let formatter = lumis::HtmlInlineBuilder::new()
                 .lang(lang)
                 .theme(Some(theme.clone()))
@@ -168,10 +200,7 @@ fn gen_css() -> Result<()> {
     let css_dir = Path::new("css");
     fs::create_dir_all(css_dir)?;
 
-    let mut themes: Vec<_> = lumis::themes::ALL_THEMES.iter().collect();
-    themes.sort_by(|a, b| a.name.cmp(&b.name));
-
-    for theme in themes {
+    for theme in lumis::themes::all() {
         let css = theme.css(true);
         let css_path = css_dir.join(format!("{}.css", theme.name));
         fs::write(&css_path, css)?;
@@ -80,21 +80,20 @@ async fn index(
         .unwrap_or("// No sample available");
 
     let theme = lumis::themes::get(&selected_theme).expect("theme validated above");
-    let language = lumis::languages::Language::guess(Some(&selected_lang), source);
-
-    let formatter = lumis::HtmlInlineBuilder::new()
-        .lang(language)
-        .theme(Some(theme))
-        .pre_class(Some(
-            "w-full overflow-auto rounded-lg p-8 font-mono text-sm antialiased leading-6"
-                .to_string(),
-        ))
-        .italic(false)
-        .include_highlights(true)
-        .build()
-        .expect("failed to build formatter");
-
-    let highlighted_html = lumis::highlight(source, formatter);
+
+    let (_, highlighted_html) = lumis::highlight::auto(source, Some(&selected_lang), |language| {
+        lumis::HtmlInlineBuilder::new()
+            .lang(language)
+            .theme(Some(theme))
+            .pre_class(Some(
+                "w-full overflow-auto rounded-lg p-8 font-mono text-sm antialiased leading-6"
+                    .to_string(),
+            ))
+            .italic(false)
+            .include_highlights(true)
+            .build()
+            .expect("failed to build formatter")
+    });
 
     let languages_json = format!(
         "[{}]",
@@ -144,7 +143,7 @@ async fn main() {
         .filter(|(id, _)| samples.contains_key(id.as_str()))
         .map(|(id, (name, _))| LangEntry { id, name })
         .collect();
-    languages.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    languages.sort_by_key(|a| a.name.to_lowercase());
 
     let mut themes: Vec<ThemeEntry> = lumis::themes::available_themes()
         .map(|t| ThemeEntry {
@@ -172,6 +172,13 @@ fn vendored_parsers() {
         extra_files: vec![],
     });
 
+    #[cfg(feature = "lang-http")]
+    parsers.push(TreeSitterParser {
+        name: "tree-sitter-http",
+        src_dir: "vendored_parsers/tree-sitter-http/src",
+        extra_files: vec![],
+    });
+
     #[cfg(feature = "lang-iex")]
     parsers.push(TreeSitterParser {
         name: "tree-sitter-iex",
@@ -416,6 +423,8 @@ fn queries() {
             "c" => cfg!(feature = "lang-c"),
             "caddy" => cfg!(feature = "lang-caddy"),
             "clojure" => cfg!(feature = "lang-clojure"),
+            "edn" => cfg!(feature = "lang-clojure"),
+            "ejs" => cfg!(feature = "lang-ejs"),
             "cmake" => cfg!(feature = "lang-cmake"),
             "comment" => cfg!(feature = "lang-comment"),
             "commonlisp" => cfg!(feature = "lang-commonlisp"),
@@ -439,6 +448,7 @@ fn queries() {
             "hcl" => cfg!(feature = "lang-hcl"),
             "heex" => cfg!(feature = "lang-heex"),
             "html" => cfg!(feature = "lang-html"),
+            "http" => cfg!(feature = "lang-http"),
             "iex" => cfg!(feature = "lang-iex"),
             "java" => cfg!(feature = "lang-java"),
             "javascript" => cfg!(feature = "lang-javascript"),
@@ -483,7 +493,7 @@ fn queries() {
         }
 
         let lang_upper = language.to_uppercase();
-        let queries = ["highlights", "injections", "locals"];
+        let queries = ["highlights", "injections", "locals", "folds"];
 
         for query in queries {
             let file_path = path.join(format!("{query}.scm"));
@@ -516,26 +526,30 @@ fn convert_lua_matches(content: &str) -> String {
     let lines: Vec<&str> = content.lines().collect();
 
     for line in lines {
+        if !line.contains("lua-match?") {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
         let line = line
             .replace("#lua-match?", "#match?")
             .replace("#not-lua-match?", "#not-match?");
 
-        if line.contains("#match?") || line.contains("#not-match?") {
-            if let Some(pattern_start) = line.find('"') {
-                if let Some(pattern_end) = line[pattern_start + 1..].find('"') {
-                    let pattern_end = pattern_start + 1 + pattern_end;
-                    let lua_pattern = &line[pattern_start + 1..pattern_end];
+        if let Some(pattern_start) = line.find('"') {
+            if let Some(pattern_end) = find_closing_quote(&line[pattern_start + 1..]) {
+                let pattern_end = pattern_start + 1 + pattern_end;
+                let lua_pattern = unescape_query_string(&line[pattern_start + 1..pattern_end]);
 
-                    let rust_pattern = convert_lua_pattern_to_rust_regex(lua_pattern);
+                let rust_pattern = convert_lua_pattern_to_rust_regex(&lua_pattern);
 
-                    let mut new_line = line[..pattern_start + 1].to_string();
-                    new_line.push_str(&rust_pattern);
-                    new_line.push_str(&line[pattern_end..]);
+                let mut new_line = line[..pattern_start + 1].to_string();
+                new_line.push_str(&escape_query_string(&rust_pattern));
+                new_line.push_str(&line[pattern_end..]);
 
-                    result.push_str(&new_line);
-                    result.push('\n');
-                    continue;
-                }
+                result.push_str(&new_line);
+                result.push('\n');
+                continue;
             }
         }
 
@@ -550,117 +564,170 @@ fn convert_lua_matches(content: &str) -> String {
     result
 }
 
-fn convert_lua_pattern_to_rust_regex(lua_pattern: &str) -> String {
-    let mut result = String::new();
-    let mut chars = lua_pattern.chars().peekable();
+// Byte offset of the quote closing a query string, skipping escaped quotes.
+fn find_closing_quote(text: &str) -> Option<usize> {
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+
+    None
+}
+
+// Query strings process their own escapes before the regex sees the pattern, so
+// patterns are unescaped before the conversion and escaped again afterwards.
+fn unescape_query_string(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
 
     while let Some(c) = chars.next() {
-        if c == '%' {
-            if let Some(&next_char) = chars.peek() {
-                match next_char {
-                    'd' => {
-                        result.push_str("\\d");
-                        chars.next();
-                    }
-                    's' => {
-                        result.push_str("\\s");
-                        chars.next();
-                    }
-                    'l' => {
-                        result.push_str("[a-z]");
-                        chars.next();
-                    }
-                    'u' => {
-                        result.push_str("[A-Z]");
-                        chars.next();
-                    }
-                    'A' => {
-                        result.push_str("[^a-zA-Z]");
-                        chars.next();
-                    }
-                    'S' => {
-                        result.push_str("\\S");
-                        chars.next();
-                    }
-                    '.' => {
-                        result.push_str("\\.");
-                        chars.next();
-                    }
-                    '%' => {
-                        result.push('%');
-                        chars.next();
-                    }
-                    '{' => {
-                        result.push_str("\\{");
-                        chars.next();
-                    }
-                    '}' => {
-                        result.push_str("\\}");
-                        chars.next();
-                    }
-                    '$' => {
-                        // Special handling for $
-                        result.push_str("\\$");
-                        chars.next();
-                        // Check if next char is {, which needs special handling in Rust regex
-                        if let Some(&next) = chars.peek() {
-                            if next == '{' {
-                                result.push('\\'); // Add extra escape for ${
-                            }
-                        }
-                    }
-                    '^' => {
-                        result.push_str("\\^");
-                        chars.next();
-                    }
-                    _ => {
-                        result.push('\\');
-                        result.push(next_char);
-                        chars.next();
-                    }
-                }
-            } else {
-                result.push('%');
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some(c) => result.push(c),
+            None => (),
+        }
+    }
+
+    result
+}
+
+fn escape_query_string(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '"' => result.push_str("\\\""),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\0' => result.push_str("\\0"),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+fn convert_lua_pattern_to_rust_regex(lua_pattern: &str) -> String {
+    let chars: Vec<char> = lua_pattern.chars().collect();
+    // Lua's `.` also matches newlines
+    let mut result = String::from("(?s)");
+    // Quantifiers are literal characters when they don't follow an item
+    let mut quantifiable = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let mut item = true;
+
+        match c {
+            '^' if i == 0 => {
+                result.push('^');
+                item = false;
+            }
+            '$' if i == chars.len() - 1 => result.push('$'),
+            '*' | '+' | '?' if quantifiable => {
+                result.push(c);
+                item = false;
+            }
+            '-' if quantifiable => {
+                result.push_str("*?");
+                item = false;
+            }
+            '(' | ')' => {
+                result.push(c);
+                item = false;
             }
-        } else if c == '\\' {
-            result.push('\\');
-            result.push('\\');
-            if let Some(&next_char) = chars.peek() {
-                result.push(next_char);
-                chars.next();
+            '.' => result.push('.'),
+            '%' if i + 1 < chars.len() => {
+                i += 1;
+                result.push_str(&convert_lua_class(chars[i], false));
             }
-        } else if c == '$' {
-            // Handle special $ character
-            result.push_str("\\$");
-            // Check if next char is {, which needs special handling in Rust regex
-            if let Some(&next) = chars.peek() {
-                if next == '{' {
-                    result.push('\\'); // Add extra escape for ${
+            '[' => {
+                result.push('[');
+                i += 1;
+
+                if chars.get(i) == Some(&'^') {
+                    result.push('^');
+                    i += 1;
                 }
+
+                let set_start = i;
+                while let Some(&c) = chars.get(i) {
+                    match c {
+                        ']' if i > set_start => break,
+                        '%' if i + 1 < chars.len() => {
+                            i += 1;
+                            result.push_str(&convert_lua_class(chars[i], true));
+                        }
+                        '-' => result.push('-'),
+                        _ => push_escaped(&mut result, c),
+                    }
+                    i += 1;
+                }
+
+                result.push(']');
             }
-        } else if c == '.'
-            || c == '*'
-            || c == '+'
-            || c == '?'
-            || c == '('
-            || c == ')'
-            || c == '['
-            || c == ']'
-            || c == '{'
-            || c == '}'
-            || c == '|'
-            || (c == '^' && !result.is_empty())
-        {
-            result.push('\\');
-            result.push(c);
-        } else {
-            result.push(c);
+            _ => push_escaped(&mut result, c),
         }
+
+        quantifiable = item;
+        i += 1;
     }
 
     result
 }
 
+// Convert the Lua class after `%` into a POSIX class, or escape it when it's a
+// plain character such as in `%.`.
+fn convert_lua_class(class: char, in_set: bool) -> String {
+    let name = match class.to_ascii_lowercase() {
+        'a' => "alpha",
+        'c' => "cntrl",
+        'd' => "digit",
+        'g' => "graph",
+        'l' => "lower",
+        'p' => "punct",
+        's' => "space",
+        'u' => "upper",
+        'w' => "alnum",
+        'x' => "xdigit",
+        _ => {
+            let mut escaped = String::new();
+            push_escaped(&mut escaped, class);
+            return escaped;
+        }
+    };
+    let negation = if class.is_ascii_uppercase() { "^" } else { "" };
+
+    if in_set {
+        format!("[:{negation}{name}:]")
+    } else {
+        format!("[[:{negation}{name}:]]")
+    }
+}
+
+fn push_escaped(result: &mut String, c: char) {
+    if "\\.+*?()|[]{}^$#&-~".contains(c) {
+        result.push('\\');
+    }
+    result.push(c);
+}
+
 fn themes() {
     println!(
         "cargo:rerun-if-changed={}",
@@ -671,7 +738,7 @@ fn themes() {
     let dest_path = Path::new(&out_dir).join("theme_data.rs");
     let themes_dir = manifest_dir().join("themes");
 
-    let theme_names: Vec<String> = fs::read_dir(&themes_dir)
+    let mut theme_names: Vec<String> = fs::read_dir(&themes_dir)
         .unwrap()
         .filter_map(|entry| {
             let entry = entry.ok()?;
@@ -683,6 +750,7 @@ fn themes() {
             }
         })
         .collect();
+    theme_names.sort();
 
     let theme_constants = theme_names.iter().map(|name| {
         let constant_name = format_ident!("{}", name.to_uppercase());
@@ -705,7 +773,7 @@ fn themes() {
     let theme_name_matches = theme_names.iter().map(|name| {
         let constant_name = format_ident!("{}", name.to_uppercase());
         let name_str = name.to_lowercase();
-        quote! { #name_str => Ok(#constant_name.clone()), }
+        quote! { #name_str => Some(#constant_name.clone()), }
     });
 
     let output = quote! {
@@ -722,6 +790,9 @@ fn themes() {
         ///
         /// Returns an owned `Theme` that can be used for syntax highlighting.
         ///
+        /// Names are matched leniently: case is ignored and hyphens and spaces are
+        /// treated as underscores, so `Catppuccin-Mocha` finds `catppuccin_mocha`.
+        ///
         /// # Examples
         ///
         /// ```
@@ -730,13 +801,32 @@ fn themes() {
         /// let theme = themes::get("github_light").expect("Theme not found");
         /// assert_eq!(theme.name, "github_light");
         ///
+        /// let theme = themes::get("Catppuccin-Mocha").expect("Theme not found");
+        /// assert_eq!(theme.name, "catppuccin_mocha");
+        ///
         /// let theme = themes::get("non_existent_theme");
         /// assert!(theme.is_err());
         /// ```
         pub fn get(name: &str) -> Result<Theme, ThemeError> {
+            get_exact(name)
+                .or_else(|| {
+                    let normalized: String = name
+                        .trim()
+                        .chars()
+                        .map(|c| match c {
+                            '-' | ' ' => '_',
+                            c => c.to_ascii_lowercase(),
+                        })
+                        .collect();
+                    get_exact(&normalized)
+                })
+                .ok_or_else(|| ThemeError::NotFound(name.to_string()))
+        }
+
+        fn get_exact(name: &str) -> Option<Theme> {
             match name {
                 #(#theme_name_matches)*
-                _ => Err(ThemeError::NotFound(name.to_string())),
+                _ => None,
             }
         }
     };
@@ -0,0 +1,4 @@
+; HTML around EEx tags
+((text) @injection.content
+ (#set! injection.language "html")
+ (#set! injection.combined))
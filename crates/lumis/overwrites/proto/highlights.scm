@@ -0,0 +1,34 @@
+; * package names
+(package
+  (full_ident
+    (identifier) @module))
+
+; * rpc definitions, distinct from their request and response types
+(rpc_name) @function.method
+
+; * option names
+(option
+  (identifier) @property)
+
+; * oneof groups and their fields, and map fields
+(oneof
+  (identifier) @property)
+
+(oneof_field
+  (identifier) @property)
+
+(map_field
+  (identifier) @property)
+
+; * keywords missing upstream
+"map" @type.builtin
+
+[
+  "stream"
+  "weak"
+  "public"
+] @keyword.modifier
+
+"max" @constant.builtin
+
+"edition" @keyword
@@ -0,0 +1,8 @@
+; Doc comments are Markdown, so fenced code in them is highlighted through the
+; Markdown injections. All `///` and `//!` lines are combined into one document
+; to let fences span lines. Fences need a language tag (```rust): the Markdown
+; grammar can't tell rustdoc's untagged default apart from plain text.
+((line_comment
+  doc: (doc_comment) @injection.content)
+  (#set! injection.language "markdown")
+  (#set! injection.combined))
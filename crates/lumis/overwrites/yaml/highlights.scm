@@ -0,0 +1,2 @@
+; * float scalars, for parity with the number.float scope used by toml
+(float_scalar) @number.float
@@ -0,0 +1,37 @@
+; EDN is a data format parsed with the Clojure grammar. Unlike Clojure, the first
+; symbol of a list is data rather than a function call, and there are no macros or
+; special forms, so symbols are only ever highlighted as variables.
+
+((dis_expr) @comment
+  (#set! priority 105))
+
+(comment) @comment @spell
+
+(kwd_lit) @string.special.symbol
+
+(str_lit) @string
+
+(num_lit) @number
+
+(char_lit) @character
+
+(bool_lit) @boolean
+
+(nil_lit) @constant.builtin
+
+(sym_lit) @variable
+
+; Tagged elements, like #inst and #uuid
+(tagged_or_ctor_lit
+  tag: (sym_lit) @type)
+
+"#" @punctuation.special
+
+[
+  "{"
+  "}"
+  "["
+  "]"
+  "("
+  ")"
+] @punctuation.bracket
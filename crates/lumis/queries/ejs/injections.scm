@@ -0,0 +1,10 @@
+; EJS is parsed with the embedded_template grammar like ERB, but its code is
+; JavaScript instead of Ruby.
+
+((content) @injection.content
+  (#set! injection.language "html")
+  (#set! injection.combined))
+
+((code) @injection.content
+  (#set! injection.language "javascript")
+  (#set! injection.combined))
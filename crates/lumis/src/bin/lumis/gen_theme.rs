@@ -24,8 +24,42 @@ pub fn generate_theme(
     let json_content = fs::read_to_string(&json_path)
         .context(format!("Failed to read generated JSON at {:?}", json_path))?;
 
+    if let Ok(theme) = lumis::themes::from_json(&json_content) {
+        warn_missing_scopes(&theme);
+    }
+
+    write_output(&json_content, output)
+}
+
+/// Converts a VS Code color theme JSON file instead of extracting a Neovim colorscheme.
+pub fn generate_theme_from_vscode(path: &str, output: Option<&str>) -> Result<()> {
+    let vscode_content =
+        fs::read_to_string(path).context(format!("Failed to read VS Code theme at {}", path))?;
+    let theme = lumis::themes::from_vscode_json(&vscode_content)
+        .context(format!("Failed to convert VS Code theme at {}", path))?;
+
+    warn_missing_scopes(&theme);
+
+    let json_content = serde_json::to_string_pretty(&theme).context("Failed to serialize theme")?;
+
+    write_output(&json_content, output)
+}
+
+fn warn_missing_scopes(theme: &lumis::themes::Theme) {
+    let missing = theme.missing_scopes();
+    if !missing.is_empty() {
+        eprintln!(
+            "⚠ Theme doesn't style {} of {} scopes: {}",
+            missing.len(),
+            lumis::constants::HIGHLIGHT_NAMES.len(),
+            missing.join(", ")
+        );
+    }
+}
+
+fn write_output(json_content: &str, output: Option<&str>) -> Result<()> {
     if let Some(output_path) = output {
-        fs::write(output_path, &json_content)
+        fs::write(output_path, json_content)
             .context(format!("Failed to write output to {}", output_path))?;
         eprintln!("✓ Theme saved to {}", output_path);
     } else {
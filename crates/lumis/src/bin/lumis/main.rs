@@ -6,7 +6,6 @@ use lumis::formatter::Formatter as FormatterTrait;
 use lumis::languages::Language;
 use std::fmt::Display;
 use std::fs;
-use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use strum::IntoEnumIterator;
 
@@ -24,11 +23,28 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// List all supported programming languages and their file patterns
-    ListLanguages,
+    ListLanguages {
+        /// List the lang-* features this binary was built with instead
+        #[arg(long)]
+        features: bool,
+    },
 
     /// List all available syntax highlighting themes
     ListThemes,
 
+    /// Print the style a theme resolves for a highlight scope (useful for debugging)
+    ThemeScope {
+        /// Theme name (e.g., "dracula", "github_dark")
+        theme: String,
+
+        /// Highlight scope (e.g., "keyword.function")
+        scope: String,
+
+        /// Specialize the scope for a language, e.g. "rust" looks up "keyword.function.rust"
+        #[arg(short = 'l', long)]
+        language: Option<String>,
+    },
+
     /// Dump the Tree-sitter AST for a file (useful for debugging)
     DumpTreeSitter {
         /// Path to the file to analyze
@@ -67,6 +83,9 @@ enum Commands {
         /// Highlight lines
         #[arg(short = 'l', long)]
         highlight_lines: Option<String>,
+        /// Print the theme stylesheet in a <style> element before html-linked output
+        #[arg(long)]
+        with_css: bool,
     },
 
     /// Highlight a string of source code
@@ -101,17 +120,24 @@ enum Commands {
         /// Highlight lines
         #[arg(long)]
         highlight_lines: Option<String>,
+        /// Print the theme stylesheet in a <style> element before html-linked output
+        #[arg(long)]
+        with_css: bool,
     },
 
     /// Generate a theme JSON from a Git repository containing a Neovim theme
     GenTheme {
         /// Git repository URL (e.g., <https://github.com/catppuccin/nvim>)
-        #[arg(short = 'u', long)]
-        url: String,
+        #[arg(short = 'u', long, required_unless_present = "vscode")]
+        url: Option<String>,
 
         /// Colorscheme name to activate (e.g., catppuccin-mocha)
-        #[arg(short = 'c', long)]
-        colorscheme: String,
+        #[arg(short = 'c', long, required_unless_present = "vscode")]
+        colorscheme: Option<String>,
+
+        /// Convert a VS Code color theme JSON file instead of a Neovim colorscheme
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["url", "colorscheme", "setup", "appearance"])]
+        vscode: Option<String>,
 
         /// Custom Lua setup code (optional)
         #[arg(short = 's', long)]
@@ -148,8 +174,13 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::ListLanguages => list_languages(),
+        Commands::ListLanguages { features } => list_languages(features),
         Commands::ListThemes => list_themes(),
+        Commands::ThemeScope {
+            theme,
+            scope,
+            language,
+        } => theme_scope(&theme, &scope, language.as_deref()),
         Commands::DumpTreeSitter { path, color } => dump_tree_sitter(&path, color),
         Commands::Highlight {
             path,
@@ -159,6 +190,7 @@ fn main() -> Result<()> {
             default_theme,
             css_variable_prefix,
             highlight_lines,
+            with_css,
         } => highlight(
             &path,
             formatter,
@@ -167,6 +199,7 @@ fn main() -> Result<()> {
             default_theme,
             css_variable_prefix,
             highlight_lines,
+            with_css,
         ),
         Commands::HighlightSource {
             source,
@@ -177,6 +210,7 @@ fn main() -> Result<()> {
             default_theme,
             css_variable_prefix,
             highlight_lines,
+            with_css,
         } => highlight_source(
             &source,
             language.as_deref(),
@@ -186,38 +220,86 @@ fn main() -> Result<()> {
             default_theme,
             css_variable_prefix,
             highlight_lines,
+            with_css,
         ),
         Commands::GenTheme {
             url,
             colorscheme,
+            vscode,
             setup,
             output,
             appearance,
-        } => gen_theme::generate_theme(
-            &url,
-            &colorscheme,
-            setup.as_deref(),
-            output.as_deref(),
-            appearance.as_deref(),
-        ),
+        } => match (vscode, url, colorscheme) {
+            (Some(vscode), _, _) => {
+                gen_theme::generate_theme_from_vscode(&vscode, output.as_deref())
+            }
+            (None, Some(url), Some(colorscheme)) => gen_theme::generate_theme(
+                &url,
+                &colorscheme,
+                setup.as_deref(),
+                output.as_deref(),
+                appearance.as_deref(),
+            ),
+            _ => unreachable!("clap requires --url and --colorscheme without --vscode"),
+        },
     }
 }
 
 /// Lists all available themes in alphabetical order
 fn list_themes() -> Result<()> {
-    let mut themes: Vec<_> = lumis::themes::ALL_THEMES.iter().collect();
-    themes.sort_by(|a, b| a.name.cmp(&b.name));
-
-    for theme in themes {
+    for theme in lumis::themes::all() {
         println!("{}", theme.name);
     }
 
     Ok(())
 }
 
+/// Prints the style a theme resolves for a scope
+///
+/// The scope is looked up with `Theme::get_style`, falling back to its parent scopes,
+/// so the output is the style the formatters apply to tokens of that scope.
+///
+/// # Arguments
+/// * `theme` - Theme name
+/// * `scope` - Highlight scope, e.g. `keyword.function`
+/// * `language` - Language appended to the scope, e.g. `rust` for `keyword.function.rust`
+fn theme_scope(theme: &str, scope: &str, language: Option<&str>) -> Result<()> {
+    let theme =
+        lumis::themes::get(theme).map_err(|_| anyhow::anyhow!("Theme '{}' not found", theme))?;
+
+    let scope = match language {
+        Some(language) => format!("{scope}.{}", language.parse::<Language>()?.id_name()),
+        None => scope.to_string(),
+    };
+
+    let style = theme
+        .get_style(&scope)
+        .ok_or_else(|| anyhow::anyhow!("Theme '{}' has no style for '{}'", theme.name, scope))?;
+
+    println!("scope: {scope}");
+    println!("fg: {}", style.fg.as_deref().unwrap_or("-"));
+    println!("bg: {}", style.bg.as_deref().unwrap_or("-"));
+    println!("bold: {}", style.bold);
+    println!("italic: {}", style.italic);
+    println!("underline: {:?}", style.text_decoration.underline);
+    println!("strikethrough: {}", style.text_decoration.strikethrough);
+
+    Ok(())
+}
+
 /// Lists all supported programming languages and their associated file patterns
-fn list_languages() -> Result<()> {
-    for language in Language::iter() {
+///
+/// Injection-only grammars are skipped since they can't highlight a file on their own.
+fn list_languages(features: bool) -> Result<()> {
+    if features {
+        for feature in lumis::languages::enabled_features() {
+            println!("{feature}");
+        }
+
+        return Ok(());
+    }
+
+    for language in Language::iter().filter(|language| !language.is_injection_only()) {
         let name = Language::id_name(&language);
         println!("{name}");
 
@@ -383,6 +465,8 @@ fn print_cursor(src: &str, cursor: &mut tree_sitter::TreeCursor, depth: usize, c
 /// * `formatter` - Output format (terminal, html-inline, html-linked)
 /// * `theme` - Theme name to use for highlighting
 /// * `highlight_lines` - Optional string specifying lines to highlight (e.g., "1,3-5,8")
+/// * `with_css` - Whether to print the theme stylesheet before html-linked output
+#[allow(clippy::too_many_arguments)]
 fn highlight(
     path: &str,
     formatter: Option<Formatter>,
@@ -391,12 +475,13 @@ fn highlight(
     default_theme: Option<String>,
     css_variable_prefix: String,
     highlight_lines: Option<String>,
+    with_css: bool,
 ) -> Result<()> {
     let theme = theme.unwrap_or("catppuccin_frappe".to_string());
     let theme = lumis::themes::get(&theme).ok();
 
     let parsed_highlight_lines = if let Some(lines_str) = highlight_lines {
-        Some(parse_highlight_lines(&lines_str)?)
+        Some(lumis::formatter::parse_line_ranges(&lines_str)?)
     } else {
         None
     };
@@ -483,7 +568,13 @@ fn highlight(
                     builder.highlight_lines(Some(html_highlight_lines));
                 }
 
-                builder.build().map_err(|e| anyhow::anyhow!("{}", e))?
+                let (formatter, warnings) = builder
+                    .build_with_warnings()
+                    .map_err(|e| anyhow::anyhow!("{}", e))?;
+                for warning in warnings {
+                    eprintln!("Warning: {}", warning);
+                }
+                formatter
             };
             let mut output = Vec::new();
             formatter.format(source, &mut output).unwrap();
@@ -493,6 +584,10 @@ fn highlight(
         }
 
         Formatter::HtmlLinked => {
+            if with_css {
+                print_linked_css(theme.as_ref(), parsed_highlight_lines.is_some())?;
+            }
+
             let formatter = if let Some(lines) = parsed_highlight_lines {
                 let html_highlight_lines = lumis::formatter::html_linked::HighlightLines {
                     lines,
@@ -535,6 +630,24 @@ fn highlight(
     Ok(())
 }
 
+/// Prints the linked stylesheet of a theme in a `<style>` element
+///
+/// # Arguments
+/// * `theme` - Theme to generate the stylesheet from
+/// * `highlight_lines` - Whether to include the rule for highlighted lines
+fn print_linked_css(theme: Option<&lumis::themes::Theme>, highlight_lines: bool) -> Result<()> {
+    let theme = theme.ok_or_else(|| anyhow::anyhow!("--with-css requires a valid --theme"))?;
+
+    let mut css = theme.css(true);
+    if highlight_lines {
+        css.push_str(&theme.highlight_line_css("highlighted"));
+    }
+
+    println!("<style>\n{css}</style>");
+
+    Ok(())
+}
+
 const EXIT_BAD_ARGUMENTS: i32 = 2;
 
 /// Reads a file or exits with an error message
@@ -596,63 +709,6 @@ impl Display for FileArgument {
     }
 }
 
-/// Parses a highlight_lines string into a vector of `RangeInclusive<usize>`
-///
-/// Supports formats like:
-/// - "1" (single line)
-/// - "1,3,5" (multiple single lines)
-/// - "1-3" (range from 1 to 3)
-/// - "1,3-5,8" (mix of single lines and ranges)
-///
-/// # Arguments
-/// * `input` - The string to parse
-fn parse_highlight_lines(input: &str) -> Result<Vec<RangeInclusive<usize>>> {
-    let mut ranges = Vec::new();
-
-    for part in input.split(',') {
-        let part = part.trim();
-        if part.is_empty() {
-            continue;
-        }
-
-        if let Some((start, end)) = part.split_once('-') {
-            let start: usize = start
-                .trim()
-                .parse()
-                .map_err(|_| anyhow::anyhow!("Invalid line number: '{}'", start.trim()))?;
-            let end: usize = end
-                .trim()
-                .parse()
-                .map_err(|_| anyhow::anyhow!("Invalid line number: '{}'", end.trim()))?;
-
-            if start == 0 || end == 0 {
-                return Err(anyhow::anyhow!("Line numbers must be greater than 0"));
-            }
-            if start > end {
-                return Err(anyhow::anyhow!(
-                    "Start line ({}) must be less than or equal to end line ({})",
-                    start,
-                    end
-                ));
-            }
-
-            ranges.push(start..=end);
-        } else {
-            let line: usize = part
-                .parse()
-                .map_err(|_| anyhow::anyhow!("Invalid line number: '{}'", part))?;
-
-            if line == 0 {
-                return Err(anyhow::anyhow!("Line numbers must be greater than 0"));
-            }
-
-            ranges.push(line..=line);
-        }
-    }
-
-    Ok(ranges)
-}
-
 /// Converts an absolute path to a path relative to the current directory
 ///
 /// # Arguments
@@ -686,6 +742,7 @@ fn try_canonicalize(path: &Path) -> PathBuf {
 /// * `formatter` - Output format (terminal, html-inline, html-linked)
 /// * `theme` - Theme name to use for highlighting
 /// * `highlight_lines` - Optional string specifying lines to highlight (e.g., "1,3-5,8")
+/// * `with_css` - Whether to print the theme stylesheet before html-linked output
 #[allow(clippy::too_many_arguments)]
 fn highlight_source(
     source: &str,
@@ -696,12 +753,13 @@ fn highlight_source(
     default_theme: Option<String>,
     css_variable_prefix: String,
     highlight_lines: Option<String>,
+    with_css: bool,
 ) -> Result<()> {
     let theme = theme.unwrap_or("catppuccin_frappe".to_string());
     let theme = lumis::themes::get(&theme).ok();
 
     let parsed_highlight_lines = if let Some(lines_str) = highlight_lines {
-        Some(parse_highlight_lines(&lines_str)?)
+        Some(lumis::formatter::parse_line_ranges(&lines_str)?)
     } else {
         None
     };
@@ -742,6 +800,10 @@ fn highlight_source(
         }
 
         Formatter::HtmlLinked => {
+            if with_css {
+                print_linked_css(theme.as_ref(), parsed_highlight_lines.is_some())?;
+            }
+
             let formatter = if let Some(lines) = parsed_highlight_lines {
                 let html_highlight_lines = lumis::formatter::html_linked::HighlightLines {
                     lines,
@@ -811,8 +873,13 @@ fn highlight_source(
                     builder.highlight_lines(Some(html_highlight_lines));
                 }
 
-                match builder.build() {
-                    Ok(f) => f,
+                match builder.build_with_warnings() {
+                    Ok((f, warnings)) => {
+                        for warning in warnings {
+                            eprintln!("Warning: {}", warning);
+                        }
+                        f
+                    }
                     Err(e) => {
                         eprintln!("Error building formatter: {}", e);
                         std::process::exit(1);
@@ -9,6 +9,11 @@
 //! - [`CLASSES`] - CSS class names corresponding to each scope (e.g., "function-builtin", "string-escape")
 //!
 //! The arrays maintain a 1:1 mapping by index. For example, `HIGHLIGHT_NAMES[0]` corresponds to `CLASSES[0]`.
+//!
+//! # Functions
+//!
+//! - [`scope_name`] - Scope name for a highlight index (e.g., the `usize` in a tree-sitter `Highlight`)
+//! - [`scope_index`] - Highlight index for a scope name
 
 /// Tree-sitter highlight scope names.
 ///
@@ -601,3 +606,39 @@ pub const CLASSES: [&str; 287] = [
     "variable-sql",
     "variable-vim",
 ];
+
+/// Get the scope name for a highlight index.
+///
+/// Highlight indices emitted by the highlighter are positions in [`HIGHLIGHT_NAMES`].
+/// Returns `None` if the index is out of range.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::constants::{scope_index, scope_name};
+///
+/// let index = scope_index("keyword").unwrap();
+/// assert_eq!(scope_name(index), Some("keyword"));
+/// assert_eq!(scope_name(usize::MAX), None);
+/// ```
+pub fn scope_name(index: usize) -> Option<&'static str> {
+    HIGHLIGHT_NAMES.get(index).copied()
+}
+
+/// Get the highlight index for a scope name.
+///
+/// This is the inverse of [`scope_name`]. Returns `None` if the scope isn't one of
+/// the [`HIGHLIGHT_NAMES`]; no parent scope fallback is applied.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::constants::{scope_index, CLASSES};
+///
+/// let index = scope_index("function.method.call").unwrap();
+/// assert_eq!(CLASSES[index], "function-method-call");
+/// assert_eq!(scope_index("not.a.scope"), None);
+/// ```
+pub fn scope_index(name: &str) -> Option<usize> {
+    HIGHLIGHT_NAMES.iter().position(|&scope| scope == name)
+}
@@ -25,7 +25,8 @@
 
 use crate::highlight::{highlight_iter, HighlightError, Style};
 use crate::languages::Language;
-use crate::themes::Theme;
+use crate::themes::{Appearance, Theme};
+use std::io::{self, Write};
 use std::ops::Range;
 
 /// ANSI reset sequence to clear all formatting.
@@ -53,17 +54,7 @@ pub const ANSI_RESET: &str = "\u{1b}[0m";
 /// assert_eq!(hex_to_rgb("invalid"), None);
 /// ```
 pub fn hex_to_rgb(hex: &str) -> Option<(u8, u8, u8)> {
-    let hex = hex.trim_start_matches('#');
-
-    if hex.len() != 6 {
-        return None;
-    }
-
-    let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-    let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-    let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-
-    Some((r, g, b))
+    crate::themes::hex_to_rgb(hex)
 }
 
 /// Generate ANSI color escape sequence from RGB values.
@@ -96,6 +87,110 @@ pub fn rgb_to_ansi(r: u8, g: u8, b: u8, is_background: bool) -> String {
     }
 }
 
+/// Color palette used when writing theme colors as ANSI escape sequences.
+///
+/// Themes define colors as hex RGB values. Most terminals render them as is, but
+/// constrained ones (and tools that reinterpret SGR codes) only understand the 16
+/// standard colors, so they can be quantized to the nearest one instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{ansi::{write_style_ansi_mode, ColorMode}, highlight::Style};
+///
+/// let style = Style {
+///     fg: Some("#ff5555".to_string()),
+///     ..Default::default()
+/// };
+///
+/// let mut output = Vec::new();
+/// write_style_ansi_mode(&mut output, &style, ColorMode::Ansi16).unwrap();
+/// assert_eq!(output, b"\x1b[91m");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorMode {
+    /// 24-bit RGB colors (`38;2;r;g;b`), as defined by the theme.
+    #[default]
+    TrueColor,
+    /// The nearest of the 16 standard ANSI colors (`30`-`37` and `90`-`97`).
+    Ansi16,
+}
+
+// xterm's default values for the 16 standard colors, used to find the nearest one.
+const ANSI16_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Find the nearest of the 16 standard ANSI colors to an RGB value.
+///
+/// Returns the color index: `0`-`7` for the normal colors (black, red, green, yellow,
+/// blue, magenta, cyan, white) and `8`-`15` for their bright variants.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::ansi::rgb_to_ansi16;
+///
+/// assert_eq!(rgb_to_ansi16(0, 0, 0), 0);
+/// assert_eq!(rgb_to_ansi16(255, 85, 85), 9);
+/// assert_eq!(rgb_to_ansi16(40, 42, 54), 0);
+/// ```
+pub fn rgb_to_ansi16(r: u8, g: u8, b: u8) -> u8 {
+    let distance = |&(pr, pg, pb): &(u8, u8, u8)| {
+        let dr = i32::from(r) - i32::from(pr);
+        let dg = i32::from(g) - i32::from(pg);
+        let db = i32::from(b) - i32::from(pb);
+        dr * dr + dg * dg + db * db
+    };
+
+    ANSI16_PALETTE
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, color)| distance(color))
+        .map(|(index, _)| index as u8)
+        .expect("palette is not empty")
+}
+
+// Write the SGR sequence setting the foreground or background to an RGB color.
+fn write_color(
+    output: &mut dyn Write,
+    (r, g, b): (u8, u8, u8),
+    is_background: bool,
+    mode: ColorMode,
+) -> io::Result<()> {
+    match mode {
+        ColorMode::TrueColor => {
+            let code = if is_background { 48 } else { 38 };
+            write!(output, "\u{1b}[{};2;{};{};{}m", code, r, g, b)
+        }
+        ColorMode::Ansi16 => {
+            let index = rgb_to_ansi16(r, g, b);
+            let base = match (is_background, index < 8) {
+                (false, true) => 30,
+                (false, false) => 90 - 8,
+                (true, true) => 40,
+                (true, false) => 100 - 8,
+            };
+            write!(output, "\u{1b}[{}m", base + index)
+        }
+    }
+}
+
 /// Convert a Style to ANSI escape sequences.
 ///
 /// Combines all style attributes (foreground, background, bold, italic, etc.)
@@ -125,43 +220,101 @@ pub fn rgb_to_ansi(r: u8, g: u8, b: u8, is_background: bool) -> String {
 /// let ansi = style_to_ansi(&style);
 /// ```
 pub fn style_to_ansi(style: &Style) -> String {
-    let mut codes = Vec::new();
+    let mut buf = Vec::new();
+    write_style_ansi(&mut buf, style).expect("writing to a Vec never fails");
+    String::from_utf8(buf).expect("ANSI escape sequences are valid UTF-8")
+}
 
-    if let Some(fg) = &style.fg {
-        if let Some((r, g, b)) = hex_to_rgb(fg) {
-            codes.push(rgb_to_ansi(r, g, b, false));
-        }
+/// Write the ANSI escape sequences for a Style directly to `output`.
+///
+/// Streaming counterpart of [`style_to_ansi`]: produces the same bytes without
+/// allocating intermediate strings, which suits callers redrawing a viewport
+/// many times per second.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{ansi::{style_to_ansi, write_style_ansi}, highlight::Style};
+///
+/// let style = Style {
+///     fg: Some("#ff79c6".to_string()),
+///     bold: true,
+///     ..Default::default()
+/// };
+///
+/// let mut output = Vec::new();
+/// write_style_ansi(&mut output, &style).unwrap();
+/// assert_eq!(output, style_to_ansi(&style).into_bytes());
+/// ```
+pub fn write_style_ansi(output: &mut dyn Write, style: &Style) -> io::Result<()> {
+    write_style_ansi_mode(output, style, ColorMode::TrueColor)
+}
+
+/// Write the ANSI escape sequences for a Style with colors in the given [`ColorMode`].
+///
+/// Same as [`write_style_ansi`] for [`ColorMode::TrueColor`].
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{ansi::{write_style_ansi_mode, ColorMode}, highlight::Style};
+///
+/// let style = Style {
+///     fg: Some("#ff5555".to_string()),
+///     bg: Some("#282a36".to_string()),
+///     ..Default::default()
+/// };
+///
+/// let mut output = Vec::new();
+/// write_style_ansi_mode(&mut output, &style, ColorMode::Ansi16).unwrap();
+/// assert_eq!(output, b"\x1b[91m\x1b[40m");
+/// ```
+pub fn write_style_ansi_mode(
+    output: &mut dyn Write,
+    style: &Style,
+    mode: ColorMode,
+) -> io::Result<()> {
+    if let Some(rgb) = style.fg.as_deref().and_then(hex_to_rgb) {
+        write_color(output, rgb, false, mode)?;
     }
 
-    if let Some(bg) = &style.bg {
-        if let Some((r, g, b)) = hex_to_rgb(bg) {
-            codes.push(rgb_to_ansi(r, g, b, true));
-        }
+    if let Some(rgb) = style.bg.as_deref().and_then(hex_to_rgb) {
+        write_color(output, rgb, true, mode)?;
     }
 
     if style.bold {
-        codes.push("\u{1b}[1m".to_string());
+        output.write_all(b"\x1b[1m")?;
     }
 
     if style.italic {
-        codes.push("\u{1b}[3m".to_string());
+        output.write_all(b"\x1b[3m")?;
     }
 
     use crate::themes::UnderlineStyle;
     match style.text_decoration.underline {
         UnderlineStyle::None => {}
-        UnderlineStyle::Solid => codes.push("\u{1b}[4m".to_string()),
-        UnderlineStyle::Wavy => codes.push("\u{1b}[4:3m".to_string()),
-        UnderlineStyle::Double => codes.push("\u{1b}[4:2m".to_string()),
-        UnderlineStyle::Dotted => codes.push("\u{1b}[4:4m".to_string()),
-        UnderlineStyle::Dashed => codes.push("\u{1b}[4:5m".to_string()),
+        UnderlineStyle::Solid => output.write_all(b"\x1b[4m")?,
+        UnderlineStyle::Wavy => output.write_all(b"\x1b[4:3m")?,
+        UnderlineStyle::Double => output.write_all(b"\x1b[4:2m")?,
+        UnderlineStyle::Dotted => output.write_all(b"\x1b[4:4m")?,
+        UnderlineStyle::Dashed => output.write_all(b"\x1b[4:5m")?,
     }
 
     if style.text_decoration.strikethrough {
-        codes.push("\u{1b}[9m".to_string());
+        output.write_all(b"\x1b[9m")?;
     }
 
-    codes.join("")
+    Ok(())
+}
+
+// Whether `write_style_ansi` would write anything for this style.
+fn has_ansi(style: &Style) -> bool {
+    style.fg.as_deref().and_then(hex_to_rgb).is_some()
+        || style.bg.as_deref().and_then(hex_to_rgb).is_some()
+        || style.bold
+        || style.italic
+        || style.text_decoration.underline != crate::themes::UnderlineStyle::None
+        || style.text_decoration.strikethrough
 }
 
 /// Wrap text with ANSI color codes based on a Style.
@@ -193,39 +346,353 @@ pub fn style_to_ansi(style: &Style) -> String {
 /// assert_eq!(wrapped, "\u{1b}[0m\u{1b}[38;2;139;233;253mfn\u{1b}[0m");
 /// ```
 pub fn wrap_with_ansi(text: &str, style: &Style) -> String {
-    let ansi_codes = style_to_ansi(style);
+    let mut buf = Vec::with_capacity(text.len() + 32);
+    write_with_ansi(&mut buf, text, style).expect("writing to a Vec never fails");
+    String::from_utf8(buf).expect("text and ANSI escape sequences are valid UTF-8")
+}
+
+/// Write text wrapped with ANSI color codes directly to `output`.
+///
+/// Streaming counterpart of [`wrap_with_ansi`]: produces the same bytes, including
+/// the resets around newlines for styles with a background color, without
+/// allocating per token. The [`Terminal`](crate::formatter::Terminal) formatter
+/// is built on it.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{ansi::write_with_ansi, highlight::Style};
+///
+/// let style = Style {
+///     fg: Some("#8be9fd".to_string()),
+///     ..Default::default()
+/// };
+///
+/// let mut output = Vec::new();
+/// write_with_ansi(&mut output, "fn", &style).unwrap();
+/// assert_eq!(output, b"\x1b[0m\x1b[38;2;139;233;253mfn\x1b[0m");
+/// ```
+pub fn write_with_ansi(output: &mut dyn Write, text: &str, style: &Style) -> io::Result<()> {
+    write_with_ansi_mode(output, text, style, ColorMode::TrueColor)
+}
+
+/// Write text wrapped with ANSI codes, with colors in the given [`ColorMode`].
+///
+/// Same as [`write_with_ansi`] for [`ColorMode::TrueColor`].
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{ansi::{write_with_ansi_mode, ColorMode}, highlight::Style};
+///
+/// let style = Style {
+///     fg: Some("#ff5555".to_string()),
+///     ..Default::default()
+/// };
+///
+/// let mut output = Vec::new();
+/// write_with_ansi_mode(&mut output, "fn", &style, ColorMode::Ansi16).unwrap();
+/// assert_eq!(output, b"\x1b[0m\x1b[91mfn\x1b[0m");
+/// ```
+pub fn write_with_ansi_mode(
+    output: &mut dyn Write,
+    text: &str,
+    style: &Style,
+    mode: ColorMode,
+) -> io::Result<()> {
+    if !has_ansi(style) {
+        return output.write_all(text.as_bytes());
+    }
 
-    if ansi_codes.is_empty() {
-        text.to_string()
-    } else if style.bg.is_some() {
+    output.write_all(ANSI_RESET.as_bytes())?;
+    write_style_ansi_mode(output, style, mode)?;
+
+    if style.bg.is_some() {
         // When there's a background color, we need to reset before newlines
         // to prevent the background from extending across the entire line width
-        let mut result = String::with_capacity(text.len() + ansi_codes.len() * 2 + 10);
-        result.push_str(ANSI_RESET);
-        result.push_str(&ansi_codes);
-
-        for (i, ch) in text.char_indices() {
-            if ch == '\n' {
-                // Reset before the newline, then output newline, then reapply style
-                result.push_str(ANSI_RESET);
-                result.push('\n');
-                // Only reapply style if there's more content after this newline
-                if i + 1 < text.len() {
-                    result.push_str(&ansi_codes);
-                }
-            } else {
-                result.push(ch);
+        let mut rest = text;
+        while let Some(newline) = rest.find('\n') {
+            output.write_all(&rest.as_bytes()[..newline])?;
+            output.write_all(ANSI_RESET.as_bytes())?;
+            output.write_all(b"\n")?;
+            rest = &rest[newline + 1..];
+            // Only reapply style if there's more content after this newline
+            if !rest.is_empty() {
+                write_style_ansi_mode(output, style, mode)?;
             }
         }
+        output.write_all(rest.as_bytes())?;
 
         // Final reset if text doesn't end with newline
         if !text.ends_with('\n') {
-            result.push_str(ANSI_RESET);
+            output.write_all(ANSI_RESET.as_bytes())?;
         }
 
-        result
+        Ok(())
+    } else {
+        output.write_all(text.as_bytes())?;
+        output.write_all(ANSI_RESET.as_bytes())
+    }
+}
+
+/// Number of terminal columns taken by `text`.
+///
+/// Wide characters such as CJK ideographs and emoji take two columns, while combining
+/// marks and control characters take none. The width tables cover the common ranges
+/// rather than the full Unicode database, which is enough to lay out source code.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::ansi::display_width;
+///
+/// assert_eq!(display_width("fn main"), 7);
+/// assert_eq!(display_width("日本"), 4);
+/// assert_eq!(display_width("e\u{301}"), 1);
+/// ```
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// Remove ANSI escape sequences from `input`, keeping only the text.
+///
+/// Strips CSI sequences such as the SGR colors and styles written by the
+/// [`Terminal`](crate::formatter::Terminal) formatter, OSC sequences such as
+/// hyperlinks (terminated by BEL or ST), and other escapes such as charset selection.
+/// Handy to compare terminal output in tests or to measure it with [`display_width`].
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::ansi::strip;
+///
+/// assert_eq!(strip("\u{1b}[0m\u{1b}[38;2;139;233;253mfn\u{1b}[0m main"), "fn main");
+/// assert_eq!(strip("\u{1b}]8;;https://lumis.sh\u{7}lumis\u{1b}]8;;\u{7}"), "lumis");
+/// ```
+pub fn strip(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            output.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI: parameters and intermediates until a final byte in `@..=~`
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: until BEL or ST (`ESC \`)
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.next_if_eq(&'\\').is_some() {
+                        break;
+                    }
+                }
+            }
+            // nF escapes such as `ESC ( B`: intermediates and a final byte
+            Some(' '..='/') => {
+                for c in chars.by_ref() {
+                    if !(' '..='/').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    output
+}
+
+// Zero-width code points: combining marks, zero-width spaces and joiners, direction
+// marks, variation selectors and the byte order mark.
+const ZERO_WIDTH: &[(u32, u32)] = &[
+    (0x0300, 0x036f),
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x0e31, 0x0e31),
+    (0x0e34, 0x0e3a),
+    (0x0e47, 0x0e4e),
+    (0x1ab0, 0x1aff),
+    (0x1dc0, 0x1dff),
+    (0x200b, 0x200f),
+    (0x202a, 0x202e),
+    (0x2060, 0x2064),
+    (0x20d0, 0x20ff),
+    (0x302a, 0x302d),
+    (0x3099, 0x309a),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0xfeff, 0xfeff),
+    (0xe0100, 0xe01ef),
+];
+
+// East Asian wide and fullwidth code points, and emoji presented as wide.
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115f),
+    (0x231a, 0x231b),
+    (0x2329, 0x232a),
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267f, 0x267f),
+    (0x2693, 0x2693),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f3),
+    (0x26f5, 0x26f5),
+    (0x26fa, 0x26fa),
+    (0x26fd, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274c),
+    (0x274e, 0x274e),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27b0),
+    (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b50),
+    (0x2b55, 0x2b55),
+    (0x2e80, 0x303e),
+    (0x3041, 0x3247),
+    (0x3250, 0x4dbf),
+    (0x4e00, 0xa4cf),
+    (0xa960, 0xa97f),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe10, 0xfe19),
+    (0xfe30, 0xfe6f),
+    (0xff00, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x16fe0, 0x18cff),
+    (0x1b000, 0x1b2ff),
+    (0x1f004, 0x1f004),
+    (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e),
+    (0x1f191, 0x1f19a),
+    (0x1f200, 0x1f251),
+    (0x1f300, 0x1f320),
+    (0x1f32d, 0x1f335),
+    (0x1f337, 0x1f37c),
+    (0x1f37e, 0x1f393),
+    (0x1f3a0, 0x1f3ca),
+    (0x1f3cf, 0x1f3d3),
+    (0x1f3e0, 0x1f3f0),
+    (0x1f3f4, 0x1f3f4),
+    (0x1f3f8, 0x1f43e),
+    (0x1f440, 0x1f440),
+    (0x1f442, 0x1f4fc),
+    (0x1f4ff, 0x1f53d),
+    (0x1f54b, 0x1f54e),
+    (0x1f550, 0x1f567),
+    (0x1f57a, 0x1f57a),
+    (0x1f595, 0x1f596),
+    (0x1f5a4, 0x1f5a4),
+    (0x1f5fb, 0x1f64f),
+    (0x1f680, 0x1f6c5),
+    (0x1f6cc, 0x1f6cc),
+    (0x1f6d0, 0x1f6d2),
+    (0x1f6d5, 0x1f6d7),
+    (0x1f6dc, 0x1f6df),
+    (0x1f6eb, 0x1f6ec),
+    (0x1f6f4, 0x1f6fc),
+    (0x1f7e0, 0x1f7eb),
+    (0x1f7f0, 0x1f7f0),
+    (0x1f90c, 0x1f93a),
+    (0x1f93c, 0x1f945),
+    (0x1f947, 0x1f9ff),
+    (0x1fa70, 0x1faff),
+    (0x20000, 0x2fffd),
+    (0x30000, 0x3fffd),
+];
+
+// Cells taken by `c` written at `column`: a tab advances to the next multiple of
+// `tab_width`, or takes none when it is 0. Used by every formatter laying out columns
+// so terminal wrapping, HTML tab expansion and SVG sizing agree.
+pub(crate) fn cell_width(c: char, column: usize, tab_width: usize) -> usize {
+    match c {
+        '\t' if tab_width == 0 => 0,
+        '\t' => tab_width - column % tab_width,
+        _ => char_width(c),
+    }
+}
+
+// Width of a character in monospace cells, tabs excluded.
+pub(crate) fn char_width(c: char) -> usize {
+    let code = c as u32;
+    let in_table = |table: &[(u32, u32)]| {
+        table
+            .binary_search_by(|&(start, end)| {
+                if end < code {
+                    std::cmp::Ordering::Less
+                } else if start > code {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    };
+
+    if c.is_control() || in_table(ZERO_WIDTH) {
+        0
+    } else if in_table(WIDE) {
+        2
     } else {
-        format!("{}{}{}{}", ANSI_RESET, ansi_codes, text, ANSI_RESET)
+        1
+    }
+}
+
+/// Guess whether the terminal has a light or dark background.
+///
+/// Reads the `COLORFGBG` environment variable set by terminals such as rxvt, Konsole
+/// and iTerm2, formatted `fg;bg` with 16-color palette indices. Backgrounds 7 (light
+/// gray) and 9 to 15 are light, the other standard colors are dark. Returns `None`
+/// when the variable is unset or doesn't name a standard color.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::ansi::terminal_appearance;
+/// use lumis::themes::Appearance;
+///
+/// let appearance = terminal_appearance().unwrap_or(Appearance::Dark);
+/// ```
+pub fn terminal_appearance() -> Option<Appearance> {
+    std::env::var("COLORFGBG")
+        .ok()
+        .and_then(|value| appearance_from_colorfgbg(&value))
+}
+
+// The background is the last field, some terminals add a middle one like `15;default;0`.
+fn appearance_from_colorfgbg(value: &str) -> Option<Appearance> {
+    match value.rsplit(';').next()?.trim().parse::<u8>().ok()? {
+        0..=6 | 8 => Some(Appearance::Dark),
+        7 | 9..=15 => Some(Appearance::Light),
+        _ => None,
     }
 }
 
@@ -385,6 +852,78 @@ mod tests {
         assert!(result.ends_with("\u{1b}[0m"));
     }
 
+    #[test]
+    fn test_write_with_ansi_background_newlines() {
+        let style = Style {
+            fg: Some("#8be9fd".to_string()),
+            bg: Some("#282a36".to_string()),
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        write_with_ansi(&mut output, "a\nb\n", &style).unwrap();
+
+        let codes = "\u{1b}[38;2;139;233;253m\u{1b}[48;2;40;42;54m";
+        let expected = format!("{ANSI_RESET}{codes}a{ANSI_RESET}\n{codes}b{ANSI_RESET}\n");
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+        assert_eq!(wrap_with_ansi("a\nb\n", &style), expected);
+    }
+
+    #[test]
+    fn test_rgb_to_ansi16() {
+        assert_eq!(rgb_to_ansi16(0, 0, 0), 0);
+        assert_eq!(rgb_to_ansi16(200, 10, 10), 1);
+        assert_eq!(rgb_to_ansi16(10, 250, 10), 10);
+        assert_eq!(rgb_to_ansi16(98, 114, 164), 8);
+        assert_eq!(rgb_to_ansi16(248, 248, 242), 15);
+    }
+
+    #[test]
+    fn test_write_style_ansi_mode_ansi16() {
+        let style = Style {
+            fg: Some("#cd0000".to_string()),
+            bg: Some("#ffffff".to_string()),
+            bold: true,
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        write_style_ansi_mode(&mut output, &style, ColorMode::Ansi16).unwrap();
+        assert_eq!(output, b"\x1b[31m\x1b[107m\x1b[1m");
+    }
+
+    #[test]
+    fn test_strip() {
+        let style = Style {
+            fg: Some("#ff5555".to_string()),
+            bold: true,
+            ..Default::default()
+        };
+
+        assert_eq!(strip(&wrap_with_ansi("let x", &style)), "let x");
+        assert_eq!(
+            strip("a\u{1b}]8;;https://x\u{1b}\\b\u{1b}]8;;\u{1b}\\c"),
+            "abc"
+        );
+        assert_eq!(strip("\u{1b}(Bx\u{1b}[2K"), "x");
+        assert_eq!(strip("日本\u{1b}[0m"), "日本");
+        assert_eq!(strip("\u{1b}["), "");
+    }
+
+    #[test]
+    fn test_appearance_from_colorfgbg() {
+        assert_eq!(appearance_from_colorfgbg("15;0"), Some(Appearance::Dark));
+        assert_eq!(appearance_from_colorfgbg("0;15"), Some(Appearance::Light));
+        assert_eq!(
+            appearance_from_colorfgbg("0;default;7"),
+            Some(Appearance::Light)
+        );
+        assert_eq!(appearance_from_colorfgbg("7;8"), Some(Appearance::Dark));
+        assert_eq!(appearance_from_colorfgbg("15;default"), None);
+        assert_eq!(appearance_from_colorfgbg("0;16"), None);
+        assert_eq!(appearance_from_colorfgbg(""), None);
+    }
+
     #[test]
     fn test_wrap_with_ansi_empty_style() {
         let style = Style::default();
@@ -29,9 +29,17 @@
 //! - [`Formatter`](crate::formatter::Formatter) trait documentation
 //! - [`examples/custom_html_formatter.rs`](https://github.com/leandrocp/lumis/blob/main/examples/custom_html_formatter.rs)
 
+use super::{
+    ContainerSpec, CopyButtonSpec, EscapeStrategy, FigureSpec, GutterCallback, HtmlElement,
+    InjectionResolver, LanguageClassMap, LineCallback, LineClassFn, TabMode, WhitespaceMode,
+};
 use crate::languages::Language;
 use crate::themes::Theme;
+use crate::vendor::tree_sitter_highlight::{Highlighter, HtmlRenderer};
+use std::borrow::Cow;
 use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Generate an HTML `<span>` element with inline CSS styles.
 ///
@@ -94,6 +102,20 @@ pub fn span_inline_attrs(
     theme: Option<&Theme>,
     italic: bool,
     include_highlights: bool,
+) -> String {
+    span_inline_attrs_on(scope, language, theme, italic, include_highlights, None)
+}
+
+// Like `span_inline_attrs`, drawing the token in black or white when its color doesn't
+// reach the WCAG AA contrast ratio of 4.5 on the line background `bg`. Tokens with
+// their own background keep their colors.
+pub(crate) fn span_inline_attrs_on(
+    scope: &str,
+    language: Option<Language>,
+    theme: Option<&Theme>,
+    italic: bool,
+    include_highlights: bool,
+    bg: Option<&str>,
 ) -> String {
     let mut attrs = String::new();
 
@@ -109,6 +131,18 @@ pub fn span_inline_attrs(
         };
 
         if let Some(style) = theme.get_style(&specialized_scope) {
+            let readable = bg.filter(|_| style.bg.is_none()).and_then(|bg| {
+                let ratio = crate::themes::contrast_ratio(style.fg.as_deref()?, bg)?;
+                contrast_color(bg).filter(|_| ratio < 4.5)
+            });
+            let style = match readable {
+                Some(fg) => Cow::Owned(crate::themes::Style {
+                    fg: Some(fg.to_string()),
+                    ..style.clone()
+                }),
+                None => Cow::Borrowed(style),
+            };
+
             let has_decoration = style.text_decoration.underline != UnderlineStyle::None
                 || style.text_decoration.strikethrough;
             if include_highlights
@@ -173,6 +207,51 @@ pub fn span_linked_attrs(scope: &str) -> String {
     format!("class=\"{}\"", class)
 }
 
+/// Generate HTML attributes for a span colored by CSS variables.
+///
+/// The color points at the variable named after the scope, with dots replaced by
+/// dashes, and falls back to the variables of each parent scope.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// let attrs = html::span_vars_attrs("keyword.function", "--");
+/// assert_eq!(attrs, r#"style="color: var(--keyword-function, var(--keyword));""#);
+/// ```
+pub fn span_vars_attrs(scope: &str, prefix: &str) -> String {
+    let mut color = String::new();
+    let mut depth = 0;
+
+    for (end, _) in scope.match_indices('.').chain([(scope.len(), "")]).rev() {
+        if depth > 0 {
+            color.push_str(", ");
+        }
+        color.push_str(&format!("var({prefix}{}", scope[..end].replace('.', "-")));
+        depth += 1;
+    }
+    color.push_str(&")".repeat(depth));
+
+    format!("style=\"color: {color};\"")
+}
+
+/// Generate a `title` attribute containing the scope name.
+///
+/// Browsers display the `title` as a native tooltip when hovering the span,
+/// which is handy for inspecting which scope a token resolved to.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// assert_eq!(html::scope_title_attr("keyword.function"), r#"title="keyword.function""#);
+/// ```
+pub fn scope_title_attr(scope: &str) -> String {
+    format!("title=\"{}\"", scope)
+}
+
 /// Sanitize a theme name for use in CSS variable names.
 ///
 /// Converts non-alphanumeric characters (except `-` and `_`) to `-`.
@@ -264,8 +343,37 @@ pub fn span_multi_themes_attrs(
     italic: bool,
     include_highlights: bool,
 ) -> String {
+    let mut buffer = Vec::new();
+    write_span_multi_themes_attrs(
+        &mut buffer,
+        scope,
+        language,
+        themes,
+        default_theme,
+        css_variable_prefix,
+        italic,
+        include_highlights,
+    )
+    .expect("writing to a Vec never fails");
+    String::from_utf8(buffer).expect("attributes are built from UTF-8 strings")
+}
+
+// Streaming version of `span_multi_themes_attrs`: style fragments are written
+// straight into `output` instead of being collected and joined, which avoids a
+// handful of allocations per token. Writes nothing if no theme styles the scope.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_span_multi_themes_attrs(
+    output: &mut Vec<u8>,
+    scope: &str,
+    language: Option<Language>,
+    themes: &std::collections::HashMap<String, Theme>,
+    default_theme: Option<&str>,
+    css_variable_prefix: &str,
+    italic: bool,
+    include_highlights: bool,
+) -> io::Result<()> {
     if themes.is_empty() {
-        return String::new();
+        return Ok(());
     }
 
     let specialized_scope = if let Some(lang) = language {
@@ -274,8 +382,22 @@ pub fn span_multi_themes_attrs(
         scope.to_string()
     };
 
-    let mut inline_styles = Vec::new();
-    let mut css_vars = Vec::new();
+    let start = output.len();
+    if include_highlights {
+        write!(output, "data-highlight=\"{}\" ", scope)?;
+    }
+    output.extend_from_slice(b"style=\"");
+    let content_start = output.len();
+
+    // Separate each style fragment from the previous one with a single space.
+    macro_rules! fragment {
+        ($($arg:tt)*) => {
+            if output.len() > content_start {
+                output.push(b' ');
+            }
+            write!(output, $($arg)*)?;
+        };
+    }
 
     if let Some(default_name) = default_theme {
         if default_name == "light-dark()" {
@@ -286,21 +408,18 @@ pub fn span_multi_themes_attrs(
                     dark_theme.get_style(&specialized_scope),
                 ) {
                     if let (Some(light_fg), Some(dark_fg)) = (&light_style.fg, &dark_style.fg) {
-                        inline_styles
-                            .push(format!("color: light-dark({}, {});", light_fg, dark_fg));
+                        fragment!("color: light-dark({}, {});", light_fg, dark_fg);
                     }
                     if let (Some(light_bg), Some(dark_bg)) = (&light_style.bg, &dark_style.bg) {
-                        inline_styles.push(format!(
-                            "background-color: light-dark({}, {});",
-                            light_bg, dark_bg
-                        ));
+                        fragment!("background-color: light-dark({}, {});", light_bg, dark_bg);
                     }
                     let light_weight = if light_style.bold { "bold" } else { "normal" };
                     let dark_weight = if dark_style.bold { "bold" } else { "normal" };
-                    inline_styles.push(format!(
+                    fragment!(
                         "font-weight: light-dark({}, {});",
-                        light_weight, dark_weight
-                    ));
+                        light_weight,
+                        dark_weight
+                    );
                     if italic {
                         let light_style_val = if light_style.italic {
                             "italic"
@@ -312,56 +431,64 @@ pub fn span_multi_themes_attrs(
                         } else {
                             "normal"
                         };
-                        inline_styles.push(format!(
+                        fragment!(
                             "font-style: light-dark({}, {});",
-                            light_style_val, dark_style_val
-                        ));
+                            light_style_val,
+                            dark_style_val
+                        );
                     }
                     let light_decoration = text_decoration(&light_style.text_decoration);
                     let dark_decoration = text_decoration(&dark_style.text_decoration);
-                    inline_styles.push(format!(
+                    fragment!(
                         "text-decoration: light-dark({}, {});",
-                        light_decoration, dark_decoration
-                    ));
+                        light_decoration,
+                        dark_decoration
+                    );
                 }
             }
         } else if let Some(default_theme_obj) = themes.get(default_name) {
             if let Some(style) = default_theme_obj.get_style(&specialized_scope) {
                 if let Some(fg) = &style.fg {
-                    inline_styles.push(format!("color:{};", fg));
+                    fragment!("color:{};", fg);
                 }
                 if let Some(bg) = &style.bg {
-                    inline_styles.push(format!("background-color:{};", bg));
+                    fragment!("background-color:{};", bg);
                 }
                 if style.bold {
-                    inline_styles.push("font-weight:bold;".to_string());
+                    fragment!("font-weight:bold;");
                 }
                 if italic && style.italic {
-                    inline_styles.push("font-style:italic;".to_string());
+                    fragment!("font-style:italic;");
                 }
                 let td_css = text_decoration(&style.text_decoration);
                 if td_css != "none" {
-                    inline_styles.push(format!("text-decoration:{};", td_css));
+                    fragment!("text-decoration:{};", td_css);
                 }
 
                 let sanitized = sanitize_theme_name(default_name);
                 let font_style = if style.italic { "italic" } else { "normal" };
-                css_vars.push(format!(
+                fragment!(
                     "{}-{}-font-style:{};",
-                    css_variable_prefix, sanitized, font_style
-                ));
+                    css_variable_prefix,
+                    sanitized,
+                    font_style
+                );
 
                 let font_weight = if style.bold { "bold" } else { "normal" };
-                css_vars.push(format!(
+                fragment!(
                     "{}-{}-font-weight:{};",
-                    css_variable_prefix, sanitized, font_weight
-                ));
+                    css_variable_prefix,
+                    sanitized,
+                    font_weight
+                );
 
                 let text_dec = text_decoration(&style.text_decoration);
-                css_vars.push(format!(
+                fragment!(
                     "{}-{}-text-decoration:{};",
-                    css_variable_prefix, sanitized, text_dec
-                ));
+                    css_variable_prefix,
+                    sanitized,
+                    text_dec
+                );
             }
 
             for (theme_name, theme) in themes.iter() {
@@ -370,30 +497,35 @@ pub fn span_multi_themes_attrs(
                         let sanitized = sanitize_theme_name(theme_name);
 
                         if let Some(fg) = &style.fg {
-                            css_vars.push(format!("{}-{}:{};", css_variable_prefix, sanitized, fg));
+                            fragment!("{}-{}:{};", css_variable_prefix, sanitized, fg);
                         }
                         if let Some(bg) = &style.bg {
-                            css_vars
-                                .push(format!("{}-{}-bg:{};", css_variable_prefix, sanitized, bg));
+                            fragment!("{}-{}-bg:{};", css_variable_prefix, sanitized, bg);
                         }
 
                         let font_style = if style.italic { "italic" } else { "normal" };
-                        css_vars.push(format!(
+                        fragment!(
                             "{}-{}-font-style:{};",
-                            css_variable_prefix, sanitized, font_style
-                        ));
+                            css_variable_prefix,
+                            sanitized,
+                            font_style
+                        );
 
                         let font_weight = if style.bold { "bold" } else { "normal" };
-                        css_vars.push(format!(
+                        fragment!(
                             "{}-{}-font-weight:{};",
-                            css_variable_prefix, sanitized, font_weight
-                        ));
+                            css_variable_prefix,
+                            sanitized,
+                            font_weight
+                        );
 
                         let text_dec = text_decoration(&style.text_decoration);
-                        css_vars.push(format!(
+                        fragment!(
                             "{}-{}-text-decoration:{};",
-                            css_variable_prefix, sanitized, text_dec
-                        ));
+                            css_variable_prefix,
+                            sanitized,
+                            text_dec
+                        );
                     }
                 }
             }
@@ -404,55 +536,46 @@ pub fn span_multi_themes_attrs(
                 let sanitized = sanitize_theme_name(theme_name);
 
                 if let Some(fg) = &style.fg {
-                    css_vars.push(format!("{}-{}: {};", css_variable_prefix, sanitized, fg));
+                    fragment!("{}-{}: {};", css_variable_prefix, sanitized, fg);
                 }
                 if let Some(bg) = &style.bg {
-                    css_vars.push(format!("{}-{}-bg: {};", css_variable_prefix, sanitized, bg));
+                    fragment!("{}-{}-bg: {};", css_variable_prefix, sanitized, bg);
                 }
 
                 let font_style = if style.italic { "italic" } else { "normal" };
-                css_vars.push(format!(
+                fragment!(
                     "{}-{}-font-style: {};",
-                    css_variable_prefix, sanitized, font_style
-                ));
+                    css_variable_prefix,
+                    sanitized,
+                    font_style
+                );
 
                 let font_weight = if style.bold { "bold" } else { "normal" };
-                css_vars.push(format!(
+                fragment!(
                     "{}-{}-font-weight: {};",
-                    css_variable_prefix, sanitized, font_weight
-                ));
+                    css_variable_prefix,
+                    sanitized,
+                    font_weight
+                );
 
                 let text_dec = text_decoration(&style.text_decoration);
-                css_vars.push(format!(
+                fragment!(
                     "{}-{}-text-decoration: {};",
-                    css_variable_prefix, sanitized, text_dec
-                ));
+                    css_variable_prefix,
+                    sanitized,
+                    text_dec
+                );
             }
         }
     }
 
-    if inline_styles.is_empty() && css_vars.is_empty() {
-        return String::new();
-    }
-
-    let mut attrs = String::new();
-    if include_highlights {
-        attrs.push_str(&format!("data-highlight=\"{}\" ", scope));
-    }
-
-    attrs.push_str("style=\"");
-    if !inline_styles.is_empty() {
-        attrs.push_str(&inline_styles.join(" "));
-    }
-    if !css_vars.is_empty() {
-        if !inline_styles.is_empty() {
-            attrs.push(' ');
-        }
-        attrs.push_str(&css_vars.join(" "));
+    if output.len() == content_start {
+        output.truncate(start);
+    } else {
+        output.push(b'"');
     }
-    attrs.push('"');
 
-    attrs
+    Ok(())
 }
 
 /// Generate an HTML `<span>` element with CSS variables for multiple themes.
@@ -551,30 +674,99 @@ pub fn span_multi_themes(
 /// assert_eq!(html::escape("{code}"), "&lbrace;code&rbrace;");
 /// ```
 pub fn escape(text: &str) -> String {
+    escape_with(text, EscapeStrategy::Named)
+}
+
+/// Escape text for safe HTML output with the given [`EscapeStrategy`].
+///
+/// Braces are escaped as with [`escape`], except with [`EscapeStrategy::Minimal`],
+/// in the entity form of the strategy.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{formatter::EscapeStrategy, html};
+///
+/// assert_eq!(html::escape_with("\"a\" < b", EscapeStrategy::Minimal), "\"a\" &lt; b");
+/// assert_eq!(html::escape_with("<{x}>", EscapeStrategy::Numeric), "&#60;&#123;x&#125;&#62;");
+/// assert_eq!(html::escape_with("é & ü", EscapeStrategy::NonAscii), "&#233; &#38; &#252;");
+/// ```
+pub fn escape_with(text: &str, strategy: EscapeStrategy) -> String {
     let mut buf = String::with_capacity(text.len() + text.len() / 10);
 
     for c in text.chars() {
-        match c {
-            '&' => buf.push_str("&amp;"),
-            '<' => buf.push_str("&lt;"),
-            '>' => buf.push_str("&gt;"),
-            '"' => buf.push_str("&quot;"),
-            '\'' => buf.push_str("&#39;"),
-            '{' => buf.push_str("&lbrace;"),
-            '}' => buf.push_str("&rbrace;"),
-            _ => buf.push(c),
+        match entity(c, strategy) {
+            Some(entity) => buf.push_str(&entity),
+            None => buf.push(c),
         }
     }
 
     buf
 }
 
+/// Entity for `c` escaped with `strategy`, or `None` if `c` is kept as is.
+fn entity(c: char, strategy: EscapeStrategy) -> Option<Cow<'static, str>> {
+    let named = match c {
+        '&' => "&amp;",
+        '<' => "&lt;",
+        '>' => "&gt;",
+        '"' => "&quot;",
+        '\'' => "&#39;",
+        '{' => "&lbrace;",
+        '}' => "&rbrace;",
+        _ if strategy == EscapeStrategy::NonAscii && !c.is_ascii() => {
+            return Some(Cow::Owned(format!("&#{};", c as u32)));
+        }
+        _ => return None,
+    };
+
+    match strategy {
+        EscapeStrategy::Minimal if !matches!(c, '&' | '<' | '>') => None,
+        EscapeStrategy::Minimal | EscapeStrategy::Named => Some(Cow::Borrowed(named)),
+        EscapeStrategy::Numeric | EscapeStrategy::NonAscii => {
+            Some(Cow::Owned(format!("&#{};", c as u32)))
+        }
+    }
+}
+
+/// Entity for a character of highlighted source, see [`HtmlRenderer::set_escape`].
+///
+/// Braces are left to [`escape_braces_with`], which the formatters only apply with
+/// the `escape_braces` option.
+///
+/// [`HtmlRenderer::set_escape`]: crate::vendor::tree_sitter_highlight::HtmlRenderer::set_escape
+pub(crate) fn escape_source_char(c: char, strategy: EscapeStrategy) -> Option<Cow<'static, str>> {
+    if matches!(c, '{' | '}') {
+        None
+    } else {
+        entity(c, strategy)
+    }
+}
+
+// Escape the source text of `renderer` with `strategy`. The renderer's own escaping
+// already matches [`EscapeStrategy::Named`], so it's only replaced for the others.
+pub(crate) fn set_escape_strategy(renderer: &mut HtmlRenderer, strategy: EscapeStrategy) {
+    if strategy != EscapeStrategy::Named {
+        renderer.set_escape(move |c| escape_source_char(c, strategy));
+    }
+}
+
+// A non-breaking space in the entity form of `strategy`.
+pub(crate) fn nbsp(strategy: EscapeStrategy) -> &'static str {
+    match strategy {
+        EscapeStrategy::Numeric | EscapeStrategy::NonAscii => "&#160;",
+        EscapeStrategy::Minimal | EscapeStrategy::Named => "&nbsp;",
+    }
+}
+
 /// Escape braces for framework compatibility.
 ///
 /// Replaces `{` with `&lbrace;` and `}` with `&rbrace;`. This is useful
 /// when rendering code inside template systems that use braces for interpolation
 /// (like Handlebars, Liquid, Jinja, Phoenix templates, etc.).
 ///
+/// The HTML formatters apply it to every line unless built with `escape_braces(false)`.
+///
 /// # Example
 ///
 /// ```rust
@@ -586,6 +778,217 @@ pub fn escape_braces(text: &str) -> String {
     text.replace('{', "&lbrace;").replace('}', "&rbrace;")
 }
 
+/// Escape braces in the entity form of the given [`EscapeStrategy`].
+///
+/// [`EscapeStrategy::Numeric`] and [`EscapeStrategy::NonAscii`] write `&#123;` and
+/// `&#125;`, the others behave like [`escape_braces`].
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{formatter::EscapeStrategy, html};
+///
+/// assert_eq!(html::escape_braces_with("{ }", EscapeStrategy::Numeric), "&#123; &#125;");
+/// assert_eq!(html::escape_braces_with("{ }", EscapeStrategy::Minimal), "&lbrace; &rbrace;");
+/// ```
+pub fn escape_braces_with(text: &str, strategy: EscapeStrategy) -> String {
+    match strategy {
+        EscapeStrategy::Numeric | EscapeStrategy::NonAscii => {
+            text.replace('{', "&#123;").replace('}', "&#125;")
+        }
+        EscapeStrategy::Minimal | EscapeStrategy::Named => escape_braces(text),
+    }
+}
+
+/// Insert an empty `<span></span>` into a line without content.
+///
+/// A line is empty when it holds nothing but its trailing newline; other lines are
+/// returned unchanged. With the marker every line contains an element, which keeps
+/// CSS grid layouts aligned and lets `:empty` selectors target the marker.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// assert_eq!(html::mark_empty_line("\n"), "<span></span>\n");
+/// assert_eq!(html::mark_empty_line("x = 1\n"), "x = 1\n");
+/// ```
+pub fn mark_empty_line(content: &str) -> String {
+    if content.trim_end_matches(['\r', '\n']).is_empty() {
+        format!("<span></span>{content}")
+    } else {
+        content.to_string()
+    }
+}
+
+/// Expand the tabs of a line into spaces, see [`TabMode`].
+///
+/// Only text is changed, tags are left alone, so the line may already hold highlighted
+/// spans. Columns are counted as displayed: an entity like `&amp;` takes one column and
+/// wide characters take two. A width of 0 removes the tabs.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{formatter::TabMode, html};
+///
+/// assert_eq!(html::expand_tabs("a\tb\n", TabMode::Spaces(4)), "a    b\n");
+/// assert_eq!(html::expand_tabs("a\tb\n", TabMode::Stops(4)), "a   b\n");
+/// assert_eq!(
+///     html::expand_tabs("<span class=\"a\">&lt;ab</span>\t1", TabMode::Stops(4)),
+///     "<span class=\"a\">&lt;ab</span> 1"
+/// );
+/// ```
+pub fn expand_tabs(line: &str, mode: TabMode) -> String {
+    let width = match mode {
+        TabMode::None => return line.to_string(),
+        TabMode::Spaces(width) | TabMode::Stops(width) => width,
+    };
+
+    let mut result = String::with_capacity(line.len());
+    let mut column = 0;
+    let mut in_tag = false;
+    let mut in_entity = false;
+    for c in line.chars() {
+        match c {
+            '<' if !in_tag => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if in_tag => (),
+            '&' => {
+                in_entity = true;
+                column += 1;
+            }
+            ';' if in_entity => in_entity = false,
+            _ if in_entity => (),
+            '\t' => {
+                let spaces = match mode {
+                    TabMode::Stops(width) => super::ansi::cell_width(c, column, width),
+                    _ => width,
+                };
+                result.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+                continue;
+            }
+            '\n' => column = 0,
+            _ => column += super::ansi::cell_width(c, column, width),
+        }
+
+        result.push(c);
+    }
+
+    result
+}
+
+/// Render the whitespace of a line visibly, see [`WhitespaceMode`].
+///
+/// Spaces become `·` and tabs `→`, with each run wrapped in a `<span class="whitespace">`.
+/// Only text is changed, whitespace inside tags is left alone, so the line may already
+/// hold highlighted spans. With [`WhitespaceMode::Boundary`] only the whitespace before
+/// the first and after the last visible character is rendered.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{formatter::WhitespaceMode, html};
+///
+/// assert_eq!(
+///     html::render_whitespace("\tx = 1 \n", WhitespaceMode::Boundary),
+///     "<span class=\"whitespace\">→</span>x = 1<span class=\"whitespace\">·</span>\n"
+/// );
+/// assert_eq!(
+///     html::render_whitespace("<span class=\"a\">x</span> 1", WhitespaceMode::All),
+///     "<span class=\"a\">x</span><span class=\"whitespace\">·</span>1"
+/// );
+/// ```
+pub fn render_whitespace(line: &str, mode: WhitespaceMode) -> String {
+    if mode == WhitespaceMode::None {
+        return line.to_string();
+    }
+
+    let is_blank = |c: char| matches!(c, ' ' | '\t' | '\r' | '\n');
+
+    // Byte range of the visible text, from its first to its last non-blank character.
+    let mut visible: Option<(usize, usize)> = None;
+    let mut in_tag = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if in_tag || is_blank(c) => (),
+            _ => {
+                let start = visible.map_or(i, |(start, _)| start);
+                visible = Some((start, i));
+            }
+        }
+    }
+
+    let mut result = String::with_capacity(line.len());
+    let mut in_tag = false;
+    let mut in_run = false;
+    for (i, c) in line.char_indices() {
+        let marker = match c {
+            ' ' => Some('·'),
+            '\t' => Some('→'),
+            _ => None,
+        };
+        let render = !in_tag
+            && marker.is_some()
+            && match (mode, visible) {
+                (WhitespaceMode::Boundary, Some((start, end))) => i < start || i > end,
+                _ => true,
+            };
+
+        if render != in_run {
+            result.push_str(if render {
+                "<span class=\"whitespace\">"
+            } else {
+                "</span>"
+            });
+            in_run = render;
+        }
+
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ => (),
+        }
+
+        result.push(if render { marker.unwrap_or(c) } else { c });
+    }
+
+    if in_run {
+        result.push_str("</span>");
+    }
+
+    result
+}
+
+/// Drop the last rendered line if it's empty.
+///
+/// The last line of a source ending in a blank line, such as `"a\n\n"`, holds only the
+/// final newline. Removing it keeps renderers from showing an empty line at the end of
+/// the block. A single line is always kept.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// let mut lines = vec!["a\n", "\n"];
+/// html::trim_final_empty_line(&mut lines);
+/// assert_eq!(lines, vec!["a\n"]);
+/// ```
+pub fn trim_final_empty_line(lines: &mut Vec<&str>) {
+    if lines.len() > 1
+        && lines
+            .last()
+            .is_some_and(|line| line.trim_end_matches(['\r', '\n']).is_empty())
+    {
+        lines.pop();
+    }
+}
+
 /// Wrap content in a line div with optional class and style attributes.
 ///
 /// Creates a `<div class="line..." data-line="N">content</div>` element
@@ -637,94 +1040,1074 @@ pub fn wrap_line(
     )
 }
 
-/// Map tree-sitter scope to CSS class name.
+/// Surround the content of a line with a gutter and raw prefix and suffix HTML.
 ///
-/// Converts scope names to their corresponding CSS class names using the
-/// CLASSES constant. This maintains the full scope hierarchy specificity.
+/// `prefix` and `suffix` are called with the 1-based line number and their output is
+/// inserted as-is, without escaping. The suffix goes before the line's trailing newline
+/// so it stays on the same visual line.
+///
+/// When `gutter` is set, its marker is rendered first in a `<span class="gutter">`,
+/// with a space for lines without a marker so the column stays aligned.
 ///
 /// # Example
 ///
 /// ```rust
-/// use lumis::html;
+/// use lumis::{formatter::LineCallback, html};
 ///
-/// assert_eq!(html::scope_to_class("string"), "string");
-/// assert_eq!(html::scope_to_class("function.method.call"), "function-method-call");
+/// let marker = LineCallback::new(|n| format!("<span class=\"ln\">{n}</span>"));
+/// let copy = LineCallback::new(|_| "<a>copy</a>".to_string());
+///
+/// assert_eq!(
+///     html::decorate_line(3, "let x = 1;\n", Some(|_| Some('+')), Some(&marker), Some(&copy)),
+///     "<span class=\"gutter\">+</span><span class=\"ln\">3</span>let x = 1;<a>copy</a>\n"
+/// );
 /// ```
-pub fn scope_to_class(scope: &str) -> &str {
-    crate::constants::HIGHLIGHT_NAMES
-        .iter()
-        .position(|&s| s == scope)
-        .and_then(|idx| crate::constants::CLASSES.get(idx))
-        .copied()
-        .unwrap_or("text")
+pub fn decorate_line(
+    line_number: usize,
+    content: &str,
+    gutter: Option<GutterCallback>,
+    prefix: Option<&LineCallback>,
+    suffix: Option<&LineCallback>,
+) -> String {
+    let (body, newline) = match content.strip_suffix('\n') {
+        Some(body) => (body, "\n"),
+        None => (content, ""),
+    };
+
+    let gutter = gutter
+        .map(|f| {
+            let marker = f(line_number).unwrap_or(' ');
+            format!(
+                "<span class=\"gutter\">{}</span>",
+                escape(marker.encode_utf8(&mut [0; 4]))
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        "{}{}{}{}{}",
+        gutter,
+        prefix.map(|f| f.html(line_number)).unwrap_or_default(),
+        body,
+        suffix.map(|f| f.html(line_number)).unwrap_or_default(),
+        newline
+    )
 }
 
-/// Generate an opening `<pre>` tag with optional class and theme styles.
+/// Fold markers for [`wrap_fold`], precomputed per line from fold ranges.
 ///
-/// Creates the opening `<pre>` tag with the base "lumis" class, an optional custom class,
-/// and optional theme styling for background and foreground colors.
+/// `ranges` must be properly nested, 1-based line ranges such as those returned by
+/// [`Language::fold_ranges`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Folds {
+    // Whether a fold starts on the line, and how many folds end on it.
+    lines: Vec<(bool, usize)>,
+}
+
+impl Folds {
+    pub fn new(ranges: &[RangeInclusive<usize>]) -> Self {
+        let len = ranges
+            .iter()
+            .map(|range| *range.end() + 1)
+            .max()
+            .unwrap_or(0);
+        let mut lines = vec![(false, 0); len];
+
+        for range in ranges {
+            lines[*range.start()].0 = true;
+            lines[*range.end()].1 += 1;
+        }
+
+        Self { lines }
+    }
+
+    /// Returns `true` if there are no folds.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+// Byte offsets where the lines of a source start, to find the line of an offset.
+pub(crate) struct LineStarts(Vec<usize>);
+
+impl LineStarts {
+    pub(crate) fn new(source: &str) -> Self {
+        let starts = source.match_indices('\n').map(|(i, _)| i + 1);
+        Self(std::iter::once(0).chain(starts).collect())
+    }
+
+    // The 1-based number of the line holding `offset`.
+    pub(crate) fn line(&self, offset: usize) -> usize {
+        self.0.partition_point(|&start| start <= offset)
+    }
+}
+
+/// Wrap a rendered line with `<details>` fold markers.
 ///
-/// # Arguments
+/// The first line of a fold becomes the `<summary>` of an open `<details class="fold">`
+/// element, which is closed after the fold's last line.
 ///
-/// * `output` - Writer to send the tag to
-/// * `pre_class` - Optional additional CSS class to append
-/// * `theme` - Optional theme for extracting pre tag styles
+/// The formatters render these elements inside `<code>`, next to the line `<div>`s.
+/// Like those, `<details>` is flow content, which the HTML specification doesn't allow
+/// in `<code>`. Browsers render it as expected, but validators report it.
 ///
 /// # Example
 ///
 /// ```rust
 /// use lumis::html;
 ///
-/// let mut output = Vec::new();
-/// html::open_pre_tag(&mut output, Some("my-code"), None).unwrap();
-/// assert_eq!(String::from_utf8(output).unwrap(), r#"<pre class="lumis my-code">"#);
+/// let folds = html::Folds::new(&[1..=3]);
+/// assert_eq!(html::wrap_fold(1, "a", &folds), r#"<details class="fold" open><summary>a</summary>"#);
+/// assert_eq!(html::wrap_fold(2, "b", &folds), "b");
+/// assert_eq!(html::wrap_fold(3, "c", &folds), "c</details>");
 /// ```
-pub fn open_pre_tag(
-    output: &mut dyn Write,
-    pre_class: Option<&str>,
-    theme: Option<&Theme>,
-) -> io::Result<()> {
-    let class = if let Some(pre_class) = pre_class {
-        format!("lumis {pre_class}")
+pub fn wrap_fold(line_number: usize, line: &str, folds: &Folds) -> String {
+    let (starts, ends) = folds.lines.get(line_number).copied().unwrap_or_default();
+    let mut result = String::with_capacity(line.len());
+
+    if starts {
+        result.push_str("<details class=\"fold\" open><summary>");
+        result.push_str(line);
+        result.push_str("</summary>");
     } else {
-        "lumis".to_string()
-    };
+        result.push_str(line);
+    }
 
-    write!(
-        output,
-        "<pre class=\"{}\"{}>",
-        class,
-        theme
-            .and_then(|theme| theme.pre_style(" "))
-            .map(|pre_style| format!(" style=\"{pre_style}\""))
-            .unwrap_or_default(),
-    )
+    for _ in 0..ends {
+        result.push_str("</details>");
+    }
+
+    result
 }
 
-/// Generate an opening `<code>` tag with language class.
-///
-/// Creates the opening `<code>` tag with the language class, translate="no",
-/// and tabindex="0" attributes.
+/// Map tree-sitter scope to the short CSS class of the matching Pygments token.
 ///
-/// # Arguments
-///
-/// * `output` - Writer to send the tag to
-/// * `lang` - The programming language for the code class
+/// Pygments stylesheets style tokens with abbreviated classes such as `k` (keyword),
+/// `kd` (declaration keyword), `s` (string) or `nf` (function name). Scopes without a
+/// mapping fall back to their parent scope, e.g. `keyword.import.rust` to
+/// `keyword.import`. Returns `None` for scopes with no Pygments counterpart, such as
+/// `injection.content`.
 ///
 /// # Example
 ///
 /// ```rust
-/// use lumis::{html, languages::Language};
+/// use lumis::html;
 ///
-/// let mut output = Vec::new();
-/// html::open_code_tag(&mut output, &Language::Rust).unwrap();
+/// assert_eq!(html::scope_to_pygments_class("keyword.function"), Some("kd"));
+/// assert_eq!(html::scope_to_pygments_class("string.escape.json"), Some("se"));
+/// assert_eq!(html::scope_to_pygments_class("injection.content"), None);
+/// ```
+pub fn scope_to_pygments_class(scope: &str) -> Option<&'static str> {
+    scope
+        .match_indices('.')
+        .map(|(end, _)| &scope[..end])
+        .chain([scope])
+        .rev()
+        .find_map(|scope| {
+            PYGMENTS_CLASSES
+                .iter()
+                .find(|(name, _)| *name == scope)
+                .map(|(_, class)| *class)
+        })
+}
+
+// Pygments token abbreviations for each scope, see
+// https://pygments.org/docs/tokens/ and `pygments.token.STANDARD_TYPES`.
+const PYGMENTS_CLASSES: &[(&str, &str)] = &[
+    ("attribute", "nd"),
+    ("boolean", "kc"),
+    ("character", "sc"),
+    ("character.special", "se"),
+    ("comment", "c"),
+    ("comment.documentation", "sd"),
+    ("comment.error", "cs"),
+    ("comment.hint", "cs"),
+    ("comment.note", "cs"),
+    ("comment.todo", "cs"),
+    ("comment.warning", "cs"),
+    ("constant", "no"),
+    ("constant.builtin", "kc"),
+    ("constructor", "nc"),
+    ("diff.delta", "gu"),
+    ("diff.minus", "gd"),
+    ("diff.plus", "gi"),
+    ("error", "err"),
+    ("function", "nf"),
+    ("function.builtin", "nb"),
+    ("keyword", "k"),
+    ("keyword.directive", "cp"),
+    ("keyword.function", "kd"),
+    ("keyword.import", "kn"),
+    ("keyword.modifier", "kd"),
+    ("keyword.operator", "ow"),
+    ("keyword.type", "kd"),
+    ("label", "nl"),
+    ("markup.heading", "gh"),
+    ("markup.italic", "ge"),
+    ("markup.link.url", "nl"),
+    ("markup.raw", "sb"),
+    ("markup.strong", "gs"),
+    ("module", "nn"),
+    ("namespace", "nn"),
+    ("number", "m"),
+    ("number.float", "mf"),
+    ("operator", "o"),
+    ("property", "py"),
+    ("punctuation", "p"),
+    ("string", "s"),
+    ("string.documentation", "sd"),
+    ("string.escape", "se"),
+    ("string.regexp", "sr"),
+    ("string.special.symbol", "ss"),
+    ("tag", "nt"),
+    ("tag.attribute", "na"),
+    ("tag.delimiter", "p"),
+    ("type", "nc"),
+    ("type.builtin", "kt"),
+    ("variable", "n"),
+    ("variable.builtin", "bp"),
+    ("variable.member", "py"),
+];
+
+/// Map tree-sitter scope to CSS class name.
+///
+/// Converts scope names to their corresponding CSS class names using the
+/// CLASSES constant. This maintains the full scope hierarchy specificity.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// assert_eq!(html::scope_to_class("string"), "string");
+/// assert_eq!(html::scope_to_class("function.method.call"), "function-method-call");
+/// ```
+pub fn scope_to_class(scope: &str) -> &str {
+    crate::constants::scope_index(scope)
+        .and_then(|idx| crate::constants::CLASSES.get(idx))
+        .copied()
+        .unwrap_or("text")
+}
+
+/// Generate the opening tag of a [`ContainerSpec`] and its header bar.
+///
+/// The wrapper gets the base "lumis-container" class, the optional custom class and a
+/// `data-lang` attribute. The header bar is omitted when neither the language label nor
+/// the copy button is enabled. With a `code_id`, the copy button points at the `<code>`
+/// element with that `id` through `data-clipboard-target`, escaped as a CSS selector.
+///
+/// # Arguments
+///
+/// * `output` - Writer to send the tags to
+/// * `container` - The container configuration
+/// * `lang` - The language shown in the label
+/// * `code_id` - The `id` of the `<code>` element the copy button copies
+/// * `strategy` - How to escape the class, label and `code_id`
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{html, formatter::{ContainerSpec, EscapeStrategy}, languages::Language};
+///
+/// let container = ContainerSpec {
+///     include_copy_button: false,
+///     ..Default::default()
+/// };
+///
+/// let mut output = Vec::new();
+/// html::open_container(&mut output, &container, &Language::Rust, None, EscapeStrategy::Named).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     r#"<div class="lumis-container" data-lang="rust"><div class="lumis-header"><span class="lumis-lang">Rust</span></div>"#
+/// );
+///
+/// let mut output = Vec::new();
+/// html::open_container(
+///     &mut output,
+///     &ContainerSpec::default(),
+///     &Language::Rust,
+///     Some("main"),
+///     EscapeStrategy::Named,
+/// )
+/// .unwrap();
+/// assert!(String::from_utf8(output).unwrap().contains(
+///     r##"<button type="button" class="lumis-copy" aria-label="Copy code" data-clipboard-target="#main">Copy</button>"##
+/// ));
+/// ```
+pub fn open_container(
+    output: &mut dyn Write,
+    container: &ContainerSpec,
+    lang: &Language,
+    code_id: Option<&str>,
+    strategy: EscapeStrategy,
+) -> io::Result<()> {
+    let class = if let Some(class) = &container.class {
+        format!("lumis-container {}", escape_with(class, strategy))
+    } else {
+        "lumis-container".to_string()
+    };
+
+    write!(
+        output,
+        "<{} class=\"{}\" data-lang=\"{}\">",
+        container_tag(container),
+        class,
+        lang.id_name()
+    )?;
+
+    if !container.include_lang_label && !container.include_copy_button {
+        return Ok(());
+    }
+
+    output.write_all(b"<div class=\"lumis-header\">")?;
+
+    if container.include_lang_label {
+        write!(
+            output,
+            "<span class=\"lumis-lang\">{}</span>",
+            escape_with(lang.name(), strategy)
+        )?;
+    }
+
+    if container.include_copy_button {
+        output
+            .write_all(b"<button type=\"button\" class=\"lumis-copy\" aria-label=\"Copy code\"")?;
+        if let Some(code_id) = code_id {
+            write!(
+                output,
+                " data-clipboard-target=\"#{}\"",
+                escape_with(&css_ident(code_id), strategy)
+            )?;
+        }
+        output.write_all(b">Copy</button>")?;
+    }
+
+    output.write_all(b"</div>")
+}
+
+/// Generate the closing tag of a [`ContainerSpec`].
+///
+/// # Arguments
+///
+/// * `output` - Writer to send the tag to
+/// * `container` - The container configuration
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{html, formatter::ContainerSpec};
+///
+/// let mut output = Vec::new();
+/// html::close_container(&mut output, &ContainerSpec::default()).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap(), "</div>");
+/// ```
+pub fn close_container(output: &mut dyn Write, container: &ContainerSpec) -> io::Result<()> {
+    write!(output, "</{}>", container_tag(container))
+}
+
+// The tag name of `container`, or `div` when it isn't a valid tag name.
+fn container_tag(container: &ContainerSpec) -> &str {
+    let mut chars = container.tag.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if valid {
+        &container.tag
+    } else {
+        "div"
+    }
+}
+
+/// Generate the opening `<figure>` tag of a [`FigureSpec`] and its caption.
+///
+/// # Arguments
+///
+/// * `output` - Writer to send the tags to
+/// * `figure` - The figure configuration
+/// * `strategy` - How to escape the caption
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{html, formatter::{EscapeStrategy, FigureSpec}};
+///
+/// let figure = FigureSpec {
+///     caption: "a <b>.rs".to_string(),
+/// };
+///
+/// let mut output = Vec::new();
+/// html::open_figure(&mut output, &figure, EscapeStrategy::Named).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     r#"<figure class="lumis-figure"><figcaption>a &lt;b&gt;.rs</figcaption>"#
+/// );
+/// ```
+pub fn open_figure(
+    output: &mut dyn Write,
+    figure: &FigureSpec,
+    strategy: EscapeStrategy,
+) -> io::Result<()> {
+    output.write_all(b"<figure class=\"lumis-figure\">")?;
+
+    if !figure.caption.is_empty() {
+        write!(
+            output,
+            "<figcaption>{}</figcaption>",
+            escape_with(&figure.caption, strategy)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Generate the closing `</figure>` tag of a [`FigureSpec`].
+///
+/// # Arguments
+///
+/// * `output` - Writer to send the tag to
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// let mut output = Vec::new();
+/// html::close_figure(&mut output).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap(), "</figure>");
+/// ```
+pub fn close_figure(output: &mut dyn Write) -> io::Result<()> {
+    output.write_all(b"</figure>")
+}
+
+/// Pick black or white text, whichever reads better on the `bg` color.
+///
+/// Compares the WCAG relative luminance of `bg`, a `#rrggbb` color, against the point
+/// where black and white text contrast equally. Returns `None` for other color formats.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// assert_eq!(html::contrast_color("#f1fa8c"), Some("#000000"));
+/// assert_eq!(html::contrast_color("#44475a"), Some("#ffffff"));
+/// assert_eq!(html::contrast_color("yellow"), None);
+/// ```
+pub fn contrast_color(bg: &str) -> Option<&'static str> {
+    let luminance = crate::themes::relative_luminance(bg)?;
+    Some(if luminance > 0.179 {
+        "#000000"
+    } else {
+        "#ffffff"
+    })
+}
+
+// The `#rrggbb` value of the `background-color` or `background` declaration in `css`.
+pub(crate) fn css_background(css: &str) -> Option<&str> {
+    css.split(';').find_map(|declaration| {
+        let (property, value) = declaration.split_once(':')?;
+        let value = value.split_whitespace().next()?;
+        (matches!(property.trim(), "background-color" | "background") && value.starts_with('#'))
+            .then_some(value)
+    })
+}
+
+/// Generate the button of a [`CopyButtonSpec`] targeting the `<code>` with `code_id`.
+///
+/// # Arguments
+///
+/// * `output` - Writer to send the button to
+/// * `button` - The button configuration
+/// * `code_id` - The `id` of the `<code>` element to copy
+/// * `strategy` - How to escape the class, `code_id` and label
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{html, formatter::{CopyButtonSpec, EscapeStrategy}};
+///
+/// let mut output = Vec::new();
+/// html::copy_button(&mut output, &CopyButtonSpec::default(), "snippet", EscapeStrategy::Named).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     r##"<button type="button" class="copy" data-clipboard-target="#snippet">Copy</button>"##
+/// );
+/// ```
+pub fn copy_button(
+    output: &mut dyn Write,
+    button: &CopyButtonSpec,
+    code_id: &str,
+    strategy: EscapeStrategy,
+) -> io::Result<()> {
+    write!(
+        output,
+        "<button type=\"button\" class=\"{}\" data-clipboard-target=\"#{}\">{}</button>",
+        escape_with(&button.class, strategy),
+        escape_with(&css_ident(code_id), strategy),
+        escape_with(&button.label, strategy)
+    )
+}
+
+// Count of generated ids, so identical blocks on one page don't share an id.
+static CODE_IDS: AtomicU64 = AtomicU64::new(0);
+
+// Id of the `<code>` element when a copy button targets it: the `code_id` option, or
+// one derived from the language and source plus a per-process counter.
+pub(crate) fn code_id(
+    id: Option<&str>,
+    container: Option<&ContainerSpec>,
+    copy_button: Option<&CopyButtonSpec>,
+    lang: &Language,
+    source: &str,
+) -> Option<String> {
+    if copy_button.is_none() && !container.is_some_and(|c| c.include_copy_button) {
+        return None;
+    }
+
+    Some(match id {
+        Some(id) => id.to_string(),
+        None => format!(
+            "lumis-code-{:016x}-{}",
+            crate::formatter::cache_key(source, lang),
+            CODE_IDS.fetch_add(1, Ordering::Relaxed)
+        ),
+    })
+}
+
+// Escape `id` as a CSS identifier, following `CSS.escape`, so `#{id}` is a valid
+// selector for any id.
+fn css_ident(id: &str) -> Cow<'_, str> {
+    let needs_escape = |i: usize, c: char| match c {
+        '0'..='9' => i == 0 || (i == 1 && id.starts_with('-')),
+        '-' => id == "-",
+        c => !(c.is_ascii_alphanumeric() || c == '_' || !c.is_ascii()),
+    };
+
+    if !id.chars().enumerate().any(|(i, c)| needs_escape(i, c)) {
+        return Cow::Borrowed(id);
+    }
+
+    let mut escaped = String::with_capacity(id.len() + 4);
+    for (i, c) in id.chars().enumerate() {
+        if c == '\0' {
+            escaped.push('\u{FFFD}');
+        } else if !needs_escape(i, c) {
+            escaped.push(c);
+        } else if c.is_ascii_control() || c.is_ascii_digit() {
+            escaped.push_str(&format!("\\{:x} ", c as u32));
+        } else {
+            escaped.push('\\');
+            escaped.push(c);
+        }
+    }
+
+    Cow::Owned(escaped)
+}
+
+// The `copy_button` option, unless the container renders its own button.
+pub(crate) fn standalone_copy_button<'a>(
+    container: Option<&ContainerSpec>,
+    copy_button: Option<&'a CopyButtonSpec>,
+) -> Option<&'a CopyButtonSpec> {
+    copy_button.filter(|_| !container.is_some_and(|c| c.include_copy_button))
+}
+
+// The options the HTML formatters share, borrowed from a formatter, and the steps they
+// render them with: highlighting the source, decorating each line, and wrapping the
+// `<pre>` block in its header, container, figure and copy button. The `<pre>` tag and
+// the token and highlighted line attributes are left to each formatter.
+pub(crate) struct HtmlBlock<'a> {
+    pub(crate) lang: Language,
+    pub(crate) header: Option<&'a HtmlElement>,
+    pub(crate) container: Option<&'a ContainerSpec>,
+    pub(crate) copy_button: Option<&'a CopyButtonSpec>,
+    pub(crate) code_id: Option<&'a str>,
+    pub(crate) figure: Option<&'a FigureSpec>,
+    pub(crate) language_class_map: &'a LanguageClassMap,
+    pub(crate) scope_tooltips: bool,
+    pub(crate) foldable: bool,
+    pub(crate) gutter: Option<GutterCallback>,
+    pub(crate) line_prefix: Option<&'a LineCallback>,
+    pub(crate) line_suffix: Option<&'a LineCallback>,
+    pub(crate) non_empty_line_marker: bool,
+    pub(crate) trim_final_newline: bool,
+    pub(crate) escape_braces: bool,
+    pub(crate) escape_strategy: EscapeStrategy,
+    pub(crate) render_whitespace: WhitespaceMode,
+    pub(crate) tab_mode: TabMode,
+    pub(crate) line_class_fn: Option<&'a LineClassFn>,
+    pub(crate) injection_resolver: Option<&'a InjectionResolver>,
+}
+
+impl HtmlBlock<'_> {
+    // Whether the output only depends on the options and source, so it can be cached:
+    // callbacks may not be pure, and a generated `code_id` changes on every call.
+    pub(crate) fn cacheable(&self) -> bool {
+        let callbacks = self.gutter.is_some()
+            || self.line_prefix.is_some()
+            || self.line_suffix.is_some()
+            || self.line_class_fn.is_some()
+            || self.injection_resolver.is_some();
+        let generated_id = self.code_id.is_none()
+            && code_id(None, self.container, self.copy_button, &self.lang, "").is_some();
+
+        !callbacks && !generated_id
+    }
+
+    // Highlight `source` and write the attributes of each token `<span>` with `span`,
+    // given the scope, the language name and the line number of the token.
+    pub(crate) fn render<F>(&self, source: &str, span: F) -> io::Result<HtmlRenderer>
+    where
+        F: Fn(&str, &str, usize, &mut Vec<u8>),
+    {
+        let mut highlighter = Highlighter::new();
+        let events = highlighter
+            .highlight(self.lang.config(), source.as_bytes(), None, |injected| {
+                crate::formatter::injection_config(self.injection_resolver, injected)
+            })
+            .map_err(io::Error::other)?;
+
+        let mut renderer = HtmlRenderer::new();
+        set_escape_strategy(&mut renderer, self.escape_strategy);
+        let line_starts = LineStarts::new(source);
+
+        renderer
+            .render_with_offsets(
+                events,
+                source.as_bytes(),
+                &|highlight, language, offset, output| {
+                    let scope = crate::constants::HIGHLIGHT_NAMES[highlight.0];
+                    let start = output.len();
+                    span(scope, language, line_starts.line(offset), output);
+                    if self.scope_tooltips {
+                        if output.len() > start {
+                            output.push(b' ');
+                        }
+                        output.extend(scope_title_attr(scope).as_bytes());
+                    }
+                },
+            )
+            .map_err(io::Error::other)?;
+
+        Ok(renderer)
+    }
+
+    // The rendered lines, without the final empty one with `trim_final_newline`.
+    pub(crate) fn lines<'r>(&self, renderer: &'r HtmlRenderer) -> Vec<&'r str> {
+        let mut lines: Vec<&str> = renderer.lines().collect();
+        if self.trim_final_newline {
+            trim_final_empty_line(&mut lines);
+        }
+        lines
+    }
+
+    pub(crate) fn folds(&self, source: &str) -> Folds {
+        if self.foldable {
+            Folds::new(&self.lang.fold_ranges(source))
+        } else {
+            Folds::default()
+        }
+    }
+
+    // Expand tabs, render whitespace and escape braces in a rendered line.
+    pub(crate) fn line_content(&self, line: &str) -> String {
+        let line = expand_tabs(line, self.tab_mode);
+        let line = render_whitespace(&line, self.render_whitespace);
+        if self.escape_braces {
+            escape_braces_with(&line, self.escape_strategy)
+        } else {
+            line
+        }
+    }
+
+    // Add the gutter and the line prefix and suffix to the content of a line.
+    pub(crate) fn decorate(&self, line_number: usize, content: &str) -> String {
+        decorate_line(
+            line_number,
+            content,
+            self.gutter,
+            self.line_prefix,
+            self.line_suffix,
+        )
+    }
+
+    // A rendered line with every line option applied, without its `<div>`.
+    pub(crate) fn render_line(&self, line_number: usize, line: &str) -> String {
+        let content = self.line_content(line);
+        let content = if self.non_empty_line_marker {
+            mark_empty_line(&content)
+        } else {
+            content
+        };
+        self.decorate(line_number, &content)
+    }
+
+    // Write the `<code>` element with `lines` in their `<div>`s and close the `<pre>`.
+    // `line_attrs` gives the class suffix and style of a line unless `line_class_fn`
+    // sets its class.
+    pub(crate) fn write_code<'l>(
+        &self,
+        output: &mut Vec<u8>,
+        code_id: Option<&str>,
+        lines: impl IntoIterator<Item = (usize, &'l str)>,
+        folds: &Folds,
+        line_attrs: impl Fn(usize) -> (Option<String>, Option<String>),
+    ) -> io::Result<()> {
+        open_code_tag_with_class(output, &self.language_class_map.class(&self.lang), code_id)?;
+
+        for (line_number, line) in lines {
+            let (class_suffix, style) = match self.line_class_fn {
+                Some(line_class_fn) => (line_class_fn.class_suffix(line_number), None),
+                None => line_attrs(line_number),
+            };
+            let wrapped = wrap_line(
+                line_number,
+                &self.render_line(line_number, line),
+                class_suffix.as_deref(),
+                style.as_deref(),
+            );
+            if folds.is_empty() {
+                output.extend(wrapped.as_bytes());
+            } else {
+                output.extend(wrap_fold(line_number, &wrapped, folds).as_bytes());
+            }
+        }
+
+        closing_tags(output)
+    }
+
+    // Write the block `write_pre` writes inside the header, container and figure, after
+    // the copy button. `write_pre` gets the `id` of the `<code>` element, which is only
+    // set when the block has a `<code>` element to copy from.
+    pub(crate) fn write<F>(
+        &self,
+        source: &str,
+        output: &mut dyn Write,
+        has_code: bool,
+        write_pre: F,
+    ) -> io::Result<()>
+    where
+        F: FnOnce(&mut Vec<u8>, Option<&str>) -> io::Result<()>,
+    {
+        let mut buffer = Vec::new();
+
+        if let Some(header) = self.header {
+            write!(buffer, "{}", header.open_tag)?;
+        }
+
+        let code_id = code_id(
+            self.code_id,
+            self.container,
+            self.copy_button,
+            &self.lang,
+            source,
+        )
+        .filter(|_| has_code);
+
+        if let Some(container) = self.container {
+            open_container(
+                &mut buffer,
+                container,
+                &self.lang,
+                code_id.as_deref(),
+                self.escape_strategy,
+            )?;
+        }
+
+        if let Some(figure) = self.figure {
+            open_figure(&mut buffer, figure, self.escape_strategy)?;
+        }
+
+        let button = standalone_copy_button(self.container, self.copy_button);
+        if let (Some(button), Some(code_id)) = (button, &code_id) {
+            copy_button(&mut buffer, button, code_id, self.escape_strategy)?;
+        }
+
+        write_pre(&mut buffer, code_id.as_deref())?;
+
+        if self.figure.is_some() {
+            close_figure(&mut buffer)?;
+        }
+
+        if let Some(container) = self.container {
+            close_container(&mut buffer, container)?;
+        }
+
+        if let Some(header) = self.header {
+            write!(buffer, "{}", header.close_tag)?;
+        }
+
+        output.write_all(&buffer)
+    }
+
+    // Highlight `source` and write the whole block, the `<pre>` tag with `open_pre`,
+    // the tokens with `span` and the highlighted lines with `line_attrs`.
+    pub(crate) fn format<F>(
+        &self,
+        source: &str,
+        output: &mut dyn Write,
+        open_pre: impl FnOnce(&mut Vec<u8>) -> io::Result<()>,
+        span: F,
+        line_attrs: impl Fn(usize) -> (Option<String>, Option<String>),
+    ) -> io::Result<()>
+    where
+        F: Fn(&str, &str, usize, &mut Vec<u8>),
+    {
+        let renderer = self.render(source, span)?;
+        let lines = self.lines(&renderer);
+        let folds = self.folds(source);
+
+        self.write(source, output, true, |buffer, code_id| {
+            open_pre(buffer)?;
+            self.write_code(buffer, code_id, (1..).zip(lines), &folds, line_attrs)
+        })
+    }
+}
+
+/// Generate an opening `<pre>` tag with optional class and theme styles.
+///
+/// Creates the opening `<pre>` tag with the base "lumis" class, an optional custom class,
+/// and optional theme styling for background and foreground colors.
+///
+/// # Arguments
+///
+/// * `output` - Writer to send the tag to
+/// * `pre_class` - Optional additional CSS class to append
+/// * `theme` - Optional theme for extracting pre tag styles
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// let mut output = Vec::new();
+/// html::open_pre_tag(&mut output, Some("my-code"), None).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap(), r#"<pre class="lumis my-code">"#);
+/// ```
+pub fn open_pre_tag(
+    output: &mut dyn Write,
+    pre_class: Option<&str>,
+    theme: Option<&Theme>,
+) -> io::Result<()> {
+    let pre_style = theme.and_then(|theme| theme.pre_style(" "));
+    open_pre_tag_with_style(output, pre_class, pre_style.as_deref())
+}
+
+/// Generate an opening `<pre>` tag with optional class and inline styles.
+///
+/// Like [`open_pre_tag`], but with the content of the `style` attribute given as-is,
+/// for example a theme's [`pre_style`](Theme::pre_style) combined with [`font_style`].
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// let mut output = Vec::new();
+/// html::open_pre_tag_with_style(&mut output, None, Some("font-size: 14px;")).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap(), r#"<pre class="lumis" style="font-size: 14px;">"#);
+/// ```
+pub fn open_pre_tag_with_style(
+    output: &mut dyn Write,
+    pre_class: Option<&str>,
+    style: Option<&str>,
+) -> io::Result<()> {
+    open_pre_tag_with_attrs(output, pre_class, style, "")
+}
+
+/// Generate an opening `<pre>` tag with optional class, inline styles and extra attributes.
+///
+/// Like [`open_pre_tag_with_style`], with `attrs` written as-is after the `class`
+/// attribute, for example the output of [`data_attributes`].
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{html, languages::Language};
+///
+/// let mut output = Vec::new();
+/// let attrs = html::data_attributes(&Language::Rust, None);
+/// html::open_pre_tag_with_attrs(&mut output, None, None, &attrs).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap(), r#"<pre class="lumis" data-lang="rust">"#);
+/// ```
+pub fn open_pre_tag_with_attrs(
+    output: &mut dyn Write,
+    pre_class: Option<&str>,
+    style: Option<&str>,
+    attrs: &str,
+) -> io::Result<()> {
+    let class = if let Some(pre_class) = pre_class {
+        format!("lumis {pre_class}")
+    } else {
+        "lumis".to_string()
+    };
+
+    write!(
+        output,
+        "<pre class=\"{}\"{}{}>",
+        class,
+        attrs,
+        style
+            .map(|style| format!(" style=\"{style}\""))
+            .unwrap_or_default(),
+    )
+}
+
+/// Generate the `data-lang` and `data-theme` attributes of a code block.
+///
+/// `data-lang` holds the language's [`id_name`](Language::id_name) and `data-theme`
+/// the theme name, when there is one, so scripts can target blocks by language or
+/// theme without parsing class names. The result starts with a space, ready to be
+/// passed to [`open_pre_tag_with_attrs`].
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{html, languages::Language};
+///
+/// assert_eq!(
+///     html::data_attributes(&Language::Rust, Some("dracula")),
+///     r#" data-lang="rust" data-theme="dracula""#
+/// );
+/// ```
+pub fn data_attributes(lang: &Language, theme_name: Option<&str>) -> String {
+    let mut attrs = format!(" data-lang=\"{}\"", escape(&lang.id_name()));
+
+    if let Some(theme_name) = theme_name {
+        attrs.push_str(&format!(" data-theme=\"{}\"", escape(theme_name)));
+    }
+
+    attrs
+}
+
+/// Generate the `font-family` and `font-size` CSS declarations of a code block.
+///
+/// The declarations are meant for a `style` attribute, so `&`, `<`, `>` and `"` in the
+/// values are escaped, and each value ends at its first `;` so it can't add other
+/// declarations. Returns `None` when neither is set.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// assert_eq!(
+///     html::font_style(Some("\"Fira Code\", monospace"), Some("14px")).as_deref(),
+///     Some("font-family: &quot;Fira Code&quot;, monospace; font-size: 14px;")
+/// );
+/// assert_eq!(
+///     html::font_style(None, Some("1em; color: red")).as_deref(),
+///     Some("font-size: 1em;")
+/// );
+/// assert_eq!(html::font_style(None, None), None);
+/// ```
+pub fn font_style(font_family: Option<&str>, font_size: Option<&str>) -> Option<String> {
+    let declarations: Vec<String> = [("font-family", font_family), ("font-size", font_size)]
+        .into_iter()
+        .filter_map(|(property, value)| {
+            value.map(|value| format!("{property}: {};", style_value(value)))
+        })
+        .collect();
+
+    if declarations.is_empty() {
+        None
+    } else {
+        Some(declarations.join(" "))
+    }
+}
+
+// A CSS value for a `style` attribute: cut at the first `;` and escaped for the
+// double-quoted attribute. Single quotes are kept so `'Fira Code'` stays readable.
+pub(crate) fn style_value(value: &str) -> String {
+    let value = value.split(';').next().unwrap_or_default().trim_end();
+    escape_with(value, EscapeStrategy::Minimal).replace('"', "&quot;")
+}
+
+/// Generate an opening `<code>` tag with language class.
+///
+/// Creates the opening `<code>` tag with the language class, translate="no",
+/// and tabindex="0" attributes.
+///
+/// # Arguments
+///
+/// * `output` - Writer to send the tag to
+/// * `lang` - The programming language for the code class
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{html, languages::Language};
+///
+/// let mut output = Vec::new();
+/// html::open_code_tag(&mut output, &Language::Rust).unwrap();
 /// assert_eq!(String::from_utf8(output).unwrap(), r#"<code class="language-rust" translate="no" tabindex="0">"#);
 /// ```
 pub fn open_code_tag(output: &mut dyn Write, lang: &Language) -> io::Result<()> {
+    open_code_tag_with_id(output, lang, None)
+}
+
+/// Generate an opening `<code>` tag with language class and an optional `id`.
+///
+/// Same as [`open_code_tag`], with the `id` placed first so a copy button can target
+/// the element.
+///
+/// # Arguments
+///
+/// * `output` - Writer to send the tag to
+/// * `lang` - The programming language for the code class
+/// * `id` - Optional id of the element
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{html, languages::Language};
+///
+/// let mut output = Vec::new();
+/// html::open_code_tag_with_id(&mut output, &Language::Rust, Some("snippet")).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap(), r#"<code id="snippet" class="language-rust" translate="no" tabindex="0">"#);
+/// ```
+pub fn open_code_tag_with_id(
+    output: &mut dyn Write,
+    lang: &Language,
+    id: Option<&str>,
+) -> io::Result<()> {
+    open_code_tag_with_class(output, &format!("language-{}", lang.id_name()), id)
+}
+
+/// Generate an opening `<code>` tag with a custom class and an optional `id`.
+///
+/// Same as [`open_code_tag_with_id`], with `class` in place of the `language-*` class,
+/// e.g. to follow the naming of a CSS framework. The class is escaped.
+///
+/// # Arguments
+///
+/// * `output` - Writer to send the tag to
+/// * `class` - The class of the element
+/// * `id` - Optional id of the element
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::html;
+///
+/// let mut output = Vec::new();
+/// html::open_code_tag_with_class(&mut output, "lang-js", None).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap(), r#"<code class="lang-js" translate="no" tabindex="0">"#);
+/// ```
+pub fn open_code_tag_with_class(
+    output: &mut dyn Write,
+    class: &str,
+    id: Option<&str>,
+) -> io::Result<()> {
+    if let Some(id) = id {
+        write!(output, "<code id=\"{}\"", escape(id))?;
+    } else {
+        write!(output, "<code")?;
+    }
+
     write!(
         output,
-        "<code class=\"language-{}\" translate=\"no\" tabindex=\"0\">",
-        lang.id_name()
+        " class=\"{}\" translate=\"no\" tabindex=\"0\">",
+        escape(class)
     )
 }
 
@@ -801,6 +2184,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_escape_with_strategies() {
+        let text = "&<>\"'{}é";
+        assert_eq!(
+            escape_with(text, EscapeStrategy::Minimal),
+            "&amp;&lt;&gt;\"'{}é"
+        );
+        assert_eq!(
+            escape_with(text, EscapeStrategy::Named),
+            format!("{}é", escape("&<>\"'{}"))
+        );
+        assert_eq!(
+            escape_with(text, EscapeStrategy::Numeric),
+            "&#38;&#60;&#62;&#34;&#39;&#123;&#125;é"
+        );
+        assert_eq!(
+            escape_with(text, EscapeStrategy::NonAscii),
+            "&#38;&#60;&#62;&#34;&#39;&#123;&#125;&#233;"
+        );
+        assert_eq!(escape_source_char('{', EscapeStrategy::Numeric), None);
+        assert_eq!(
+            escape_source_char('😀', EscapeStrategy::NonAscii).as_deref(),
+            Some("&#128512;")
+        );
+    }
+
     #[test]
     fn test_escape_preserves_normal_text() {
         assert_eq!(escape("hello world"), "hello world");
@@ -950,4 +2359,126 @@ mod tests {
         let result = span_linked("fn", "keyword.function");
         assert_str_eq!(result, r#"<span class="keyword-function">fn</span>"#);
     }
+
+    #[test]
+    fn test_render_whitespace() {
+        let line = "  <span class=\"k\">if</span> x\t\n";
+
+        assert_str_eq!(render_whitespace(line, WhitespaceMode::None), line);
+        assert_str_eq!(
+            render_whitespace(line, WhitespaceMode::All),
+            "<span class=\"whitespace\">··</span><span class=\"k\">if</span><span class=\"whitespace\">·</span>x<span class=\"whitespace\">→</span>\n"
+        );
+        assert_str_eq!(
+            render_whitespace(line, WhitespaceMode::Boundary),
+            "<span class=\"whitespace\">··</span><span class=\"k\">if</span> x<span class=\"whitespace\">→</span>\n"
+        );
+        assert_str_eq!(
+            render_whitespace(" \t\n", WhitespaceMode::Boundary),
+            "<span class=\"whitespace\">·→</span>\n"
+        );
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        let line = "<span class=\"k\">x</span>\t<span class=\"c\">// a</span>\n";
+
+        assert_str_eq!(expand_tabs(line, TabMode::None), line);
+        assert_str_eq!(
+            expand_tabs(line, TabMode::Stops(4)),
+            "<span class=\"k\">x</span>   <span class=\"c\">// a</span>\n"
+        );
+        assert_str_eq!(
+            expand_tabs(line, TabMode::Spaces(2)),
+            "<span class=\"k\">x</span>  <span class=\"c\">// a</span>\n"
+        );
+        assert_str_eq!(
+            expand_tabs("&amp;&amp;\tx", TabMode::Stops(4)),
+            "&amp;&amp;  x"
+        );
+        assert_str_eq!(expand_tabs("日本\tx", TabMode::Stops(8)), "日本    x");
+        assert_str_eq!(expand_tabs("abcd\t\tx", TabMode::Stops(4)), "abcd        x");
+        assert_str_eq!(expand_tabs("a\tx", TabMode::Stops(0)), "ax");
+    }
+
+    #[test]
+    fn test_css_ident() {
+        assert_eq!(css_ident("lumis-code-1"), "lumis-code-1");
+        assert_eq!(css_ident("a.b:c"), r"a\.b\:c");
+        assert_eq!(css_ident("1a"), r"\31 a");
+        assert_eq!(css_ident("-2"), r"-\32 ");
+        assert_eq!(css_ident("-"), r"\-");
+        assert_eq!(css_ident("a\tb"), r"a\9 b");
+
+        let mut output = Vec::new();
+        copy_button(
+            &mut output,
+            &CopyButtonSpec::default(),
+            "x\"y",
+            EscapeStrategy::Named,
+        )
+        .unwrap();
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .contains(r##"data-clipboard-target="#x\&quot;y""##));
+    }
+
+    #[test]
+    fn test_generated_code_id_is_unique() {
+        let spec = CopyButtonSpec::default();
+        let first = code_id(None, None, Some(&spec), &Language::Rust, "fn main() {}");
+        let second = code_id(None, None, Some(&spec), &Language::Rust, "fn main() {}");
+        assert_ne!(first, second);
+        assert_eq!(
+            code_id(
+                Some("main"),
+                None,
+                Some(&spec),
+                &Language::Rust,
+                "fn main() {}"
+            ),
+            Some("main".to_string())
+        );
+    }
+
+    #[test]
+    fn test_line_starts() {
+        let starts = LineStarts::new("ab\n\ncd\n");
+        let lines: Vec<usize> = (0..8).map(|offset| starts.line(offset)).collect();
+        assert_eq!(lines, [1, 1, 1, 2, 3, 3, 3, 4]);
+    }
+
+    #[test]
+    fn test_container_tag() {
+        let render = |tag: &str| {
+            let container = ContainerSpec {
+                tag: tag.to_string(),
+                include_lang_label: false,
+                include_copy_button: false,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            open_container(
+                &mut output,
+                &container,
+                &Language::Rust,
+                None,
+                EscapeStrategy::Named,
+            )
+            .unwrap();
+            close_container(&mut output, &container).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert_str_eq!(
+            render("my-box2"),
+            r#"<my-box2 class="lumis-container" data-lang="rust"></my-box2>"#
+        );
+        for tag in ["", "2div", "div onclick=\"x\"", "div><script>"] {
+            assert_str_eq!(
+                render(tag),
+                r#"<div class="lumis-container" data-lang="rust"></div>"#
+            );
+        }
+    }
 }
@@ -0,0 +1,629 @@
+//! HTML formatter with generated atomic CSS classes.
+//!
+//! This module provides the [`HtmlAtomic`] formatter that gives each distinct style of a
+//! theme a class named after a hash of its declarations, like atomic CSS. Tokens
+//! sharing a style share a class, so large documents repeat a few class names instead
+//! of full `style` attributes, and the stylesheet only holds the styles actually used.
+//!
+//! # Example Output
+//!
+//! For the Rust code `fn main() {}` with the dracula theme, the formatter generates HTML like:
+//!
+//! ```html
+//! <pre class="lumis l-45ec96d298f22a64"><code class="language-rust" translate="no" tabindex="0"><div class="line" data-line="1"><span class="l-677d58bfd3afb95a">fn</span> <span class="l-91689840aca3c553">main</span><span class="l-665d52d61eb81554">(</span>...</div></code></pre>
+//! ```
+//!
+//! And the CSS rules:
+//!
+//! ```css
+//! .l-45ec96d298f22a64 { color: #f8f8f2; background-color: #282a36; }
+//! .l-677d58bfd3afb95a { color: #8be9fd; }
+//! .l-91689840aca3c553 { color: #50fa7b; }
+//! .l-665d52d61eb81554 { color: #f8f8f2; }
+//! ```
+//!
+//! See the [formatter](crate::formatter) module for more information and examples.
+
+use super::html_inline::{HighlightLines, HighlightLinesStyle};
+use super::{
+    ContainerSpec, CopyButtonSpec, EscapeStrategy, FigureSpec, Formatter, GutterCallback,
+    HtmlElement, InjectionResolver, LanguageClassMap, LineCallback, LineClassFn, TabMode,
+    WhitespaceMode,
+};
+use crate::languages::Language;
+use crate::themes::Theme;
+use derive_builder::Builder;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::io::{self, Write};
+
+/// HTML formatter with a generated class per distinct style.
+///
+/// Each style is written once as a CSS rule for a class named `{class_prefix}{hash}`,
+/// where the hash is taken from the style declarations. The same style always gets the
+/// same class, across code blocks and runs, so the rules of several blocks can be
+/// merged into one stylesheet. Use [`format_with_css`](HtmlAtomic::format_with_css) to
+/// get the rules along with the HTML; [`Formatter::format`] only writes the HTML.
+/// The options shared by the HTML formatters, such as `container`, `copy_button` or
+/// `line_class_fn`, work as in [`HtmlInline`](super::HtmlInline), with the font
+/// declarations moved into the class of the `<pre>` tag. Use [`HtmlAtomicBuilder`] to
+/// create instances.
+///
+/// # When to use
+///
+/// - CSS-in-JS or atomic CSS pipelines that collect rules per page
+/// - Large documents where repeated inline styles would bloat the output
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{HtmlAtomicBuilder, languages::Language, themes};
+///
+/// let formatter = HtmlAtomicBuilder::new()
+///     .lang(Language::Rust)
+///     .theme(themes::get("dracula").ok())
+///     .build()
+///     .unwrap();
+///
+/// let output = formatter.format_with_css("fn main() {}").unwrap();
+/// assert!(output.html.contains(r#"<span class="l-91689840aca3c553">main</span>"#));
+/// assert!(output.css.contains(".l-91689840aca3c553 { color: #50fa7b; }"));
+/// ```
+#[derive(Builder, Clone, Debug, Hash)]
+#[builder(default)]
+pub struct HtmlAtomic {
+    lang: Language,
+    theme: Option<Theme>,
+    pre_class: Option<String>,
+    italic: bool,
+    highlight_lines: Option<HighlightLines>,
+    header: Option<HtmlElement>,
+    container: Option<ContainerSpec>,
+    copy_button: Option<CopyButtonSpec>,
+    code_id: Option<String>,
+    figure: Option<FigureSpec>,
+    #[builder(setter(custom))]
+    language_class_map: LanguageClassMap,
+    scope_tooltips: bool,
+    foldable: bool,
+    gutter: Option<GutterCallback>,
+    #[builder(setter(custom))]
+    line_prefix: Option<LineCallback>,
+    #[builder(setter(custom))]
+    line_suffix: Option<LineCallback>,
+    non_empty_line_marker: bool,
+    trim_final_newline: bool,
+    escape_braces: bool,
+    escape_strategy: EscapeStrategy,
+    render_whitespace: WhitespaceMode,
+    tab_mode: TabMode,
+    font_family: Option<String>,
+    font_size: Option<String>,
+    data_attributes: bool,
+    #[builder(setter(custom))]
+    line_class_fn: Option<LineClassFn>,
+    #[builder(setter(custom))]
+    injection_resolver: Option<InjectionResolver>,
+    #[builder(setter(into))]
+    class_prefix: String,
+}
+
+/// HTML and CSS rules returned by [`HtmlAtomic::format_with_css`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AtomicHtml {
+    /// The highlighted code block.
+    pub html: String,
+    /// One rule per class used in `html`, in order of first use.
+    pub css: String,
+}
+
+impl HtmlAtomicBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decide which language highlights each injected region, see [`InjectionResolver`].
+    pub fn injection_resolver<F>(&mut self, resolver: F) -> &mut Self
+    where
+        F: Fn(&str) -> Option<Language> + Send + Sync + 'static,
+    {
+        self.injection_resolver = Some(Some(InjectionResolver::new(resolver)));
+        self
+    }
+
+    /// Set the class of each line with a callback, see [`LineClassFn`].
+    pub fn line_class_fn<F>(&mut self, class: F) -> &mut Self
+    where
+        F: Fn(usize) -> Option<String> + Send + Sync + 'static,
+    {
+        self.line_class_fn = Some(Some(LineClassFn::new(class)));
+        self
+    }
+
+    /// Insert raw HTML at the start of each line, see [`LineCallback`].
+    pub fn line_prefix<F>(&mut self, prefix: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_prefix = Some(Some(LineCallback::new(prefix)));
+        self
+    }
+
+    /// Insert raw HTML at the end of each line, see [`LineCallback`].
+    pub fn line_suffix<F>(&mut self, suffix: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_suffix = Some(Some(LineCallback::new(suffix)));
+        self
+    }
+
+    /// Replace the `language-*` class of the `<code>` element for some languages, e.g.
+    /// `lang-js` for JavaScript. Other languages keep `language-{id}`.
+    pub fn language_class_map(&mut self, map: HashMap<Language, String>) -> &mut Self {
+        self.language_class_map = Some(LanguageClassMap(map));
+        self
+    }
+}
+
+impl HtmlAtomic {
+    pub fn new(
+        lang: Language,
+        theme: Option<Theme>,
+        pre_class: Option<String>,
+        italic: bool,
+        highlight_lines: Option<HighlightLines>,
+        header: Option<HtmlElement>,
+        class_prefix: String,
+    ) -> Self {
+        Self {
+            lang,
+            theme,
+            pre_class,
+            italic,
+            highlight_lines,
+            header,
+            container: None,
+            copy_button: None,
+            code_id: None,
+            figure: None,
+            language_class_map: LanguageClassMap::default(),
+            scope_tooltips: false,
+            foldable: false,
+            gutter: None,
+            line_prefix: None,
+            line_suffix: None,
+            non_empty_line_marker: false,
+            trim_final_newline: false,
+            escape_braces: true,
+            escape_strategy: EscapeStrategy::Named,
+            render_whitespace: WhitespaceMode::None,
+            tab_mode: TabMode::None,
+            font_family: None,
+            font_size: None,
+            data_attributes: false,
+            line_class_fn: None,
+            injection_resolver: None,
+            class_prefix,
+        }
+    }
+
+    /// Highlight `source` into HTML along with the CSS rules of its classes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::{HtmlAtomicBuilder, languages::Language, themes};
+    ///
+    /// let formatter = HtmlAtomicBuilder::new()
+    ///     .lang(Language::Rust)
+    ///     .theme(themes::get("dracula").ok())
+    ///     .class_prefix("hl-")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let a = formatter.format_with_css("let a = 1;").unwrap();
+    /// let b = formatter.format_with_css("let b = 2;").unwrap();
+    /// // The same styles get the same classes, so the rules of both blocks match
+    /// assert!(a.css.starts_with(".hl-"));
+    /// assert_eq!(a.css, b.css);
+    /// ```
+    pub fn format_with_css(&self, source: &str) -> io::Result<AtomicHtml> {
+        let rules = RefCell::new(Rules::new(&self.class_prefix));
+        let pre_class = self.pre_class(&mut rules.borrow_mut());
+
+        let block = self.block();
+        let renderer = block.render(source, |scope, language, _line, output| {
+            let lang = Language::guess(Some(language), "");
+            let style = self
+                .theme
+                .as_ref()
+                .and_then(|theme| theme.get_style(&format!("{}.{}", scope, lang.id_name())));
+            if let Some(style) = style {
+                let css = style.css(self.italic, " ");
+                if !css.is_empty() {
+                    let class = rules.borrow_mut().class(&css);
+                    output.extend(format!("class=\"{class}\"").as_bytes());
+                }
+            }
+        })?;
+        let lines = block.lines(&renderer);
+
+        let highlight_class = self.highlight_class(&mut rules.borrow_mut());
+        let attrs = if self.data_attributes {
+            let theme_name = self.theme.as_ref().map(|theme| theme.name.as_str());
+            crate::formatter::html::data_attributes(&self.lang, theme_name)
+        } else {
+            String::new()
+        };
+
+        let mut buffer = Vec::new();
+        block.write(source, &mut buffer, true, |buffer, code_id| {
+            crate::formatter::html::open_pre_tag_with_attrs(
+                buffer,
+                Some(pre_class.as_str()).filter(|class| !class.is_empty()),
+                None,
+                &attrs,
+            )?;
+            block.write_code(
+                buffer,
+                code_id,
+                (1..).zip(lines),
+                &block.folds(source),
+                |line_number| {
+                    let highlighted = self
+                        .highlight_lines
+                        .as_ref()
+                        .is_some_and(|hl| hl.lines.iter().any(|r| r.contains(&line_number)));
+                    (highlight_class.clone().filter(|_| highlighted), None)
+                },
+            )
+        })?;
+
+        Ok(AtomicHtml {
+            html: String::from_utf8(buffer).map_err(io::Error::other)?,
+            css: rules.into_inner().css(),
+        })
+    }
+
+    fn block(&self) -> crate::formatter::html::HtmlBlock<'_> {
+        crate::formatter::html::HtmlBlock {
+            lang: self.lang,
+            header: self.header.as_ref(),
+            container: self.container.as_ref(),
+            copy_button: self.copy_button.as_ref(),
+            code_id: self.code_id.as_deref(),
+            figure: self.figure.as_ref(),
+            language_class_map: &self.language_class_map,
+            scope_tooltips: self.scope_tooltips,
+            foldable: self.foldable,
+            gutter: self.gutter,
+            line_prefix: self.line_prefix.as_ref(),
+            line_suffix: self.line_suffix.as_ref(),
+            non_empty_line_marker: self.non_empty_line_marker,
+            trim_final_newline: self.trim_final_newline,
+            escape_braces: self.escape_braces,
+            escape_strategy: self.escape_strategy,
+            render_whitespace: self.render_whitespace,
+            tab_mode: self.tab_mode,
+            line_class_fn: self.line_class_fn.as_ref(),
+            injection_resolver: self.injection_resolver.as_ref(),
+        }
+    }
+
+    // Classes of the `<pre>` tag: the custom class and the class of the theme colors and
+    // font declarations.
+    fn pre_class(&self, rules: &mut Rules) -> String {
+        let declarations = [
+            self.theme.as_ref().and_then(|theme| theme.pre_style(" ")),
+            crate::formatter::html::font_style(
+                self.font_family.as_deref(),
+                self.font_size.as_deref(),
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+        [
+            self.pre_class.clone(),
+            Some(declarations)
+                .filter(|declarations| !declarations.is_empty())
+                .map(|declarations| rules.class(&declarations)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+
+    // Class suffix of highlighted lines: the class of the highlight style and the custom
+    // class. No rule is added without lines to highlight.
+    fn highlight_class(&self, rules: &mut Rules) -> Option<String> {
+        let highlight_lines = self
+            .highlight_lines
+            .as_ref()
+            .filter(|hl| !hl.lines.is_empty())?;
+
+        let style = match &highlight_lines.style {
+            Some(HighlightLinesStyle::Theme) => self
+                .theme
+                .as_ref()
+                .and_then(|theme| theme.get_style("highlighted"))
+                .map(|style| style.css(self.italic, " ")),
+            Some(HighlightLinesStyle::Style(style)) => Some(style.clone()),
+            None => None,
+        };
+
+        let suffix = [
+            style
+                .filter(|style| !style.is_empty())
+                .map(|style| rules.class(&style)),
+            highlight_lines.class.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|class| format!(" {class}"))
+        .collect::<String>();
+
+        Some(suffix).filter(|suffix| !suffix.is_empty())
+    }
+}
+
+impl Default for HtmlAtomic {
+    fn default() -> Self {
+        Self {
+            lang: Language::PlainText,
+            theme: None,
+            pre_class: None,
+            italic: false,
+            highlight_lines: None,
+            header: None,
+            container: None,
+            copy_button: None,
+            code_id: None,
+            figure: None,
+            language_class_map: LanguageClassMap::default(),
+            scope_tooltips: false,
+            foldable: false,
+            gutter: None,
+            line_prefix: None,
+            line_suffix: None,
+            non_empty_line_marker: false,
+            trim_final_newline: false,
+            escape_braces: true,
+            escape_strategy: EscapeStrategy::Named,
+            render_whitespace: WhitespaceMode::None,
+            tab_mode: TabMode::None,
+            font_family: None,
+            font_size: None,
+            data_attributes: false,
+            line_class_fn: None,
+            injection_resolver: None,
+            class_prefix: "l-".to_string(),
+        }
+    }
+}
+
+impl Formatter for HtmlAtomic {
+    fn format(&self, source: &str, output: &mut dyn Write) -> io::Result<()> {
+        let atomic = self.format_with_css(source)?;
+        output.write_all(atomic.html.as_bytes())
+    }
+
+    fn language(&self) -> Language {
+        self.lang
+    }
+
+    fn cache_key(&self, source: &str) -> Option<u64> {
+        self.block()
+            .cacheable()
+            .then(|| super::cache_key(source, self))
+    }
+}
+
+// Distinct style declarations in order of first use, each with its class.
+struct Rules<'a> {
+    prefix: &'a str,
+    rules: Vec<(String, String)>,
+}
+
+impl<'a> Rules<'a> {
+    fn new(prefix: &'a str) -> Self {
+        Self {
+            prefix,
+            rules: Vec::new(),
+        }
+    }
+
+    // The class is the prefix and the hash of the declarations.
+    fn class(&mut self, declarations: &str) -> String {
+        if let Some((class, _)) = self.rules.iter().find(|(_, d)| d == declarations) {
+            return class.clone();
+        }
+
+        let class = format!("{}{:016x}", self.prefix, class_hash(declarations));
+        self.rules.push((class.clone(), declarations.to_string()));
+        class
+    }
+
+    fn css(&self) -> String {
+        self.rules
+            .iter()
+            .map(|(class, declarations)| format!(".{class} {{ {declarations} }}\n"))
+            .collect()
+    }
+}
+
+// 64-bit FNV-1a of the declarations, stable across runs like `cache_key` and wide
+// enough that distinct styles don't share a class in practice.
+fn class_hash(declarations: &str) -> u64 {
+    let mut hasher = super::Fnv1a::default();
+    hasher.write(declarations.as_bytes());
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::themes;
+
+    fn dracula() -> HtmlAtomic {
+        HtmlAtomicBuilder::new()
+            .lang(Language::Rust)
+            .theme(themes::get("dracula").ok())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_format_with_css() {
+        let output = dracula().format_with_css("fn a() {}\nfn b() {}").unwrap();
+
+        assert!(output.html.starts_with(r#"<pre class="lumis l-"#));
+        assert!(!output.html.contains("style="));
+
+        let rules: Vec<&str> = output.css.lines().collect();
+        assert!(rules[0].contains("background-color: #282a36;"));
+        for rule in &rules {
+            let class = rule[1..].split(' ').next().unwrap();
+            assert!(class.starts_with("l-") && class.len() == 18);
+            assert!(output.html.contains(class));
+        }
+        let keyword = rules
+            .iter()
+            .find(|r| r.ends_with("{ color: #8be9fd; }"))
+            .unwrap();
+        let class = &keyword[1..19];
+        assert_eq!(
+            output.html.matches(&format!("class=\"{class}\"")).count(),
+            2
+        );
+
+        assert_eq!(
+            dracula()
+                .format_with_css("fn c() {}")
+                .unwrap()
+                .css
+                .lines()
+                .find(|r| r == keyword),
+            Some(*keyword)
+        );
+    }
+
+    #[test]
+    fn test_format_writes_html_only() {
+        let formatter = dracula();
+        let mut buffer = Vec::new();
+        formatter.format("fn main() {}", &mut buffer).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            formatter.format_with_css("fn main() {}").unwrap().html
+        );
+    }
+
+    #[test]
+    fn test_without_theme() {
+        let output = HtmlAtomicBuilder::new()
+            .lang(Language::Rust)
+            .build()
+            .unwrap()
+            .format_with_css("fn main() {}")
+            .unwrap();
+
+        // Unstyled tokens keep their span but get no class or style
+        assert!(output.html.starts_with(r#"<pre class="lumis"><code"#));
+        assert!(output.html.contains(">fn</span>"));
+        assert!(!output.html.contains("class=\"l-"));
+        assert!(!output.html.contains("style="));
+        assert_eq!(output.css, "");
+    }
+
+    #[test]
+    fn test_class_hash() {
+        // FNV-1a reference values
+        assert_eq!(class_hash(""), 0xcbf29ce484222325);
+        assert_eq!(class_hash("a"), 0xaf63dc4c8601ec8c);
+        assert_ne!(class_hash("color: #8be9fd;"), class_hash("color: #8be9fe;"));
+    }
+
+    #[test]
+    fn test_highlight_lines() {
+        let output = HtmlAtomicBuilder::new()
+            .lang(Language::PlainText)
+            .highlight_lines(Some(HighlightLines {
+                lines: vec![2..=2],
+                style: Some(HighlightLinesStyle::Style(
+                    "background: yellow;".to_string(),
+                )),
+                class: Some("marked".to_string()),
+            }))
+            .class_prefix("x")
+            .build()
+            .unwrap()
+            .format_with_css("a\nb")
+            .unwrap();
+
+        let class = format!("x{:016x}", class_hash("background: yellow;"));
+        assert!(output.html.contains(&format!(
+            "<div class=\"line {class} marked\" data-line=\"2\">b\n</div>"
+        )));
+        assert!(output.html.contains(r#"<div class="line" data-line="1">"#));
+        assert_eq!(output.css, format!(".{class} {{ background: yellow; }}\n"));
+    }
+
+    #[test]
+    fn test_empty_highlight_lines() {
+        let output = HtmlAtomicBuilder::new()
+            .highlight_lines(Some(HighlightLines {
+                lines: vec![],
+                style: Some(HighlightLinesStyle::Style("color: red;".to_string())),
+                class: None,
+            }))
+            .build()
+            .unwrap()
+            .format_with_css("a")
+            .unwrap();
+
+        assert_eq!(output.css, "");
+    }
+
+    #[test]
+    fn test_shared_options() {
+        let formatter = HtmlAtomicBuilder::new()
+            .lang(Language::Rust)
+            .theme(themes::get("dracula").ok())
+            .container(Some(ContainerSpec::default()))
+            .code_id(Some("main".to_string()))
+            .font_family(Some("Fira Code".to_string()))
+            .tab_mode(TabMode::Spaces(2))
+            .trim_final_newline(true)
+            .line_class_fn(|n| (n == 2).then(|| "added".to_string()))
+            .build()
+            .unwrap();
+
+        let output = formatter.format_with_css("fn a() {\n\tb();\n}\n").unwrap();
+
+        assert!(output
+            .html
+            .starts_with(r#"<div class="lumis-container" data-lang="rust">"#));
+        assert!(output.html.contains(r##"data-clipboard-target="#main""##));
+        assert!(output
+            .html
+            .contains(r#"<code id="main" class="language-rust""#));
+        assert!(output
+            .html
+            .contains(r#"<div class="line added" data-line="2">  <span"#));
+        assert!(!output.html.contains(r#"data-line="4""#));
+        assert!(output
+            .css
+            .lines()
+            .next()
+            .unwrap()
+            .contains("font-family: Fira Code;"));
+        assert_eq!(formatter.cache_key("a"), None);
+    }
+}
@@ -15,12 +15,17 @@
 //!
 //! See the [formatter](crate::formatter) module for more information and examples.
 
-use super::{Formatter, HtmlElement};
+use super::{
+    ContainerSpec, CopyButtonSpec, EscapeStrategy, FigureSpec, Formatter, GutterCallback,
+    HtmlElement, InjectionResolver, LanguageClassMap, LineCallback, LineClassFn, TabMode,
+    WhitespaceMode,
+};
 use crate::languages::Language;
 use crate::themes::Theme;
-use crate::vendor::tree_sitter_highlight::{Highlighter, HtmlRenderer};
+use crate::vendor::tree_sitter_highlight::HtmlRenderer;
 use derive_builder::Builder;
 use std::{
+    collections::HashMap,
     io::{self, Write},
     ops::RangeInclusive,
 };
@@ -74,7 +79,7 @@ use std::{
 ///     class: None,
 /// };
 /// ```
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct HighlightLines {
     /// List of line ranges to highlight.
     ///
@@ -88,7 +93,7 @@ pub struct HighlightLines {
 }
 
 /// Defines how highlighted lines should be styled in HTML inline output.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum HighlightLinesStyle {
     /// Use the theme's 'highlighted' style if available.
     ///
@@ -122,6 +127,19 @@ impl Default for HighlightLines {
     }
 }
 
+/// A line rendered by [`HtmlInline::format_structured`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderedLine {
+    /// Line number, starting at 1.
+    pub number: usize,
+    /// Highlighted HTML of the line, without the `<div class="line">` wrapper or the
+    /// line ending.
+    pub html: String,
+    /// Whether the line is in one of the `highlight_lines` ranges. Always `false` when
+    /// `line_class_fn` is set.
+    pub highlighted: bool,
+}
+
 /// HTML formatter with inline CSS styles.
 ///
 /// Generates self-contained HTML with styles embedded directly in elements.
@@ -134,6 +152,22 @@ impl Default for HighlightLines {
 /// - Embedding code snippets in emails or restricted environments
 /// - Quick prototyping without CSS setup
 ///
+/// # Email
+///
+/// Email clients such as Gmail drop `<style>` blocks and most `<pre>` styling. With
+/// `email_safe(true)` the block is rendered as a `<table>` with one row per line, and
+/// the theme colors, monospace font and whitespace handling are inlined on every
+/// `<td>`. Folding and copy buttons are not supported in this mode.
+///
+/// # Highlighted line contrast
+///
+/// A light highlight background can make the token colors of a dark theme unreadable.
+/// With `auto_contrast_highlight(true)`, tokens on highlighted lines whose color falls
+/// below the WCAG AA contrast ratio of 4.5 against the line background are drawn in
+/// black or white instead, whichever has the better contrast. The background is read
+/// from the `background-color` of the [`HighlightLinesStyle`], so this has no effect
+/// on styles without a `#rrggbb` background.
+///
 /// # Example
 ///
 /// ```rust
@@ -153,7 +187,7 @@ impl Default for HighlightLines {
 /// let mut output = Vec::new();
 /// formatter.format(code, &mut output).unwrap();
 /// ```
-#[derive(Builder, Clone, Debug)]
+#[derive(Builder, Clone, Debug, Hash)]
 #[builder(default)]
 pub struct HtmlInline {
     lang: Language,
@@ -163,12 +197,83 @@ pub struct HtmlInline {
     include_highlights: bool,
     highlight_lines: Option<HighlightLines>,
     header: Option<HtmlElement>,
+    container: Option<ContainerSpec>,
+    copy_button: Option<CopyButtonSpec>,
+    code_id: Option<String>,
+    figure: Option<FigureSpec>,
+    #[builder(setter(custom))]
+    language_class_map: LanguageClassMap,
+    scope_tooltips: bool,
+    foldable: bool,
+    gutter: Option<GutterCallback>,
+    #[builder(setter(custom))]
+    line_prefix: Option<LineCallback>,
+    #[builder(setter(custom))]
+    line_suffix: Option<LineCallback>,
+    non_empty_line_marker: bool,
+    trim_final_newline: bool,
+    escape_braces: bool,
+    escape_strategy: EscapeStrategy,
+    render_whitespace: WhitespaceMode,
+    tab_mode: TabMode,
+    font_family: Option<String>,
+    font_size: Option<String>,
+    data_attributes: bool,
+    auto_contrast_highlight: bool,
+    #[builder(setter(custom))]
+    line_class_fn: Option<LineClassFn>,
+    #[builder(setter(custom))]
+    injection_resolver: Option<InjectionResolver>,
+    email_safe: bool,
 }
 
 impl HtmlInlineBuilder {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Decide which language highlights each injected region, see [`InjectionResolver`].
+    pub fn injection_resolver<F>(&mut self, resolver: F) -> &mut Self
+    where
+        F: Fn(&str) -> Option<Language> + Send + Sync + 'static,
+    {
+        self.injection_resolver = Some(Some(InjectionResolver::new(resolver)));
+        self
+    }
+
+    /// Set the class of each line with a callback, see [`LineClassFn`].
+    pub fn line_class_fn<F>(&mut self, class: F) -> &mut Self
+    where
+        F: Fn(usize) -> Option<String> + Send + Sync + 'static,
+    {
+        self.line_class_fn = Some(Some(LineClassFn::new(class)));
+        self
+    }
+
+    /// Insert raw HTML at the start of each line, see [`LineCallback`].
+    pub fn line_prefix<F>(&mut self, prefix: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_prefix = Some(Some(LineCallback::new(prefix)));
+        self
+    }
+
+    /// Insert raw HTML at the end of each line, see [`LineCallback`].
+    pub fn line_suffix<F>(&mut self, suffix: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_suffix = Some(Some(LineCallback::new(suffix)));
+        self
+    }
+
+    /// Replace the `language-*` class of the `<code>` element for some languages, e.g.
+    /// `lang-js` for JavaScript. Other languages keep `language-{id}`.
+    pub fn language_class_map(&mut self, map: HashMap<Language, String>) -> &mut Self {
+        self.language_class_map = Some(LanguageClassMap(map));
+        self
+    }
 }
 
 impl HtmlInline {
@@ -190,16 +295,204 @@ impl HtmlInline {
             include_highlights,
             highlight_lines,
             header,
+            container: None,
+            copy_button: None,
+            code_id: None,
+            figure: None,
+            language_class_map: LanguageClassMap::default(),
+            scope_tooltips: false,
+            foldable: false,
+            gutter: None,
+            line_prefix: None,
+            line_suffix: None,
+            non_empty_line_marker: false,
+            trim_final_newline: false,
+            escape_braces: true,
+            escape_strategy: EscapeStrategy::Named,
+            render_whitespace: WhitespaceMode::None,
+            tab_mode: TabMode::None,
+            font_family: None,
+            font_size: None,
+            data_attributes: false,
+            auto_contrast_highlight: false,
+            line_class_fn: None,
+            injection_resolver: None,
+            email_safe: false,
         }
     }
 
-    fn get_line_attrs(&self, line_number: usize) -> (Option<String>, Option<String>) {
-        let is_highlighted = self
-            .highlight_lines
-            .as_ref()
-            .is_some_and(|hl| hl.lines.iter().any(|r| r.contains(&line_number)));
+    /// Highlight `source` into one [`RenderedLine`] per line.
+    ///
+    /// Each line holds the same HTML as in [`format`](Formatter::format) output, with the
+    /// `escape_braces`, `escape_strategy`, `tab_mode`, `render_whitespace`, `non_empty_line_marker`, `gutter`,
+    /// `line_prefix` and `line_suffix` options applied, but without the `<pre>`,
+    /// `<code>` and per-line `<div>` wrappers, so lines can be wrapped in your own
+    /// components. `trim_final_newline` is honored, while `header`, `container`,
+    /// `copy_button`, `figure`, `foldable` and `email_safe` only apply to
+    /// [`format`](Formatter::format).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if highlighting fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::{HtmlInlineBuilder, formatter::html_inline::HighlightLines, languages::Language};
+    ///
+    /// let formatter = HtmlInlineBuilder::new()
+    ///     .lang(Language::Rust)
+    ///     .theme(None)
+    ///     .highlight_lines(Some(HighlightLines { lines: vec![2..=2], ..Default::default() }))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let lines = formatter.format_structured("let a = 1;\nlet b = 2;\n").unwrap();
+    ///
+    /// assert_eq!(lines.len(), 2);
+    /// assert_eq!(lines[1].number, 2);
+    /// assert!(lines[1].highlighted);
+    /// assert!(lines[1].html.contains("let"));
+    /// ```
+    pub fn format_structured(&self, source: &str) -> io::Result<Vec<RenderedLine>> {
+        let block = self.block();
+        let renderer = self.render(&block, source)?;
+
+        Ok(block
+            .lines(&renderer)
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let number = i + 1;
+                let html = block.render_line(number, line);
+                RenderedLine {
+                    number,
+                    html: html.strip_suffix('\n').unwrap_or(&html).to_string(),
+                    highlighted: self.is_highlighted(number),
+                }
+            })
+            .collect())
+    }
+
+    /// Highlight `source` once and split the output into pages of `lines_per_page` lines.
+    ///
+    /// Each page is a complete `<pre><code>` block like [`format`](Formatter::format)
+    /// output, for viewers of large files that render one page at a time. Lines keep
+    /// their number in the whole source, so `data-line` attributes continue across pages
+    /// and `highlight_lines` ranges land on the right page. `trim_final_newline` is
+    /// honored, while `header`, `container`, `copy_button`, `figure`, `foldable` and
+    /// `email_safe` only apply to [`format`](Formatter::format). A `lines_per_page` of 0
+    /// puts every line on one page, and an empty source has no pages.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if highlighting fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::{HtmlInlineBuilder, languages::Language};
+    ///
+    /// let formatter = HtmlInlineBuilder::new()
+    ///     .lang(Language::Rust)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let pages = formatter.format_pages("let a = 1;\nlet b = 2;\nlet c = 3;\n", 2).unwrap();
+    ///
+    /// assert_eq!(pages.len(), 2);
+    /// assert!(pages[0].starts_with("<pre class=\"lumis\""));
+    /// assert!(pages[1].contains(r#"data-line="3""#));
+    /// assert!(!pages[1].contains(r#"data-line="2""#));
+    /// ```
+    pub fn format_pages(&self, source: &str, lines_per_page: usize) -> io::Result<Vec<String>> {
+        let block = self.block();
+        let renderer = self.render(&block, source)?;
+        let lines = block.lines(&renderer);
+
+        let lines_per_page = if lines_per_page == 0 {
+            lines.len().max(1)
+        } else {
+            lines_per_page
+        };
 
-        if !is_highlighted {
+        lines
+            .chunks(lines_per_page)
+            .enumerate()
+            .map(|(page, chunk)| {
+                let first_line = page * lines_per_page + 1;
+                let mut buffer = Vec::new();
+                self.open_pre_tag(&mut buffer)?;
+                block.write_code(
+                    &mut buffer,
+                    None,
+                    (first_line..).zip(chunk.iter().copied()),
+                    &Default::default(),
+                    |line_number| self.get_line_attrs(line_number),
+                )?;
+                String::from_utf8(buffer).map_err(io::Error::other)
+            })
+            .collect()
+    }
+
+    fn block(&self) -> crate::formatter::html::HtmlBlock<'_> {
+        crate::formatter::html::HtmlBlock {
+            lang: self.lang,
+            header: self.header.as_ref(),
+            container: self.container.as_ref(),
+            copy_button: self.copy_button.as_ref(),
+            code_id: self.code_id.as_deref(),
+            figure: self.figure.as_ref(),
+            language_class_map: &self.language_class_map,
+            scope_tooltips: self.scope_tooltips,
+            foldable: self.foldable,
+            gutter: self.gutter,
+            line_prefix: self.line_prefix.as_ref(),
+            line_suffix: self.line_suffix.as_ref(),
+            non_empty_line_marker: self.non_empty_line_marker,
+            trim_final_newline: self.trim_final_newline,
+            escape_braces: self.escape_braces,
+            escape_strategy: self.escape_strategy,
+            render_whitespace: self.render_whitespace,
+            tab_mode: self.tab_mode,
+            line_class_fn: self.line_class_fn.as_ref(),
+            injection_resolver: self.injection_resolver.as_ref(),
+        }
+    }
+
+    fn render(
+        &self,
+        block: &crate::formatter::html::HtmlBlock,
+        source: &str,
+    ) -> io::Result<HtmlRenderer> {
+        let contrast_bg = self
+            .get_highlight_style()
+            .and_then(|style| Some(self.contrast_background(&style)?.0.to_string()));
+
+        block.render(source, |scope, language, line, output| {
+            let bg = contrast_bg.as_deref().filter(|_| self.is_highlighted(line));
+            let attrs = crate::formatter::html::span_inline_attrs_on(
+                scope,
+                Some(Language::guess(Some(language), "")),
+                self.theme.as_ref(),
+                self.italic,
+                self.include_highlights,
+                bg,
+            );
+            output.extend(attrs.as_bytes());
+        })
+    }
+
+    fn is_highlighted(&self, line_number: usize) -> bool {
+        self.line_class_fn.is_none()
+            && self
+                .highlight_lines
+                .as_ref()
+                .is_some_and(|hl| hl.lines.iter().any(|r| r.contains(&line_number)))
+    }
+
+    fn get_line_attrs(&self, line_number: usize) -> (Option<String>, Option<String>) {
+        if !self.is_highlighted(line_number) {
             return (None, None);
         }
 
@@ -209,11 +502,35 @@ impl HtmlInline {
             .and_then(|hl| hl.class.as_ref())
             .map(|c| format!(" {}", c));
 
-        let style = self.get_highlight_style();
+        let style = self.get_highlight_style().map(|style| {
+            // Text without a token color inherits the theme foreground
+            let fg = self.theme.as_ref().and_then(|theme| theme.fg());
+            match self.contrast_background(&style) {
+                Some((bg, readable))
+                    if fg
+                        .and_then(|fg| crate::themes::contrast_ratio(fg, bg))
+                        .is_none_or(|ratio| ratio < 4.5) =>
+                {
+                    format!("{style} color: {readable};")
+                }
+                _ => style,
+            }
+        });
 
         (class_suffix, style)
     }
 
+    // The `#rrggbb` background of a highlighted line style and the readable text color
+    // on it, when `auto_contrast_highlight` is enabled.
+    fn contrast_background<'a>(&self, style: &'a str) -> Option<(&'a str, &'static str)> {
+        if !self.auto_contrast_highlight {
+            return None;
+        }
+
+        let bg = crate::formatter::html::css_background(style)?;
+        Some((bg, crate::formatter::html::contrast_color(bg)?))
+    }
+
     fn get_highlight_style(&self) -> Option<String> {
         let highlight_lines = self.highlight_lines.as_ref()?;
 
@@ -227,86 +544,171 @@ impl HtmlInline {
             None => None,
         }
     }
+
+    fn open_pre_tag(&self, output: &mut Vec<u8>) -> io::Result<()> {
+        let pre_style = [
+            self.theme.as_ref().and_then(|theme| theme.pre_style(" ")),
+            self.font_style(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+        let attrs = if self.data_attributes {
+            let theme_name = self.theme.as_ref().map(|theme| theme.name.as_str());
+            crate::formatter::html::data_attributes(&self.lang, theme_name)
+        } else {
+            String::new()
+        };
+        crate::formatter::html::open_pre_tag_with_attrs(
+            output,
+            self.pre_class.as_deref(),
+            Some(pre_style.as_str()).filter(|style| !style.is_empty()),
+            &attrs,
+        )
+    }
+
+    fn font_style(&self) -> Option<String> {
+        crate::formatter::html::font_style(self.font_family.as_deref(), self.font_size.as_deref())
+    }
+
+    fn write_email_table<'a>(
+        &self,
+        block: &crate::formatter::html::HtmlBlock,
+        output: &mut Vec<u8>,
+        lines: impl Iterator<Item = &'a str>,
+    ) -> io::Result<()> {
+        let theme_style = self
+            .theme
+            .as_ref()
+            .and_then(|theme| theme.pre_style(" "))
+            .map(|style| format!(" {style}"))
+            .unwrap_or_default();
+        let font_family = self
+            .font_family
+            .as_deref()
+            .map(crate::formatter::html::style_value)
+            .unwrap_or_else(|| "monospace".to_string());
+        let font_size = self
+            .font_size
+            .as_deref()
+            .map(|size| format!(" font-size: {};", crate::formatter::html::style_value(size)))
+            .unwrap_or_default();
+        let class = match &self.pre_class {
+            Some(pre_class) => format!("lumis {pre_class}"),
+            None => "lumis".to_string(),
+        };
+
+        write!(
+            output,
+            "<table class=\"{class}\" role=\"presentation\" cellpadding=\"0\" cellspacing=\"0\" border=\"0\" style=\"border-collapse: collapse; width: 100%;{theme_style}\"><tbody>"
+        )?;
+
+        for (i, line) in lines.enumerate() {
+            let line_number = i + 1;
+            let line = block.line_content(line);
+            // Empty cells collapse in some email clients, so blank lines hold a
+            // non-breaking space, after the empty line marker when enabled
+            let line = if line.trim_end_matches('\n').is_empty() {
+                let line = if self.non_empty_line_marker {
+                    crate::formatter::html::mark_empty_line(&line)
+                } else {
+                    line
+                };
+                format!(
+                    "{}{}",
+                    line.trim_end_matches('\n'),
+                    crate::formatter::html::nbsp(self.escape_strategy)
+                )
+            } else {
+                line
+            };
+            let line = block.decorate(line_number, &line);
+            let content = line.trim_end_matches('\n');
+            let highlight_style = self
+                .get_line_attrs(line_number)
+                .1
+                .map(|style| format!(" {style}"))
+                .unwrap_or_default();
+
+            write!(
+                output,
+                "<tr><td style=\"padding: 0 8px; font-family: {font_family};{font_size} white-space: pre;{theme_style}{highlight_style}\">{content}</td></tr>"
+            )?;
+        }
+
+        write!(output, "</tbody></table>")
+    }
 }
 
 impl Default for HtmlInline {
     fn default() -> Self {
         Self {
             lang: Language::PlainText,
-            theme: None,
+            theme: crate::themes::default(),
             pre_class: None,
             italic: false,
             include_highlights: false,
             highlight_lines: None,
             header: None,
+            container: None,
+            copy_button: None,
+            code_id: None,
+            figure: None,
+            language_class_map: LanguageClassMap::default(),
+            scope_tooltips: false,
+            foldable: false,
+            gutter: None,
+            line_prefix: None,
+            line_suffix: None,
+            non_empty_line_marker: false,
+            trim_final_newline: false,
+            escape_braces: true,
+            escape_strategy: EscapeStrategy::Named,
+            render_whitespace: WhitespaceMode::None,
+            tab_mode: TabMode::None,
+            font_family: None,
+            font_size: None,
+            data_attributes: false,
+            auto_contrast_highlight: false,
+            line_class_fn: None,
+            injection_resolver: None,
+            email_safe: false,
         }
     }
 }
 
 impl Formatter for HtmlInline {
     fn format(&self, source: &str, output: &mut dyn Write) -> io::Result<()> {
-        let mut buffer = Vec::new();
-
-        if let Some(ref header) = self.header {
-            write!(buffer, "{}", header.open_tag)?;
-        }
+        let block = self.block();
+        let renderer = self.render(&block, source)?;
+        let lines = block.lines(&renderer);
 
-        crate::formatter::html::open_pre_tag(
-            &mut buffer,
-            self.pre_class.as_deref(),
-            self.theme.as_ref(),
-        )?;
-        crate::formatter::html::open_code_tag(&mut buffer, &self.lang)?;
-
-        let mut highlighter = Highlighter::new();
-        let events = highlighter
-            .highlight(self.lang.config(), source.as_bytes(), None, |injected| {
-                Some(Language::guess(Some(injected), "").config())
-            })
-            .map_err(io::Error::other)?;
-
-        let mut renderer = HtmlRenderer::new();
-
-        renderer
-            .render(
-                events,
-                source.as_bytes(),
-                &move |highlight, language, output| {
-                    let scope = crate::constants::HIGHLIGHT_NAMES[highlight.0];
-                    let lang = Language::guess(Some(language), "");
-                    let attrs = crate::formatter::html::span_inline_attrs(
-                        scope,
-                        Some(lang),
-                        self.theme.as_ref(),
-                        self.italic,
-                        self.include_highlights,
-                    );
-                    output.extend(attrs.as_bytes());
-                },
-            )
-            .map_err(io::Error::other)?;
-
-        for (i, line) in renderer.lines().enumerate() {
-            let line_number = i + 1;
-            let line_with_braces = crate::formatter::html::escape_braces(line);
-            let (class_suffix, style) = self.get_line_attrs(line_number);
-            let wrapped = crate::formatter::html::wrap_line(
-                line_number,
-                &line_with_braces,
-                class_suffix.as_deref(),
-                style.as_deref(),
-            );
-            write!(&mut buffer, "{}", wrapped)?;
-        }
-
-        crate::formatter::html::closing_tags(&mut buffer)?;
+        // Email tables have no `<code>` element for a copy button to target.
+        block.write(source, output, !self.email_safe, |buffer, code_id| {
+            if self.email_safe {
+                self.write_email_table(&block, buffer, lines.into_iter())
+            } else {
+                self.open_pre_tag(buffer)?;
+                block.write_code(
+                    buffer,
+                    code_id,
+                    (1..).zip(lines),
+                    &block.folds(source),
+                    |line_number| self.get_line_attrs(line_number),
+                )
+            }
+        })
+    }
 
-        if let Some(ref header) = self.header {
-            write!(buffer, "{}", header.close_tag)?;
-        }
+    fn language(&self) -> Language {
+        self.lang
+    }
 
-        output.write_all(&buffer)?;
-        Ok(())
+    fn cache_key(&self, source: &str) -> Option<u64> {
+        self.block()
+            .cacheable()
+            .then(|| super::cache_key(source, self))
     }
 }
 
@@ -390,6 +792,24 @@ mod tests {
         assert!(pre_tag.contains("<pre class=\"lumis test-pre-class\" style=\"color: #1f2328; background-color: #ffffff;\">"));
     }
 
+    #[test]
+    fn test_pre_class_built_at_runtime() {
+        let formatter = {
+            let classes = ["code", "block"].join("-");
+            HtmlInlineBuilder::new()
+                .lang(Language::Rust)
+                .pre_class(Some(classes))
+                .build()
+                .unwrap()
+        };
+
+        let mut buffer = Vec::new();
+        crate::formatter::html::open_pre_tag(&mut buffer, formatter.pre_class.as_deref(), None)
+            .unwrap();
+        let pre_tag = String::from_utf8(buffer).unwrap();
+        assert_eq!(pre_tag, "<pre class=\"lumis code-block\">");
+    }
+
     #[test]
     fn test_builder_pattern() {
         let theme = themes::get("github_light").unwrap();
@@ -567,6 +987,32 @@ mod tests {
         assert_str_eq!(result, expected);
     }
 
+    #[test]
+    fn test_container_inside_header() {
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::PlainText)
+            .header(Some(HtmlElement {
+                open_tag: "<section>".to_string(),
+                close_tag: "</section>".to_string(),
+            }))
+            .container(Some(ContainerSpec {
+                tag: "figure".to_string(),
+                class: Some("code".to_string()),
+                ..Default::default()
+            }))
+            .code_id(Some("a".to_string()))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let expected = r##"<section><figure class="lumis-container code" data-lang="plaintext"><div class="lumis-header"><span class="lumis-lang">Plain Text</span><button type="button" class="lumis-copy" aria-label="Copy code" data-clipboard-target="#a">Copy</button></div><pre class="lumis"><code id="a" class="language-plaintext" translate="no" tabindex="0"><div class="line" data-line="1">a
+</div></code></pre></figure></section>"##;
+        assert_str_eq!(result, expected);
+    }
+
     #[test]
     fn test_header_with_complex_structure() {
         let header = HtmlElement {
@@ -592,4 +1038,523 @@ mod tests {
 </div></code></pre></section>"#;
         assert_str_eq!(result, expected);
     }
+
+    #[test]
+    fn test_email_safe() {
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::Rust)
+            .theme(themes::get("dracula").ok())
+            .highlight_lines(Some(HighlightLines {
+                lines: vec![3..=3],
+                style: Some(HighlightLinesStyle::Style(
+                    "background-color: #44475a;".to_string(),
+                )),
+                class: None,
+            }))
+            .email_safe(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("fn a() {\n\n}\n", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let expected = r#"<table class="lumis" role="presentation" cellpadding="0" cellspacing="0" border="0" style="border-collapse: collapse; width: 100%; color: #f8f8f2; background-color: #282a36;"><tbody><tr><td style="padding: 0 8px; font-family: monospace; white-space: pre; color: #f8f8f2; background-color: #282a36;"><span style="color: #8be9fd;">fn</span> <span style="color: #50fa7b;">a</span><span style="color: #f8f8f2;">(</span><span style="color: #f8f8f2;">)</span> <span style="color: #f8f8f2;">&lbrace;</span></td></tr><tr><td style="padding: 0 8px; font-family: monospace; white-space: pre; color: #f8f8f2; background-color: #282a36;">&nbsp;</td></tr><tr><td style="padding: 0 8px; font-family: monospace; white-space: pre; color: #f8f8f2; background-color: #282a36; background-color: #44475a;"><span style="color: #f8f8f2;">&rbrace;</span></td></tr></tbody></table>"#;
+        assert_str_eq!(result, expected);
+        assert!(!result.contains("var("));
+    }
+
+    #[test]
+    fn test_line_prefix_and_suffix() {
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::PlainText)
+            .line_prefix(|n| format!("<span class=\"gutter\">{n}</span>"))
+            .line_suffix(|_| "<button>copy</button>".to_string())
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a {\nb\n", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let expected = r#"<pre class="lumis"><code class="language-plaintext" translate="no" tabindex="0"><div class="line" data-line="1"><span class="gutter">1</span>a &lbrace;<button>copy</button>
+</div><div class="line" data-line="2"><span class="gutter">2</span>b<button>copy</button>
+</div></code></pre>"#;
+        assert_str_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_gutter() {
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::PlainText)
+            .gutter(Some(|n| if n == 1 { Some('<') } else { None }))
+            .line_prefix(|n| format!("<span class=\"ln\">{n}</span>"))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a\nb\n", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let expected = r#"<pre class="lumis"><code class="language-plaintext" translate="no" tabindex="0"><div class="line" data-line="1"><span class="gutter">&lt;</span><span class="ln">1</span>a
+</div><div class="line" data-line="2"><span class="gutter"> </span><span class="ln">2</span>b
+</div></code></pre>"#;
+        assert_str_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_non_empty_line_marker() {
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::PlainText)
+            .non_empty_line_marker(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a\n\nb\n", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let expected = r#"<pre class="lumis"><code class="language-plaintext" translate="no" tabindex="0"><div class="line" data-line="1">a
+</div><div class="line" data-line="2"><span></span>
+</div><div class="line" data-line="3">b
+</div></code></pre>"#;
+        assert_str_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_non_empty_line_marker_email_safe() {
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::PlainText)
+            .theme(None)
+            .non_empty_line_marker(true)
+            .email_safe(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a\n\nb\n", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.contains(
+            r#"<td style="padding: 0 8px; font-family: monospace; white-space: pre;">a</td>"#
+        ));
+        assert!(result.contains(
+            r#"<td style="padding: 0 8px; font-family: monospace; white-space: pre;"><span></span>&nbsp;</td>"#
+        ));
+    }
+
+    #[test]
+    fn test_font_family_and_size() {
+        let builder = || {
+            let mut builder = HtmlInlineBuilder::new();
+            builder
+                .lang(Language::Rust)
+                .theme(themes::get("dracula").ok())
+                .font_family(Some("'Fira Code', monospace".to_string()))
+                .font_size(Some("14px".to_string()));
+            builder
+        };
+
+        let mut buffer = Vec::new();
+        builder().build().unwrap().format("a", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert!(result.starts_with(r#"<pre class="lumis" style="color: #f8f8f2; background-color: #282a36; font-family: 'Fira Code', monospace; font-size: 14px;">"#));
+
+        let mut buffer = Vec::new();
+        builder()
+            .email_safe(true)
+            .build()
+            .unwrap()
+            .format("a", &mut buffer)
+            .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert!(result.contains(r#"<td style="padding: 0 8px; font-family: 'Fira Code', monospace; font-size: 14px; white-space: pre;"#));
+    }
+
+    #[test]
+    fn test_font_values_are_escaped() {
+        for email_safe in [false, true] {
+            let formatter = HtmlInlineBuilder::new()
+                .theme(None)
+                .font_family(Some("x\" onclick=\"alert(1)".to_string()))
+                .font_size(Some("1em; background: url(x)</style>".to_string()))
+                .email_safe(email_safe)
+                .build()
+                .unwrap();
+
+            let mut buffer = Vec::new();
+            formatter.format("a", &mut buffer).unwrap();
+            let result = String::from_utf8(buffer).unwrap();
+
+            assert!(result.contains("font-family: x&quot; onclick=&quot;alert(1); font-size: 1em;"));
+            assert!(!result.contains("background: url"));
+        }
+    }
+
+    #[test]
+    fn test_format_structured() {
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::Rust)
+            .theme(themes::get("dracula").ok())
+            .highlight_lines(Some(HighlightLines {
+                lines: vec![2..=2],
+                ..Default::default()
+            }))
+            .trim_final_newline(true)
+            .build()
+            .unwrap();
+
+        let source = "fn a() {}\nfn b() {}\n";
+        let lines = formatter.format_structured(source).unwrap();
+
+        assert_eq!(
+            lines
+                .iter()
+                .map(|line| (line.number, line.highlighted))
+                .collect::<Vec<_>>(),
+            vec![(1, false), (2, true)]
+        );
+        assert!(lines[0]
+            .html
+            .starts_with(r#"<span style="color: #8be9fd;">fn</span>"#));
+        assert!(lines[1].html.ends_with("&rbrace;</span>"));
+
+        let mut buffer = Vec::new();
+        formatter.format(source, &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        for line in &lines {
+            assert!(result.contains(&line.html));
+        }
+    }
+
+    #[test]
+    fn test_data_attributes() {
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::Rust)
+            .theme(themes::get("dracula").ok())
+            .data_attributes(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.starts_with(
+            r#"<pre class="lumis" data-lang="rust" data-theme="dracula" style="color: #f8f8f2; background-color: #282a36;">"#
+        ));
+    }
+
+    #[test]
+    fn test_line_class_fn() {
+        let formatter = HtmlInlineBuilder::new()
+            .highlight_lines(Some(HighlightLines {
+                lines: vec![1..=3],
+                style: Some(HighlightLinesStyle::Style("color: red".to_string())),
+                class: Some("hl".to_string()),
+            }))
+            .line_class_fn(|n| (n % 2 == 0).then(|| format!("even line-{n}")))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a\nb\nc", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.contains(r#"<div class="line" data-line="1">a"#));
+        assert!(result.contains(r#"<div class="line even line-2" data-line="2">b"#));
+        assert!(result.contains(r#"<div class="line" data-line="3">c"#));
+        assert!(!result.contains("color: red"));
+    }
+
+    #[test]
+    fn test_auto_contrast_highlight() {
+        let render = |auto_contrast, line| {
+            let formatter = HtmlInlineBuilder::new()
+                .lang(Language::Rust)
+                .theme(themes::get("dracula").ok())
+                .highlight_lines(Some(HighlightLines {
+                    lines: vec![line..=line],
+                    style: Some(HighlightLinesStyle::Style(
+                        "background-color: #f1fa8c;".to_string(),
+                    )),
+                    class: None,
+                }))
+                .auto_contrast_highlight(auto_contrast)
+                .build()
+                .unwrap();
+
+            let mut buffer = Vec::new();
+            formatter
+                .format("fn main() {}\nfn b() {}", &mut buffer)
+                .unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        let result = render(true, 1);
+        let (first, second) = result.split_once("</div>").unwrap();
+        assert!(first.contains(
+            r#"<div class="line" style="background-color: #f1fa8c; color: #000000;" data-line="1"><span style="color: #000000;">fn</span> <span style="color: #000000;">main</span>"#
+        ));
+        assert!(second.contains(r#"<span style="color: #8be9fd;">fn</span>"#));
+
+        let result = render(true, 2);
+        let (first, second) = result.split_once("</div>").unwrap();
+        assert!(first.contains(r#"<span style="color: #8be9fd;">fn</span>"#));
+        assert!(second.contains(r#"<span style="color: #000000;">fn</span>"#));
+
+        // Spans reopened on the lines of a multiline token
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::Rust)
+            .theme(themes::get("dracula").ok())
+            .highlight_lines(Some(HighlightLines {
+                lines: vec![2..=2],
+                style: Some(HighlightLinesStyle::Style(
+                    "background-color: #f1fa8c;".to_string(),
+                )),
+                class: None,
+            }))
+            .auto_contrast_highlight(true)
+            .build()
+            .unwrap();
+        let lines = formatter.format_structured("/* a\r\nb\n c */").unwrap();
+        assert!(lines[0].html.contains("#6272a4"));
+        assert!(lines[1].html.contains(r#"<span style="color: #000000;">b"#));
+        assert!(lines[2].html.contains("#6272a4"));
+
+        let result = render(false, 1);
+        assert!(result.contains(
+            r#"<div class="line" style="background-color: #f1fa8c;" data-line="1"><span style="color: #8be9fd;">fn</span>"#
+        ));
+    }
+
+    #[test]
+    fn test_copy_button() {
+        let formatter = HtmlInlineBuilder::new()
+            .container(Some(ContainerSpec {
+                include_lang_label: false,
+                include_copy_button: false,
+                ..Default::default()
+            }))
+            .copy_button(Some(CopyButtonSpec {
+                class: "btn copy".to_string(),
+                label: "Copy <code>".to_string(),
+            }))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let code_id = result
+            .split("data-clipboard-target=\"#")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap();
+        assert!(code_id.starts_with("lumis-code-"));
+        assert!(result.starts_with(&format!(
+            r##"<div class="lumis-container" data-lang="plaintext"><button type="button" class="btn copy" data-clipboard-target="#{code_id}">Copy &lt;code&gt;</button><pre class="lumis">"##
+        )));
+        assert!(result.contains(&format!(
+            r#"<code id="{code_id}" class="language-plaintext""#
+        )));
+
+        // The container's own button replaces the standalone one
+        let formatter = HtmlInlineBuilder::new()
+            .container(Some(ContainerSpec {
+                include_lang_label: false,
+                ..Default::default()
+            }))
+            .copy_button(Some(CopyButtonSpec::default()))
+            .code_id(Some("snippet".to_string()))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert_eq!(result.matches("<button").count(), 1);
+        assert!(result.starts_with(
+            r##"<div class="lumis-container" data-lang="plaintext"><div class="lumis-header"><button type="button" class="lumis-copy" aria-label="Copy code" data-clipboard-target="#snippet">Copy</button></div><pre class="lumis">"##
+        ));
+        assert!(result.contains(r#"<code id="snippet" class="language-plaintext""#));
+
+        let email = HtmlInlineBuilder::new()
+            .copy_button(Some(CopyButtonSpec::default()))
+            .email_safe(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        email.format("a", &mut buffer).unwrap();
+        assert!(!String::from_utf8(buffer).unwrap().contains("<button"));
+    }
+
+    #[test]
+    fn test_format_pages() {
+        let source = "a\nb\nc\nd\ne\n";
+        let formatter = HtmlInlineBuilder::new()
+            .highlight_lines(Some(HighlightLines {
+                lines: vec![3..=3],
+                style: Some(HighlightLinesStyle::Style("color: red".to_string())),
+                class: None,
+            }))
+            .build()
+            .unwrap();
+
+        let pages = formatter.format_pages(source, 2).unwrap();
+
+        assert_eq!(pages.len(), 3);
+        assert!(pages.iter().all(
+            |page| page.starts_with("<pre class=\"lumis\">") && page.ends_with("</code></pre>")
+        ));
+        assert!(!pages[0].contains("color: red"));
+        assert!(pages[1].contains("color: red"));
+        assert!(pages[1].contains(r#"data-line="3""#));
+        assert!(pages[1].contains(r#"data-line="4">d"#));
+        assert!(pages[2].contains(r#"data-line="5">e"#));
+
+        let mut buffer = Vec::new();
+        formatter.format(source, &mut buffer).unwrap();
+        assert_eq!(
+            formatter.format_pages(source, 0).unwrap(),
+            vec![String::from_utf8(buffer).unwrap()]
+        );
+        assert!(formatter.format_pages("", 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_figure() {
+        let formatter = HtmlInlineBuilder::new()
+            .header(Some(HtmlElement {
+                open_tag: "<section>".to_string(),
+                close_tag: "</section>".to_string(),
+            }))
+            .container(Some(ContainerSpec {
+                include_lang_label: false,
+                include_copy_button: false,
+                ..Default::default()
+            }))
+            .figure(Some(FigureSpec {
+                caption: "<a & b>.txt".to_string(),
+            }))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.starts_with(
+            r#"<section><div class="lumis-container" data-lang="plaintext"><figure class="lumis-figure"><figcaption>&lt;a &amp; b&gt;.txt</figcaption><pre class="lumis">"#
+        ));
+        assert!(result.ends_with("</pre></figure></div></section>"));
+
+        let formatter = HtmlInlineBuilder::new()
+            .figure(Some(FigureSpec::default()))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.starts_with(r#"<figure class="lumis-figure"><pre class="lumis">"#));
+        assert!(!result.contains("<figcaption>"));
+    }
+
+    #[test]
+    fn test_scope_tooltips() {
+        let theme = themes::get("dracula").unwrap();
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::Rust)
+            .theme(Some(theme))
+            .scope_tooltips(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("fn", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(
+            result.contains(r#"<span style="color: #8be9fd;" title="keyword.function">fn</span>"#)
+        );
+        assert!(!result.contains("data-highlight"));
+    }
+
+    #[test]
+    fn test_scope_tooltips_with_include_highlights() {
+        let formatter = HtmlInlineBuilder::new()
+            .lang(Language::Rust)
+            .include_highlights(true)
+            .scope_tooltips(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("fn", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.contains(
+            r#"<span data-highlight="keyword.function" title="keyword.function">fn</span>"#
+        ));
+    }
+
+    #[test]
+    fn test_escape_strategy() {
+        let source = "if a < b { \"é\" }";
+        let format = |strategy, escape_braces| {
+            let formatter = HtmlInlineBuilder::new()
+                .lang(Language::PlainText)
+                .escape_strategy(strategy)
+                .escape_braces(escape_braces)
+                .build()
+                .unwrap();
+            let mut buffer = Vec::new();
+            formatter.format(source, &mut buffer).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert!(format(EscapeStrategy::Named, true)
+            .contains("if a &lt; b &lbrace; &quot;é&quot; &rbrace;"));
+        assert!(
+            format(EscapeStrategy::Minimal, true).contains("if a &lt; b &lbrace; \"é\" &rbrace;")
+        );
+        assert!(format(EscapeStrategy::Numeric, false).contains("if a &#60; b { &#34;é&#34; }"));
+        assert!(format(EscapeStrategy::NonAscii, true)
+            .contains("if a &#60; b &#123; &#34;&#233;&#34; &#125;"));
+    }
+
+    #[test]
+    fn test_escape_strategy_labels() {
+        let format = |email_safe| {
+            let formatter = HtmlInlineBuilder::new()
+                .lang(Language::PlainText)
+                .escape_strategy(EscapeStrategy::Numeric)
+                .figure(Some(FigureSpec {
+                    caption: "{a}".to_string(),
+                }))
+                .copy_button(Some(CopyButtonSpec {
+                    label: "<Copy>".to_string(),
+                    ..Default::default()
+                }))
+                .email_safe(email_safe)
+                .build()
+                .unwrap();
+            let mut buffer = Vec::new();
+            formatter.format("a\n\nb\n", &mut buffer).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        let result = format(false);
+        assert!(result.contains("<figcaption>&#123;a&#125;</figcaption>"));
+        assert!(result.contains(">&#60;Copy&#62;</button>"));
+
+        let result = format(true);
+        assert!(result.contains(">&#160;</td>"));
+        assert!(!result.contains("&nbsp;"));
+    }
 }
@@ -15,11 +15,15 @@
 //!
 //! See the [formatter](crate::formatter) module for more information and examples.
 
-use super::{Formatter, HtmlElement};
+use super::{
+    ContainerSpec, CopyButtonSpec, EscapeStrategy, FigureSpec, Formatter, GutterCallback,
+    HtmlElement, InjectionResolver, LanguageClassMap, LineCallback, LineClassFn, TabMode,
+    WhitespaceMode,
+};
 use crate::languages::Language;
-use crate::vendor::tree_sitter_highlight::{Highlighter, HtmlRenderer};
 use derive_builder::Builder;
 use std::{
+    collections::HashMap,
     io::{self, Write},
     ops::RangeInclusive,
 };
@@ -60,7 +64,7 @@ use std::{
 /// ```html
 /// <div class="line transition-colors duration-500 w-full inline-block bg-yellow-500" data-line="2">...</div>
 /// ```
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Hash)]
 pub struct HighlightLines {
     /// List of line ranges to highlight.
     ///
@@ -127,19 +131,114 @@ impl Default for HighlightLines {
 /// formatter.format(code, &mut output).unwrap();
 /// // Remember to include the corresponding CSS file for your theme
 /// ```
-#[derive(Builder, Clone, Debug)]
+///
+/// Set `include_highlights(true)` to also add the original scope name as a
+/// `data-highlight` attribute, as the inline formatters do, which is handy for debugging.
+///
+/// Set `pygments_classes(true)` to use the short Pygments token classes instead, see
+/// [`scope_to_pygments_class`](crate::formatter::html::scope_to_pygments_class), so
+/// existing Pygments stylesheets apply. These stylesheets usually target the
+/// `highlight` class, which can be added with `pre_class`:
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::Formatter};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::Rust)
+///     .pre_class(Some("highlight".to_string()))
+///     .pygments_classes(true)
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("fn main() {}", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.contains(r#"<span class="kd">fn</span> <span class="nf">main</span>"#));
+/// ```
+#[derive(Builder, Clone, Debug, Hash)]
 #[builder(default)]
 pub struct HtmlLinked {
     lang: Language,
     pre_class: Option<String>,
     highlight_lines: Option<HighlightLines>,
     header: Option<HtmlElement>,
+    container: Option<ContainerSpec>,
+    copy_button: Option<CopyButtonSpec>,
+    code_id: Option<String>,
+    figure: Option<FigureSpec>,
+    #[builder(setter(custom))]
+    language_class_map: LanguageClassMap,
+    include_highlights: bool,
+    pygments_classes: bool,
+    scope_tooltips: bool,
+    foldable: bool,
+    gutter: Option<GutterCallback>,
+    #[builder(setter(custom))]
+    line_prefix: Option<LineCallback>,
+    #[builder(setter(custom))]
+    line_suffix: Option<LineCallback>,
+    non_empty_line_marker: bool,
+    trim_final_newline: bool,
+    escape_braces: bool,
+    escape_strategy: EscapeStrategy,
+    render_whitespace: WhitespaceMode,
+    tab_mode: TabMode,
+    font_family: Option<String>,
+    font_size: Option<String>,
+    data_attributes: bool,
+    #[builder(setter(custom))]
+    line_class_fn: Option<LineClassFn>,
+    #[builder(setter(custom))]
+    injection_resolver: Option<InjectionResolver>,
 }
 
 impl HtmlLinkedBuilder {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Decide which language highlights each injected region, see [`InjectionResolver`].
+    pub fn injection_resolver<F>(&mut self, resolver: F) -> &mut Self
+    where
+        F: Fn(&str) -> Option<Language> + Send + Sync + 'static,
+    {
+        self.injection_resolver = Some(Some(InjectionResolver::new(resolver)));
+        self
+    }
+
+    /// Set the class of each line with a callback, see [`LineClassFn`].
+    pub fn line_class_fn<F>(&mut self, class: F) -> &mut Self
+    where
+        F: Fn(usize) -> Option<String> + Send + Sync + 'static,
+    {
+        self.line_class_fn = Some(Some(LineClassFn::new(class)));
+        self
+    }
+
+    /// Insert raw HTML at the start of each line, see [`LineCallback`].
+    pub fn line_prefix<F>(&mut self, prefix: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_prefix = Some(Some(LineCallback::new(prefix)));
+        self
+    }
+
+    /// Insert raw HTML at the end of each line, see [`LineCallback`].
+    pub fn line_suffix<F>(&mut self, suffix: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_suffix = Some(Some(LineCallback::new(suffix)));
+        self
+    }
+
+    /// Replace the `language-*` class of the `<code>` element for some languages, e.g.
+    /// `lang-js` for JavaScript. Other languages keep `language-{id}`.
+    pub fn language_class_map(&mut self, map: HashMap<Language, String>) -> &mut Self {
+        self.language_class_map = Some(LanguageClassMap(map));
+        self
+    }
 }
 
 impl HtmlLinked {
@@ -154,6 +253,29 @@ impl HtmlLinked {
             pre_class,
             highlight_lines,
             header,
+            container: None,
+            copy_button: None,
+            code_id: None,
+            figure: None,
+            language_class_map: LanguageClassMap::default(),
+            include_highlights: false,
+            pygments_classes: false,
+            scope_tooltips: false,
+            foldable: false,
+            gutter: None,
+            line_prefix: None,
+            line_suffix: None,
+            non_empty_line_marker: false,
+            trim_final_newline: false,
+            escape_braces: true,
+            escape_strategy: EscapeStrategy::Named,
+            render_whitespace: WhitespaceMode::None,
+            tab_mode: TabMode::None,
+            font_family: None,
+            font_size: None,
+            data_attributes: false,
+            line_class_fn: None,
+            injection_resolver: None,
         }
     }
 }
@@ -165,73 +287,167 @@ impl Default for HtmlLinked {
             pre_class: None,
             highlight_lines: None,
             header: None,
+            container: None,
+            copy_button: None,
+            code_id: None,
+            figure: None,
+            language_class_map: LanguageClassMap::default(),
+            include_highlights: false,
+            pygments_classes: false,
+            scope_tooltips: false,
+            foldable: false,
+            gutter: None,
+            line_prefix: None,
+            line_suffix: None,
+            non_empty_line_marker: false,
+            trim_final_newline: false,
+            escape_braces: true,
+            escape_strategy: EscapeStrategy::Named,
+            render_whitespace: WhitespaceMode::None,
+            tab_mode: TabMode::None,
+            font_family: None,
+            font_size: None,
+            data_attributes: false,
+            line_class_fn: None,
+            injection_resolver: None,
         }
     }
 }
 
 impl Formatter for HtmlLinked {
     fn format(&self, source: &str, output: &mut dyn Write) -> io::Result<()> {
-        let mut buffer = Vec::new();
+        self.block().format(
+            source,
+            output,
+            |buffer| self.open_pre_tag(buffer),
+            |scope, _language, _line, output| {
+                if self.include_highlights {
+                    output.extend(format!("data-highlight=\"{}\" ", scope).as_bytes());
+                }
+                if self.pygments_classes {
+                    if let Some(class) = crate::formatter::html::scope_to_pygments_class(scope) {
+                        output.extend(format!("class=\"{}\"", class).as_bytes());
+                    }
+                } else {
+                    output.extend(crate::formatter::html::span_linked_attrs(scope).as_bytes());
+                }
+            },
+            |line_number| (self.class_suffix(line_number), None),
+        )
+    }
 
-        if let Some(ref header) = self.header {
-            write!(buffer, "{}", header.open_tag)?;
-        }
+    fn language(&self) -> Language {
+        self.lang
+    }
 
-        crate::formatter::html::open_pre_tag(&mut buffer, self.pre_class.as_deref(), None)?;
-        crate::formatter::html::open_code_tag(&mut buffer, &self.lang)?;
+    fn cache_key(&self, source: &str) -> Option<u64> {
+        self.block()
+            .cacheable()
+            .then(|| super::cache_key(source, self))
+    }
+}
 
-        let mut highlighter = Highlighter::new();
-        let events = highlighter
-            .highlight(self.lang.config(), source.as_bytes(), None, |injected| {
-                Some(Language::guess(Some(injected), "").config())
+impl HtmlLinked {
+    /// Render `source` with the same markup as [`format`](Formatter::format) but
+    /// without highlighting, see [`highlight::plain`](crate::highlight::plain).
+    pub(crate) fn format_plain(&self, source: &str, output: &mut dyn Write) -> io::Result<()> {
+        let lines: Vec<String> = source
+            .split_inclusive('\n')
+            .map(|line| {
+                let line = line.trim_end_matches('\n');
+                format!("{}\n", escape_plain(line, self.escape_strategy))
             })
-            .map_err(io::Error::other)?;
-
-        let mut renderer = HtmlRenderer::new();
-
-        renderer
-            .render(
-                events,
-                source.as_bytes(),
-                &move |highlight, _language, output| {
-                    let scope = crate::constants::HIGHLIGHT_NAMES[highlight.0];
-                    let attrs = crate::formatter::html::span_linked_attrs(scope);
-                    output.extend(attrs.as_bytes());
-                },
-            )
-            .map_err(io::Error::other)?;
+            .collect();
+        let mut lines: Vec<&str> = lines.iter().map(String::as_str).collect();
 
-        for (i, line) in renderer.lines().enumerate() {
-            let line_number = i + 1;
-            let class_suffix = self.highlight_lines.as_ref().and_then(|hl| {
-                if hl.lines.iter().any(|range| range.contains(&line_number)) {
-                    Some(format!(" {}", hl.class))
-                } else {
-                    None
-                }
-            });
-
-            let line_with_braces = crate::formatter::html::escape_braces(line);
-            let wrapped = crate::formatter::html::wrap_line(
-                line_number,
-                &line_with_braces,
-                class_suffix.as_deref(),
-                None,
-            );
-            write!(&mut buffer, "{}", wrapped)?;
+        let block = crate::formatter::html::HtmlBlock {
+            escape_braces: false,
+            ..self.block()
+        };
+        if block.trim_final_newline {
+            crate::formatter::html::trim_final_empty_line(&mut lines);
         }
 
-        crate::formatter::html::closing_tags(&mut buffer)?;
+        block.write(source, output, true, |buffer, code_id| {
+            self.open_pre_tag(buffer)?;
+            block.write_code(
+                buffer,
+                code_id,
+                (1..).zip(lines),
+                &Default::default(),
+                |line_number| (self.class_suffix(line_number), None),
+            )
+        })
+    }
 
-        if let Some(ref header) = self.header {
-            write!(buffer, "{}", header.close_tag)?;
+    fn block(&self) -> crate::formatter::html::HtmlBlock<'_> {
+        crate::formatter::html::HtmlBlock {
+            lang: self.lang,
+            header: self.header.as_ref(),
+            container: self.container.as_ref(),
+            copy_button: self.copy_button.as_ref(),
+            code_id: self.code_id.as_deref(),
+            figure: self.figure.as_ref(),
+            language_class_map: &self.language_class_map,
+            scope_tooltips: self.scope_tooltips,
+            foldable: self.foldable,
+            gutter: self.gutter,
+            line_prefix: self.line_prefix.as_ref(),
+            line_suffix: self.line_suffix.as_ref(),
+            non_empty_line_marker: self.non_empty_line_marker,
+            trim_final_newline: self.trim_final_newline,
+            escape_braces: self.escape_braces,
+            escape_strategy: self.escape_strategy,
+            render_whitespace: self.render_whitespace,
+            tab_mode: self.tab_mode,
+            line_class_fn: self.line_class_fn.as_ref(),
+            injection_resolver: self.injection_resolver.as_ref(),
         }
+    }
+
+    fn open_pre_tag(&self, output: &mut Vec<u8>) -> io::Result<()> {
+        let font_style = crate::formatter::html::font_style(
+            self.font_family.as_deref(),
+            self.font_size.as_deref(),
+        );
+        let attrs = if self.data_attributes {
+            crate::formatter::html::data_attributes(&self.lang, None)
+        } else {
+            String::new()
+        };
+        crate::formatter::html::open_pre_tag_with_attrs(
+            output,
+            self.pre_class.as_deref(),
+            font_style.as_deref(),
+            &attrs,
+        )
+    }
 
-        output.write_all(&buffer)?;
-        Ok(())
+    // Class suffix of the lines in `highlight_lines`.
+    fn class_suffix(&self, line_number: usize) -> Option<String> {
+        self.highlight_lines.as_ref().and_then(|hl| {
+            hl.lines
+                .iter()
+                .any(|range| range.contains(&line_number))
+                .then(|| format!(" {}", hl.class))
+        })
     }
 }
 
+// Escape like the highlighter's HTML renderer, which drops carriage returns.
+fn escape_plain(text: &str, strategy: EscapeStrategy) -> String {
+    let mut buf = String::with_capacity(text.len());
+
+    for c in text.chars().filter(|&c| c != '\r') {
+        match crate::formatter::html::escape_source_char(c, strategy) {
+            Some(entity) => buf.push_str(&entity),
+            None => buf.push(c),
+        }
+    }
+
+    buf
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -389,4 +605,220 @@ mod tests {
 </div></code></pre></section>"#;
         assert_str_eq!(result, expected);
     }
+
+    #[test]
+    fn test_foldable() {
+        let formatter = HtmlLinkedBuilder::new()
+            .lang(Language::Fish)
+            .foldable(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter
+            .format("function a\n    if true\n    end\nend\n", &mut buffer)
+            .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert_eq!(
+            result
+                .matches("<details class=\"fold\" open><summary>")
+                .count(),
+            2
+        );
+        assert_eq!(result.matches("</details>").count(), 2);
+        assert!(result
+            .contains(r#"<details class="fold" open><summary><div class="line" data-line="1">"#));
+        assert!(result.contains("\n</div></details><div class=\"line\" data-line=\"4\">"));
+        assert!(result.ends_with("</div></details></code></pre>"));
+    }
+
+    #[test]
+    fn test_scope_tooltips() {
+        let formatter = HtmlLinkedBuilder::new()
+            .lang(Language::Rust)
+            .scope_tooltips(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("fn", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(
+            result.contains(r#"<span class="keyword-function" title="keyword.function">fn</span>"#)
+        );
+    }
+
+    #[test]
+    fn test_trim_final_newline() {
+        let formatter = HtmlLinkedBuilder::new()
+            .lang(Language::PlainText)
+            .trim_final_newline(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a\n\n", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let expected = r#"<pre class="lumis"><code class="language-plaintext" translate="no" tabindex="0"><div class="line" data-line="1">a
+</div></code></pre>"#;
+        assert_str_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_escape_braces_disabled() {
+        let formatter = HtmlLinkedBuilder::new()
+            .lang(Language::PlainText)
+            .escape_braces(false)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("{a}", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.contains(r#"data-line="1">{a}"#));
+        assert!(!result.contains("&lbrace;"));
+    }
+
+    #[test]
+    fn test_font_family_and_size() {
+        let formatter = HtmlLinkedBuilder::new()
+            .font_family(Some("monospace".to_string()))
+            .font_size(Some("0.9rem".to_string()))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.starts_with(
+            r#"<pre class="lumis" style="font-family: monospace; font-size: 0.9rem;"><code"#
+        ));
+    }
+
+    #[test]
+    fn test_pygments_classes() {
+        let formatter = HtmlLinkedBuilder::new()
+            .lang(Language::Rust)
+            .pygments_classes(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter
+            .format("use std::io;\nlet x = \"a\\n\";", &mut buffer)
+            .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.contains(r#"<span class="kn">use</span>"#));
+        assert!(result.contains(r#"<span class="se">\n</span>"#));
+        assert!(result.contains(r#"<span class="p">;</span>"#));
+        assert!(result.contains(r#"<span class="n">x</span>"#));
+        assert!(!result.contains("keyword"));
+    }
+
+    #[test]
+    fn test_include_highlights() {
+        let formatter = HtmlLinkedBuilder::new()
+            .lang(Language::Rust)
+            .include_highlights(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("fn", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.contains(
+            r#"<span data-highlight="keyword.function" class="keyword-function">fn</span>"#
+        ));
+    }
+
+    #[test]
+    fn test_language_class_map() {
+        let map = || {
+            HashMap::from([
+                (Language::JavaScript, "lang-js".to_string()),
+                (Language::TypeScript, "lang-ts".to_string()),
+                (Language::Python, "a\"b".to_string()),
+            ])
+        };
+        let format = |lang: Language| {
+            let formatter = HtmlLinkedBuilder::new()
+                .lang(lang)
+                .language_class_map(map())
+                .build()
+                .unwrap();
+            let mut buffer = Vec::new();
+            formatter.format("x", &mut buffer).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert!(format(Language::JavaScript).contains(r#"<code class="lang-js" translate="no""#));
+        assert!(format(Language::Rust).contains(r#"<code class="language-rust" translate="no""#));
+        assert!(format(Language::Python).contains(r#"<code class="a&quot;b""#));
+
+        let build = || {
+            HtmlLinkedBuilder::new()
+                .language_class_map(map())
+                .build()
+                .unwrap()
+        };
+        assert_eq!(build().cache_key("x"), build().cache_key("x"));
+    }
+
+    #[test]
+    fn test_cache_key_without_callbacks() {
+        let plain = HtmlLinkedBuilder::new().build().unwrap();
+        let gutter = HtmlLinkedBuilder::new()
+            .gutter(Some(|_| Some('+')))
+            .build()
+            .unwrap();
+        let line_class = HtmlLinkedBuilder::new()
+            .line_class_fn(|_| None)
+            .build()
+            .unwrap();
+        let prefix = HtmlLinkedBuilder::new()
+            .line_prefix(|n| format!("<b>{n}</b>"))
+            .build()
+            .unwrap();
+
+        assert!(plain.cache_key("x").is_some());
+        assert_eq!(gutter.cache_key("x"), None);
+        assert_eq!(line_class.cache_key("x"), None);
+        assert_eq!(prefix.cache_key("x"), None);
+    }
+
+    #[test]
+    fn test_plain_escape_strategy() {
+        let source = "let s = \"<é>\" & 'a';\r\n";
+
+        for strategy in [EscapeStrategy::Minimal, EscapeStrategy::NonAscii] {
+            let formatter = HtmlLinkedBuilder::new()
+                .lang(Language::Rust)
+                .escape_strategy(strategy)
+                .build()
+                .unwrap();
+            let mut buffer = Vec::new();
+            formatter.format(source, &mut buffer).unwrap();
+            let highlighted = String::from_utf8(buffer).unwrap();
+
+            let mut text = String::new();
+            let mut rest = highlighted.as_str();
+            while let Some(start) = rest.find("<span") {
+                text.push_str(&rest[..start]);
+                rest = &rest[start + rest[start..].find('>').unwrap() + 1..];
+            }
+            text.push_str(rest);
+
+            assert_eq!(
+                text.replace("</span>", ""),
+                crate::highlight::plain(source, &formatter)
+            );
+        }
+    }
 }
@@ -120,13 +120,17 @@
 //! and [html_multi_themesi.html](https://github.com/leandrocp/lumis/blob/main/examples/html_multi_themesi.html) for a demo.
 //!
 
-use super::{Formatter, HtmlElement};
+use super::{
+    ContainerSpec, CopyButtonSpec, EscapeStrategy, FigureSpec, Formatter, GutterCallback,
+    HtmlElement, InjectionResolver, LanguageClassMap, LineCallback, LineClassFn, TabMode,
+    WhitespaceMode,
+};
 use crate::formatter::html_inline::HighlightLines;
 use crate::languages::Language;
 use crate::themes::Theme;
-use crate::vendor::tree_sitter_highlight::{Highlighter, HtmlRenderer};
 use derive_builder::Builder;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
 use std::io::{self, Write};
 use std::str::FromStr;
 
@@ -136,7 +140,7 @@ use std::str::FromStr;
 /// while other themes are defined as CSS variables (e.g., `--lumis-dark:#ff7b72`).
 ///
 /// Use `Option<DefaultTheme>` where `None` means no default theme (all CSS variables only).
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Hash)]
 pub enum DefaultTheme {
     /// Use a specific named theme as the default (e.g., "light", "dark")
     Theme(String),
@@ -195,6 +199,32 @@ pub struct HtmlMultiThemes {
     include_highlights: bool,
     highlight_lines: Option<HighlightLines>,
     header: Option<HtmlElement>,
+    container: Option<ContainerSpec>,
+    copy_button: Option<CopyButtonSpec>,
+    code_id: Option<String>,
+    figure: Option<FigureSpec>,
+    #[builder(setter(custom))]
+    language_class_map: LanguageClassMap,
+    scope_tooltips: bool,
+    foldable: bool,
+    gutter: Option<GutterCallback>,
+    #[builder(setter(custom))]
+    line_prefix: Option<LineCallback>,
+    #[builder(setter(custom))]
+    line_suffix: Option<LineCallback>,
+    non_empty_line_marker: bool,
+    trim_final_newline: bool,
+    escape_braces: bool,
+    escape_strategy: EscapeStrategy,
+    render_whitespace: WhitespaceMode,
+    tab_mode: TabMode,
+    font_family: Option<String>,
+    font_size: Option<String>,
+    data_attributes: bool,
+    #[builder(setter(custom))]
+    line_class_fn: Option<LineClassFn>,
+    #[builder(setter(custom))]
+    injection_resolver: Option<InjectionResolver>,
 }
 
 /// Builder for creating [`HtmlMultiThemes`] formatters.
@@ -224,12 +254,89 @@ impl HtmlMultiThemesBuilder {
         Self::default()
     }
 
+    /// Decide which language highlights each injected region, see [`InjectionResolver`].
+    pub fn injection_resolver<F>(&mut self, resolver: F) -> &mut Self
+    where
+        F: Fn(&str) -> Option<Language> + Send + Sync + 'static,
+    {
+        self.injection_resolver = Some(Some(InjectionResolver::new(resolver)));
+        self
+    }
+
+    /// Set the class of each line with a callback, see [`LineClassFn`].
+    pub fn line_class_fn<F>(&mut self, class: F) -> &mut Self
+    where
+        F: Fn(usize) -> Option<String> + Send + Sync + 'static,
+    {
+        self.line_class_fn = Some(Some(LineClassFn::new(class)));
+        self
+    }
+
+    /// Insert raw HTML at the start of each line, see [`LineCallback`].
+    pub fn line_prefix<F>(&mut self, prefix: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_prefix = Some(Some(LineCallback::new(prefix)));
+        self
+    }
+
+    /// Insert raw HTML at the end of each line, see [`LineCallback`].
+    pub fn line_suffix<F>(&mut self, suffix: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_suffix = Some(Some(LineCallback::new(suffix)));
+        self
+    }
+
+    /// Replace the `language-*` class of the `<code>` element for some languages, e.g.
+    /// `lang-js` for JavaScript. Other languages keep `language-{id}`.
+    pub fn language_class_map(&mut self, map: HashMap<Language, String>) -> &mut Self {
+        self.language_class_map = Some(LanguageClassMap(map));
+        self
+    }
+
     pub fn default_theme<T: Into<DefaultThemeArg>>(&mut self, value: T) -> &mut Self {
         self.default_theme = Some(value.into().into_enum());
         self
     }
 
+    /// Build the formatter, see [`build_with_warnings`](Self::build_with_warnings) to
+    /// also get the warnings about themes without styles for the language.
     pub fn build(&mut self) -> Result<HtmlMultiThemes, String> {
+        self.build_with_warnings().map(|(formatter, _)| formatter)
+    }
+
+    /// Build the formatter and return it with the warnings collected along the way.
+    ///
+    /// A warning is emitted for each theme in the map that doesn't resolve a style for
+    /// any highlight scope of the configured language, which usually means the theme is
+    /// empty or misconfigured. The formatter still works, but tokens for that theme will
+    /// be rendered without colors.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::{HtmlMultiThemesBuilder, languages::Language, themes::{self, Theme}};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut themes = HashMap::new();
+    /// themes.insert("light".to_string(), themes::get("github_light").unwrap());
+    /// themes.insert("empty".to_string(), Theme::default());
+    ///
+    /// let (formatter, warnings) = HtmlMultiThemesBuilder::new()
+    ///     .lang(Language::Rust)
+    ///     .themes(themes)
+    ///     .build_with_warnings()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     warnings,
+    ///     ["Theme 'empty' does not define styles for any Rust scopes"]
+    /// );
+    /// ```
+    pub fn build_with_warnings(&mut self) -> Result<(HtmlMultiThemes, Vec<String>), String> {
         let result = HtmlMultiThemes {
             lang: self.lang.take().unwrap_or(Language::PlainText),
             themes: self.themes.take().unwrap_or_default(),
@@ -243,6 +350,27 @@ impl HtmlMultiThemesBuilder {
             include_highlights: self.include_highlights.take().unwrap_or(false),
             highlight_lines: self.highlight_lines.take().flatten(),
             header: self.header.take().flatten(),
+            container: self.container.take().flatten(),
+            copy_button: self.copy_button.take().flatten(),
+            code_id: self.code_id.take().flatten(),
+            figure: self.figure.take().flatten(),
+            language_class_map: self.language_class_map.take().unwrap_or_default(),
+            scope_tooltips: self.scope_tooltips.take().unwrap_or(false),
+            foldable: self.foldable.take().unwrap_or(false),
+            gutter: self.gutter.take().flatten(),
+            line_prefix: self.line_prefix.take().flatten(),
+            line_suffix: self.line_suffix.take().flatten(),
+            non_empty_line_marker: self.non_empty_line_marker.take().unwrap_or(false),
+            trim_final_newline: self.trim_final_newline.take().unwrap_or(false),
+            escape_braces: self.escape_braces.take().unwrap_or(true),
+            escape_strategy: self.escape_strategy.take().unwrap_or_default(),
+            render_whitespace: self.render_whitespace.take().unwrap_or_default(),
+            tab_mode: self.tab_mode.take().unwrap_or_default(),
+            font_family: self.font_family.take().flatten(),
+            font_size: self.font_size.take().flatten(),
+            data_attributes: self.data_attributes.take().unwrap_or(false),
+            line_class_fn: self.line_class_fn.take().flatten(),
+            injection_resolver: self.injection_resolver.take().flatten(),
         };
 
         if result.themes.is_empty() {
@@ -250,24 +378,34 @@ impl HtmlMultiThemesBuilder {
         }
 
         match &result.default_theme {
-            Some(DefaultTheme::Theme(name)) => {
-                if !result.themes.contains_key(name) {
-                    return Err(format!("Default theme '{}' not found in themes map", name));
-                }
+            Some(DefaultTheme::Theme(name)) if !result.themes.contains_key(name) => {
+                return Err(format!("Default theme '{}' not found in themes map", name));
             }
-            Some(DefaultTheme::LightDark) => {
-                if !result.themes.contains_key("light") || !result.themes.contains_key("dark") {
-                    return Err(
-                        "LightDark mode requires themes named 'light' and 'dark'".to_string()
-                    );
-                }
+            Some(DefaultTheme::LightDark)
+                if !result.themes.contains_key("light") || !result.themes.contains_key("dark") =>
+            {
+                return Err("LightDark mode requires themes named 'light' and 'dark'".to_string());
             }
-            None => {
+            _ => {
                 // No default theme - all themes are CSS variables only
             }
         }
 
-        Ok(result)
+        let mut theme_names: Vec<&String> = result.themes.keys().collect();
+        theme_names.sort();
+        let warnings = theme_names
+            .into_iter()
+            .filter(|name| !result.themes[*name].resolves_any_scope(&result.lang))
+            .map(|name| {
+                format!(
+                    "Theme '{}' does not define styles for any {} scopes",
+                    name,
+                    result.lang.name()
+                )
+            })
+            .collect();
+
+        Ok((result, warnings))
     }
 }
 
@@ -321,6 +459,27 @@ impl Default for HtmlMultiThemes {
             include_highlights: false,
             highlight_lines: None,
             header: None,
+            container: None,
+            copy_button: None,
+            code_id: None,
+            figure: None,
+            language_class_map: LanguageClassMap::default(),
+            scope_tooltips: false,
+            foldable: false,
+            gutter: None,
+            line_prefix: None,
+            line_suffix: None,
+            non_empty_line_marker: false,
+            trim_final_newline: false,
+            escape_braces: true,
+            escape_strategy: EscapeStrategy::Named,
+            render_whitespace: WhitespaceMode::None,
+            tab_mode: TabMode::None,
+            font_family: None,
+            font_size: None,
+            data_attributes: false,
+            line_class_fn: None,
+            injection_resolver: None,
         }
     }
 }
@@ -407,6 +566,13 @@ impl HtmlMultiThemes {
             }
         }
 
+        if let Some(font_style) = crate::formatter::html::font_style(
+            self.font_family.as_deref(),
+            self.font_size.as_deref(),
+        ) {
+            styles.push(font_style);
+        }
+
         Ok(styles.join(" "))
     }
 
@@ -415,12 +581,48 @@ impl HtmlMultiThemes {
         let style = self.generate_pre_style()?;
 
         write!(output, "<pre class=\"{}\"", classes)?;
+        if self.data_attributes {
+            // Only a named default theme applies to the page before any switching
+            let theme_name = match &self.default_theme {
+                Some(DefaultTheme::Theme(name)) => {
+                    self.themes.get(name).map(|theme| theme.name.as_str())
+                }
+                _ => None,
+            };
+            let attrs = crate::formatter::html::data_attributes(&self.lang, theme_name);
+            write!(output, "{}", attrs)?;
+        }
         if !style.is_empty() {
             write!(output, " style=\"{}\"", style)?;
         }
         write!(output, ">")
     }
 
+    fn block(&self) -> crate::formatter::html::HtmlBlock<'_> {
+        crate::formatter::html::HtmlBlock {
+            lang: self.lang,
+            header: self.header.as_ref(),
+            container: self.container.as_ref(),
+            copy_button: self.copy_button.as_ref(),
+            code_id: self.code_id.as_deref(),
+            figure: self.figure.as_ref(),
+            language_class_map: &self.language_class_map,
+            scope_tooltips: self.scope_tooltips,
+            foldable: self.foldable,
+            gutter: self.gutter,
+            line_prefix: self.line_prefix.as_ref(),
+            line_suffix: self.line_suffix.as_ref(),
+            non_empty_line_marker: self.non_empty_line_marker,
+            trim_final_newline: self.trim_final_newline,
+            escape_braces: self.escape_braces,
+            escape_strategy: self.escape_strategy,
+            render_whitespace: self.render_whitespace,
+            tab_mode: self.tab_mode,
+            line_class_fn: self.line_class_fn.as_ref(),
+            injection_resolver: self.injection_resolver.as_ref(),
+        }
+    }
+
     fn get_line_attrs(&self, line_number: usize) -> (Option<String>, Option<String>) {
         let is_highlighted = self
             .highlight_lines
@@ -465,71 +667,112 @@ impl HtmlMultiThemes {
 
 impl Formatter for HtmlMultiThemes {
     fn format(&self, source: &str, output: &mut dyn Write) -> io::Result<()> {
-        let mut buffer = Vec::new();
-
-        if let Some(ref header) = self.header {
-            write!(buffer, "{}", header.open_tag)?;
-        }
-
-        self.open_pre_tag(&mut buffer)?;
-        crate::formatter::html::open_code_tag(&mut buffer, &self.lang)?;
+        let default_theme = match &self.default_theme {
+            Some(DefaultTheme::Theme(name)) => Some(name.as_str()),
+            Some(DefaultTheme::LightDark) => Some("light-dark()"),
+            None => None,
+        };
 
-        let mut highlighter = Highlighter::new();
-        let events = highlighter
-            .highlight(self.lang.config(), source.as_bytes(), None, |injected| {
-                Some(Language::guess(Some(injected), "").config())
-            })
-            .map_err(io::Error::other)?;
-
-        let mut renderer = HtmlRenderer::new();
-
-        renderer
-            .render(
-                events,
-                source.as_bytes(),
-                &move |highlight, language, output| {
-                    let scope = crate::constants::HIGHLIGHT_NAMES[highlight.0];
-                    let lang = Language::guess(Some(language), "");
-                    let default_theme_str = match &self.default_theme {
-                        Some(DefaultTheme::Theme(name)) => Some(name.as_str()),
-                        Some(DefaultTheme::LightDark) => Some("light-dark()"),
-                        None => None,
-                    };
-                    let attrs = crate::formatter::html::span_multi_themes_attrs(
-                        scope,
-                        Some(lang),
-                        &self.themes,
-                        default_theme_str,
-                        &self.css_variable_prefix,
-                        self.italic,
-                        self.include_highlights,
-                    );
-                    output.extend(attrs.as_bytes());
-                },
-            )
-            .map_err(io::Error::other)?;
-
-        for (i, line) in renderer.lines().enumerate() {
-            let line_number = i + 1;
-            let line_with_braces = crate::formatter::html::escape_braces(line);
-            let (class_suffix, style) = self.get_line_attrs(line_number);
-            let wrapped = crate::formatter::html::wrap_line(
-                line_number,
-                &line_with_braces,
-                class_suffix.as_deref(),
-                style.as_deref(),
-            );
-            write!(&mut buffer, "{}", wrapped)?;
-        }
+        self.block().format(
+            source,
+            output,
+            |buffer| self.open_pre_tag(buffer),
+            |scope, language, _line, output| {
+                crate::formatter::html::write_span_multi_themes_attrs(
+                    output,
+                    scope,
+                    Some(Language::guess(Some(language), "")),
+                    &self.themes,
+                    default_theme,
+                    &self.css_variable_prefix,
+                    self.italic,
+                    self.include_highlights,
+                )
+                .expect("writing to a Vec never fails");
+            },
+            |line_number| self.get_line_attrs(line_number),
+        )
+    }
 
-        crate::formatter::html::closing_tags(&mut buffer)?;
+    fn language(&self) -> Language {
+        self.lang
+    }
 
-        if let Some(ref header) = self.header {
-            write!(buffer, "{}", header.close_tag)?;
-        }
+    fn cache_key(&self, source: &str) -> Option<u64> {
+        self.block()
+            .cacheable()
+            .then(|| super::cache_key(source, self))
+    }
+}
 
-        output.write_all(&buffer)?;
-        Ok(())
+impl Hash for HtmlMultiThemes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let Self {
+            lang,
+            themes,
+            default_theme,
+            css_variable_prefix,
+            pre_class,
+            italic,
+            include_highlights,
+            highlight_lines,
+            header,
+            container,
+            copy_button,
+            code_id,
+            figure,
+            language_class_map,
+            scope_tooltips,
+            foldable,
+            gutter,
+            line_prefix,
+            line_suffix,
+            non_empty_line_marker,
+            trim_final_newline,
+            escape_braces,
+            escape_strategy,
+            render_whitespace,
+            tab_mode,
+            font_family,
+            font_size,
+            data_attributes,
+            line_class_fn,
+            injection_resolver,
+        } = self;
+
+        // The themes map iterates in random order, so hash it sorted by name
+        let themes: BTreeMap<&String, &Theme> = themes.iter().collect();
+
+        lang.hash(state);
+        themes.hash(state);
+        default_theme.hash(state);
+        css_variable_prefix.hash(state);
+        pre_class.hash(state);
+        italic.hash(state);
+        include_highlights.hash(state);
+        highlight_lines.hash(state);
+        header.hash(state);
+        container.hash(state);
+        copy_button.hash(state);
+        code_id.hash(state);
+        figure.hash(state);
+        language_class_map.hash(state);
+        scope_tooltips.hash(state);
+        foldable.hash(state);
+        gutter.hash(state);
+        line_prefix.hash(state);
+        line_suffix.hash(state);
+        non_empty_line_marker.hash(state);
+        trim_final_newline.hash(state);
+        escape_braces.hash(state);
+        escape_strategy.hash(state);
+        render_whitespace.hash(state);
+        tab_mode.hash(state);
+        font_family.hash(state);
+        font_size.hash(state);
+        data_attributes.hash(state);
+        line_class_fn.hash(state);
+        injection_resolver.hash(state);
     }
 }
 
@@ -537,6 +780,30 @@ impl Formatter for HtmlMultiThemes {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_cache_key() {
+        let build = |names: &[&str], default: &str| {
+            let themes = names
+                .iter()
+                .map(|name| (name.to_string(), crate::themes::get(name).unwrap()))
+                .collect();
+
+            HtmlMultiThemesBuilder::new()
+                .lang(Language::Rust)
+                .themes(themes)
+                .default_theme(default)
+                .build()
+                .unwrap()
+        };
+
+        let a = build(&["github_light", "github_dark", "dracula"], "dracula");
+        let b = build(&["dracula", "github_dark", "github_light"], "dracula");
+        let c = build(&["dracula", "github_dark", "github_light"], "github_dark");
+
+        assert_eq!(a.cache_key("fn main() {}"), b.cache_key("fn main() {}"));
+        assert_ne!(a.cache_key("fn main() {}"), c.cache_key("fn main() {}"));
+    }
+
     #[test]
     fn test_text_decoration() {
         use crate::formatter::html::text_decoration;
@@ -779,4 +1046,150 @@ mod tests {
 
         assert!(html.contains("font-style: light-dark("));
     }
+
+    #[test]
+    fn test_scope_tooltips() {
+        let mut themes = HashMap::new();
+        themes.insert("dark".to_string(), crate::themes::get("dracula").unwrap());
+
+        let formatter = HtmlMultiThemesBuilder::new()
+            .lang(Language::Rust)
+            .themes(themes)
+            .scope_tooltips(true)
+            .build()
+            .unwrap();
+
+        let mut output = Vec::new();
+        formatter.format("fn", &mut output).unwrap();
+        let html = String::from_utf8(output).unwrap();
+
+        assert!(html.contains(r#"title="keyword.function">fn</span>"#));
+        assert!(!html.contains("data-highlight"));
+    }
+
+    #[test]
+    fn test_font_family_and_size() {
+        let mut themes = HashMap::new();
+        themes.insert("dark".to_string(), crate::themes::get("dracula").unwrap());
+
+        let formatter = HtmlMultiThemesBuilder::new()
+            .themes(themes)
+            .default_theme("dark")
+            .font_size(Some("14px".to_string()))
+            .build()
+            .unwrap();
+
+        let mut output = Vec::new();
+        formatter.format("a", &mut output).unwrap();
+        let html = String::from_utf8(output).unwrap();
+
+        assert!(
+            html.contains(r#"style="color:#f8f8f2; background-color:#282a36; font-size: 14px;">"#)
+        );
+    }
+
+    #[test]
+    fn test_data_attributes() {
+        let mut themes = HashMap::new();
+        themes.insert(
+            "light".to_string(),
+            crate::themes::get("github_light").unwrap(),
+        );
+        themes.insert(
+            "dark".to_string(),
+            crate::themes::get("github_dark").unwrap(),
+        );
+
+        let render = |default_theme: DefaultThemeArg| {
+            let formatter = HtmlMultiThemesBuilder::new()
+                .lang(Language::Rust)
+                .themes(themes.clone())
+                .default_theme(default_theme)
+                .data_attributes(true)
+                .build()
+                .unwrap();
+
+            let mut output = Vec::new();
+            formatter.format("a", &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert!(render(DefaultThemeArg::String("dark".to_string()))
+            .contains(r#"data-lang="rust" data-theme="github_dark" style="#));
+        assert!(render(DefaultThemeArg::Bool(false)).contains(r#"" data-lang="rust" style="#));
+    }
+
+    #[test]
+    fn test_injection_resolver() {
+        let mut themes = HashMap::new();
+        themes.insert("dark".to_string(), crate::themes::get("dracula").unwrap());
+        let source = "```rust\nfn main() {}\n```\n";
+
+        let render = |builder: &mut HtmlMultiThemesBuilder| {
+            let formatter = builder
+                .lang(Language::Markdown)
+                .themes(themes.clone())
+                .include_highlights(true)
+                .build()
+                .unwrap();
+            let mut output = Vec::new();
+            formatter.format(source, &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let html = render(&mut HtmlMultiThemesBuilder::new());
+        assert!(html.contains(r#"data-highlight="keyword.function""#));
+
+        let html = render(
+            HtmlMultiThemesBuilder::new().injection_resolver(|name| match name {
+                "rust" => None,
+                name => Some(Language::guess(Some(name), "")),
+            }),
+        );
+        assert!(!html.contains(r#"data-highlight="keyword.function""#));
+        assert!(html.contains("fn main()"));
+    }
+
+    #[test]
+    fn test_build_warns_about_themes_without_styles() {
+        let mut themes = HashMap::new();
+        themes.insert(
+            "light".to_string(),
+            crate::themes::get("github_light").unwrap(),
+        );
+        themes.insert("empty".to_string(), Theme::default());
+
+        let (_, warnings) = HtmlMultiThemesBuilder::new()
+            .lang(Language::Rust)
+            .themes(themes)
+            .default_theme("light")
+            .build_with_warnings()
+            .unwrap();
+
+        assert_eq!(
+            warnings,
+            ["Theme 'empty' does not define styles for any Rust scopes"]
+        );
+    }
+
+    #[test]
+    fn test_build_without_warnings() {
+        let mut themes = HashMap::new();
+        themes.insert(
+            "light".to_string(),
+            crate::themes::get("github_light").unwrap(),
+        );
+        themes.insert(
+            "dark".to_string(),
+            crate::themes::get("github_dark").unwrap(),
+        );
+
+        let (_, warnings) = HtmlMultiThemesBuilder::new()
+            .lang(Language::Rust)
+            .themes(themes)
+            .build_with_warnings()
+            .unwrap();
+
+        assert!(warnings.is_empty());
+    }
 }
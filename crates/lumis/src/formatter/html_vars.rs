@@ -0,0 +1,420 @@
+//! HTML formatter with colors from CSS custom properties.
+//!
+//! This module provides the [`HtmlVars`] formatter that generates HTML output where each
+//! token is colored by a CSS variable named after its scope. It sits between
+//! [`HtmlInline`](super::HtmlInline) and [`HtmlLinked`](super::HtmlLinked): no stylesheet
+//! per theme is needed, only a small palette of variables defined by the page.
+//!
+//! # Example Output
+//!
+//! For the Rust code `fn main() {}`, the formatter generates HTML like:
+//!
+//! ```html
+//! <pre class="lumis"><code class="language-rust" translate="no" tabindex="0"><div class="line" data-line="1"><span style="color: var(--keyword-function, var(--keyword));">fn</span> <span style="color: var(--function);">main</span><span style="color: var(--punctuation-bracket, var(--punctuation));">(</span><span style="color: var(--punctuation-bracket, var(--punctuation));">)</span> <span style="color: var(--punctuation-bracket, var(--punctuation));">&lbrace;</span><span style="color: var(--punctuation-bracket, var(--punctuation));">&rbrace;</span></div></code></pre>
+//! ```
+//!
+//! See the [formatter](crate::formatter) module for more information and examples.
+
+use super::html_linked::HighlightLines;
+use super::{
+    ContainerSpec, CopyButtonSpec, EscapeStrategy, FigureSpec, Formatter, GutterCallback,
+    HtmlElement, InjectionResolver, LanguageClassMap, LineCallback, LineClassFn, TabMode,
+    WhitespaceMode,
+};
+use crate::languages::Language;
+use derive_builder::Builder;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// HTML formatter with colors from CSS variables.
+///
+/// Every token gets a `color` pointing at the variable of its scope, with dots replaced
+/// by dashes, falling back to the variables of its parent scopes. A `keyword.function`
+/// token is styled `color: var(--keyword-function, var(--keyword));`, so a palette only
+/// needs the top-level scopes (`--keyword`, `--string`, `--comment`, ...) and can refine
+/// specific ones as needed. Tokens whose variables are all undefined inherit the text
+/// color. Use [`HtmlVarsBuilder`] to create instances.
+///
+/// # When to use
+///
+/// - Sites built on design tokens that already define a color palette
+/// - Switching colors at runtime by redefining a few variables
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{HtmlVarsBuilder, languages::Language, formatter::Formatter};
+///
+/// let formatter = HtmlVarsBuilder::new()
+///     .lang(Language::Rust)
+///     .css_variable_prefix("--code-")
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("fn main() {}", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.contains(r#"<span style="color: var(--code-keyword-function, var(--code-keyword));">fn</span>"#));
+/// ```
+///
+/// With a palette such as:
+///
+/// ```css
+/// :root {
+///   --code-keyword: #d73a49;
+///   --code-string: #032f62;
+///   --code-comment: #6a737d;
+/// }
+/// ```
+#[derive(Builder, Clone, Debug, Hash)]
+#[builder(default)]
+pub struct HtmlVars {
+    lang: Language,
+    pre_class: Option<String>,
+    highlight_lines: Option<HighlightLines>,
+    header: Option<HtmlElement>,
+    container: Option<ContainerSpec>,
+    copy_button: Option<CopyButtonSpec>,
+    code_id: Option<String>,
+    figure: Option<FigureSpec>,
+    #[builder(setter(custom))]
+    language_class_map: LanguageClassMap,
+    scope_tooltips: bool,
+    foldable: bool,
+    gutter: Option<GutterCallback>,
+    #[builder(setter(custom))]
+    line_prefix: Option<LineCallback>,
+    #[builder(setter(custom))]
+    line_suffix: Option<LineCallback>,
+    non_empty_line_marker: bool,
+    trim_final_newline: bool,
+    escape_braces: bool,
+    escape_strategy: EscapeStrategy,
+    render_whitespace: WhitespaceMode,
+    tab_mode: TabMode,
+    font_family: Option<String>,
+    font_size: Option<String>,
+    data_attributes: bool,
+    #[builder(setter(custom))]
+    line_class_fn: Option<LineClassFn>,
+    #[builder(setter(custom))]
+    injection_resolver: Option<InjectionResolver>,
+    #[builder(setter(into))]
+    css_variable_prefix: String,
+}
+
+impl HtmlVarsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decide which language highlights each injected region, see [`InjectionResolver`].
+    pub fn injection_resolver<F>(&mut self, resolver: F) -> &mut Self
+    where
+        F: Fn(&str) -> Option<Language> + Send + Sync + 'static,
+    {
+        self.injection_resolver = Some(Some(InjectionResolver::new(resolver)));
+        self
+    }
+
+    /// Set the class of each line with a callback, see [`LineClassFn`].
+    pub fn line_class_fn<F>(&mut self, class: F) -> &mut Self
+    where
+        F: Fn(usize) -> Option<String> + Send + Sync + 'static,
+    {
+        self.line_class_fn = Some(Some(LineClassFn::new(class)));
+        self
+    }
+
+    /// Insert raw HTML at the start of each line, see [`LineCallback`].
+    pub fn line_prefix<F>(&mut self, prefix: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_prefix = Some(Some(LineCallback::new(prefix)));
+        self
+    }
+
+    /// Insert raw HTML at the end of each line, see [`LineCallback`].
+    pub fn line_suffix<F>(&mut self, suffix: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_suffix = Some(Some(LineCallback::new(suffix)));
+        self
+    }
+
+    /// Replace the `language-*` class of the `<code>` element for some languages, e.g.
+    /// `lang-js` for JavaScript. Other languages keep `language-{id}`.
+    pub fn language_class_map(&mut self, map: HashMap<Language, String>) -> &mut Self {
+        self.language_class_map = Some(LanguageClassMap(map));
+        self
+    }
+}
+
+impl HtmlVars {
+    pub fn new(
+        lang: Language,
+        pre_class: Option<String>,
+        highlight_lines: Option<HighlightLines>,
+        header: Option<HtmlElement>,
+        css_variable_prefix: String,
+    ) -> Self {
+        Self {
+            lang,
+            pre_class,
+            highlight_lines,
+            header,
+            container: None,
+            copy_button: None,
+            code_id: None,
+            figure: None,
+            language_class_map: LanguageClassMap::default(),
+            scope_tooltips: false,
+            foldable: false,
+            gutter: None,
+            line_prefix: None,
+            line_suffix: None,
+            non_empty_line_marker: false,
+            trim_final_newline: false,
+            escape_braces: true,
+            escape_strategy: EscapeStrategy::Named,
+            render_whitespace: WhitespaceMode::None,
+            tab_mode: TabMode::None,
+            font_family: None,
+            font_size: None,
+            data_attributes: false,
+            line_class_fn: None,
+            injection_resolver: None,
+            css_variable_prefix,
+        }
+    }
+}
+
+impl Default for HtmlVars {
+    fn default() -> Self {
+        Self {
+            lang: Language::PlainText,
+            pre_class: None,
+            highlight_lines: None,
+            header: None,
+            container: None,
+            copy_button: None,
+            code_id: None,
+            figure: None,
+            language_class_map: LanguageClassMap::default(),
+            scope_tooltips: false,
+            foldable: false,
+            gutter: None,
+            line_prefix: None,
+            line_suffix: None,
+            non_empty_line_marker: false,
+            trim_final_newline: false,
+            escape_braces: true,
+            escape_strategy: EscapeStrategy::Named,
+            render_whitespace: WhitespaceMode::None,
+            tab_mode: TabMode::None,
+            font_family: None,
+            font_size: None,
+            data_attributes: false,
+            line_class_fn: None,
+            injection_resolver: None,
+            css_variable_prefix: "--".to_string(),
+        }
+    }
+}
+
+impl HtmlVars {
+    fn block(&self) -> crate::formatter::html::HtmlBlock<'_> {
+        crate::formatter::html::HtmlBlock {
+            lang: self.lang,
+            header: self.header.as_ref(),
+            container: self.container.as_ref(),
+            copy_button: self.copy_button.as_ref(),
+            code_id: self.code_id.as_deref(),
+            figure: self.figure.as_ref(),
+            language_class_map: &self.language_class_map,
+            scope_tooltips: self.scope_tooltips,
+            foldable: self.foldable,
+            gutter: self.gutter,
+            line_prefix: self.line_prefix.as_ref(),
+            line_suffix: self.line_suffix.as_ref(),
+            non_empty_line_marker: self.non_empty_line_marker,
+            trim_final_newline: self.trim_final_newline,
+            escape_braces: self.escape_braces,
+            escape_strategy: self.escape_strategy,
+            render_whitespace: self.render_whitespace,
+            tab_mode: self.tab_mode,
+            line_class_fn: self.line_class_fn.as_ref(),
+            injection_resolver: self.injection_resolver.as_ref(),
+        }
+    }
+}
+
+impl Formatter for HtmlVars {
+    fn format(&self, source: &str, output: &mut dyn Write) -> io::Result<()> {
+        self.block().format(
+            source,
+            output,
+            |buffer| {
+                let font_style = crate::formatter::html::font_style(
+                    self.font_family.as_deref(),
+                    self.font_size.as_deref(),
+                );
+                let attrs = if self.data_attributes {
+                    crate::formatter::html::data_attributes(&self.lang, None)
+                } else {
+                    String::new()
+                };
+                crate::formatter::html::open_pre_tag_with_attrs(
+                    buffer,
+                    self.pre_class.as_deref(),
+                    font_style.as_deref(),
+                    &attrs,
+                )
+            },
+            |scope, _language, _line, output| {
+                output.extend(
+                    crate::formatter::html::span_vars_attrs(scope, &self.css_variable_prefix)
+                        .as_bytes(),
+                );
+            },
+            |line_number| {
+                let class_suffix = self.highlight_lines.as_ref().and_then(|hl| {
+                    hl.lines
+                        .iter()
+                        .any(|range| range.contains(&line_number))
+                        .then(|| format!(" {}", hl.class))
+                });
+                (class_suffix, None)
+            },
+        )
+    }
+
+    fn language(&self) -> Language {
+        self.lang
+    }
+
+    fn cache_key(&self, source: &str) -> Option<u64> {
+        self.block()
+            .cacheable()
+            .then(|| super::cache_key(source, self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_str_eq;
+
+    #[test]
+    fn test_format() {
+        let formatter = HtmlVarsBuilder::new()
+            .lang(Language::Rust)
+            .highlight_lines(Some(HighlightLines {
+                lines: vec![1..=1],
+                ..Default::default()
+            }))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("fn main() {}", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let expected = r#"<pre class="lumis"><code class="language-rust" translate="no" tabindex="0"><div class="line highlighted" data-line="1"><span style="color: var(--keyword-function, var(--keyword));">fn</span> <span style="color: var(--function);">main</span><span style="color: var(--punctuation-bracket, var(--punctuation));">(</span><span style="color: var(--punctuation-bracket, var(--punctuation));">)</span> <span style="color: var(--punctuation-bracket, var(--punctuation));">&lbrace;</span><span style="color: var(--punctuation-bracket, var(--punctuation));">&rbrace;</span>
+</div></code></pre>"#;
+        assert_str_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_tab_mode() {
+        let formatter = HtmlVarsBuilder::new()
+            .tab_mode(TabMode::Stops(4))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("ab\tc", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.contains("data-line=\"1\">ab  c\n</div>"));
+    }
+
+    #[test]
+    fn test_line_options() {
+        let formatter = HtmlVarsBuilder::new()
+            .gutter(Some(|n| (n == 1).then_some('+')))
+            .line_prefix(|n| format!("<i>{n}</i>"))
+            .line_suffix(|_| "<b></b>".to_string())
+            .render_whitespace(WhitespaceMode::All)
+            .trim_final_newline(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a b\nc\n\n", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let expected = r#"<pre class="lumis"><code class="language-plaintext" translate="no" tabindex="0"><div class="line" data-line="1"><span class="gutter">+</span><i>1</i>a<span class="whitespace">·</span>b<b></b>
+</div><div class="line" data-line="2"><span class="gutter"> </span><i>2</i>c<b></b>
+</div></code></pre>"#;
+        assert_str_eq!(result, expected);
+        assert_eq!(formatter.cache_key("a"), None);
+    }
+
+    #[test]
+    fn test_scope_tooltips_and_injection_resolver() {
+        let formatter = HtmlVarsBuilder::new()
+            .lang(Language::Markdown)
+            .scope_tooltips(true)
+            .injection_resolver(|_| None)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter
+            .format("# Title\n```rust\nfn main() {}\n```\n", &mut buffer)
+            .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.contains(r#"title="markup.heading.1""#));
+        assert!(!result.contains("keyword"));
+    }
+
+    #[test]
+    fn test_foldable() {
+        let formatter = HtmlVarsBuilder::new()
+            .lang(Language::Fish)
+            .foldable(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter
+            .format("function a\n    if true\n    end\nend\n", &mut buffer)
+            .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert_eq!(result.matches("<details class=\"fold\" open>").count(), 2);
+        assert!(result.ends_with("</div></details></code></pre>"));
+    }
+
+    #[test]
+    fn test_non_empty_line_marker() {
+        let formatter = HtmlVarsBuilder::new()
+            .non_empty_line_marker(true)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("a\n\nb\n", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let expected = r#"<pre class="lumis"><code class="language-plaintext" translate="no" tabindex="0"><div class="line" data-line="1">a
+</div><div class="line" data-line="2"><span></span>
+</div><div class="line" data-line="3">b
+</div></code></pre>"#;
+        assert_str_eq!(result, expected);
+    }
+}
@@ -1,10 +1,16 @@
 //! Formatter implementations for generating syntax highlighted output.
 //!
-//! This module provides four different formatters for rendering syntax highlighted code:
+//! This module provides seven different formatters for rendering syntax highlighted code:
 //! - [`html_inline`] - HTML output with inline CSS styles (single theme)
 //! - [`html_multi_themes`] - HTML output with inline CSS styles (multiple themes)
 //! - [`html_linked`] - HTML output with CSS classes (requires external CSS)
+//! - [`html_vars`] - HTML output colored by CSS variables (requires a palette)
+//! - [`html_atomic`] - HTML output with generated atomic CSS classes and their rules
 //! - [`terminal`] - ANSI color codes for terminal output
+//! - [`svg`] - Standalone SVG images
+//!
+//! For TUI frameworks, [`styled_lines`] returns the highlighted lines as structured
+//! spans instead of formatted output.
 //!
 //! # Builder Pattern
 //!
@@ -12,11 +18,14 @@
 //! - [`HtmlInlineBuilder`] - Create HTML formatters with inline CSS styles
 //! - [`HtmlMultiThemesBuilder`] - Create HTML formatters with multiple theme support
 //! - [`HtmlLinkedBuilder`] - Create HTML formatters with CSS classes
+//! - [`HtmlVarsBuilder`] - Create HTML formatters with CSS variables
+//! - [`HtmlAtomicBuilder`] - Create HTML formatters with atomic CSS classes
 //! - [`TerminalBuilder`] - Create terminal formatters with ANSI colors
+//! - [`SvgBuilder`] - Create SVG formatters for code images
 //!
 //! Builders are exported at the crate root for convenient access:
 //! ```rust
-//! use lumis::{HtmlInlineBuilder, HtmlMultiThemesBuilder, HtmlLinkedBuilder, TerminalBuilder};
+//! use lumis::{HtmlInlineBuilder, HtmlMultiThemesBuilder, HtmlLinkedBuilder, HtmlVarsBuilder, HtmlAtomicBuilder, TerminalBuilder, SvgBuilder};
 //! ```
 //!
 //! # Examples
@@ -142,13 +151,20 @@
 
 // Originally based on https://github.com/Colonial-Dev/inkjet/tree/da289fa8b68f11dffad176e4b8fabae8d6ac376d/src/formatter
 
+use crate::languages::Language;
+use crate::vendor::tree_sitter_highlight::HighlightConfiguration;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use thiserror::Error;
 
 pub mod ansi;
 pub mod html;
 
 pub mod html_inline;
-pub use html_inline::{HtmlInline, HtmlInlineBuilder};
+pub use html_inline::{HtmlInline, HtmlInlineBuilder, RenderedLine};
 
 pub mod html_multi_themes;
 pub use html_multi_themes::{HtmlMultiThemes, HtmlMultiThemesBuilder};
@@ -156,9 +172,21 @@ pub use html_multi_themes::{HtmlMultiThemes, HtmlMultiThemesBuilder};
 pub mod html_linked;
 pub use html_linked::{HtmlLinked, HtmlLinkedBuilder};
 
+pub mod html_vars;
+pub use html_vars::{HtmlVars, HtmlVarsBuilder};
+
+pub mod html_atomic;
+pub use html_atomic::{AtomicHtml, HtmlAtomic, HtmlAtomicBuilder};
+
 pub mod terminal;
 pub use terminal::{Terminal, TerminalBuilder};
 
+pub mod svg;
+pub use svg::{Svg, SvgBuilder};
+
+pub mod styled;
+pub use styled::{styled_lines, Rgb, StyledSpan};
+
 /// Configuration for wrapping the formatted output with custom HTML elements.
 ///
 /// This struct allows you to specify opening and closing HTML tags that will wrap
@@ -186,7 +214,7 @@ pub use terminal::{Terminal, TerminalBuilder};
 ///     close_tag: "</section>".to_string(),
 /// };
 /// ```
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct HtmlElement {
     /// The opening HTML tag that will be placed before the formatted code.
     ///
@@ -200,6 +228,522 @@ pub struct HtmlElement {
     pub close_tag: String,
 }
 
+/// Configuration for a container element with a header bar around the code block.
+///
+/// Unlike [`HtmlElement`], which only wraps the output with fixed tags, the container
+/// renders the scaffold most documentation sites write by hand: a wrapper tagged with
+/// the language and a header bar holding a language label and a copy button. The
+/// button has no behavior attached; wire it up with your own script, for example by
+/// selecting `.lumis-copy` buttons and copying the sibling `<pre>` text.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::{ContainerSpec, Formatter}};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::Rust)
+///     .container(Some(ContainerSpec {
+///         class: Some("not-prose".to_string()),
+///         ..Default::default()
+///     }))
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("fn main() {}", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.starts_with(r#"<div class="lumis-container not-prose" data-lang="rust"><div class="lumis-header"><span class="lumis-lang">Rust</span>"#));
+/// assert!(html.ends_with("</pre></div>"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ContainerSpec {
+    /// Tag name of the wrapper element, e.g. `"div"` or `"figure"`. Names that aren't an
+    /// ASCII letter followed by ASCII letters, digits or `-` fall back to `"div"`.
+    pub tag: String,
+    /// Optional CSS class appended to the base `lumis-container` class.
+    pub class: Option<String>,
+    /// Show the language name (from [`Language::name`]) in the header bar.
+    pub include_lang_label: bool,
+    /// Add a `<button class="lumis-copy">` to the header bar, which copies the code like
+    /// a [`CopyButtonSpec`] button and replaces it.
+    pub include_copy_button: bool,
+}
+
+impl Default for ContainerSpec {
+    fn default() -> Self {
+        Self {
+            tag: "div".to_string(),
+            class: None,
+            include_lang_label: true,
+            include_copy_button: true,
+        }
+    }
+}
+
+/// Configuration for a copy button linked to the code block.
+///
+/// The HTML formatters render the button right before the `<pre>` and give the `<code>`
+/// element an `id` that the button points at with `data-clipboard-target`, the attribute
+/// read by clipboard.js. No script is included.
+///
+/// The id is the formatter's `code_id` option or, by default, `lumis-code-` followed by a
+/// hash of the language and source and a counter, so every call gets its own id even for
+/// identical snippets. Output with a generated id is not cached; set `code_id` to get
+/// deterministic, cacheable output.
+///
+/// A [`ContainerSpec`] with [`include_copy_button`](ContainerSpec::include_copy_button)
+/// renders its own button in the header bar, targeting the same `id`, and this one is
+/// left out.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::{CopyButtonSpec, Formatter}};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::Rust)
+///     .copy_button(Some(CopyButtonSpec {
+///         label: "Copy code".to_string(),
+///         ..Default::default()
+///     }))
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("fn main() {}", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.starts_with(r##"<button type="button" class="copy" data-clipboard-target="#lumis-code-"##));
+/// assert!(html.contains(r#"<code id="lumis-code-"#));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CopyButtonSpec {
+    /// CSS class of the button.
+    pub class: String,
+    /// Text of the button.
+    pub label: String,
+}
+
+impl Default for CopyButtonSpec {
+    fn default() -> Self {
+        Self {
+            class: "copy".to_string(),
+            label: "Copy".to_string(),
+        }
+    }
+}
+
+/// Configuration for a `<figure>` wrapping the code block with a caption.
+///
+/// The HTML formatters render the `<figure>` inside the [`HtmlElement`] header and the
+/// [`ContainerSpec`], with a `<figcaption>` holding the escaped caption before the
+/// `<pre>`. An empty caption renders the figure without a `<figcaption>`. A common
+/// caption is the file name the language was guessed from.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::{FigureSpec, Formatter}};
+///
+/// let path = "src/main.rs";
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::guess(Some(path), ""))
+///     .figure(Some(FigureSpec {
+///         caption: path.to_string(),
+///     }))
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("fn main() {}", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.starts_with(r#"<figure class="lumis-figure"><figcaption>src/main.rs</figcaption><pre class="lumis">"#));
+/// assert!(html.ends_with("</pre></figure>"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FigureSpec {
+    /// Text of the `<figcaption>`, escaped when rendered.
+    pub caption: String,
+}
+
+/// Which whitespace the HTML formatters render visibly, as editors do.
+///
+/// Visible spaces are shown as `·` and tabs as `→`, each run wrapped in a
+/// `<span class="whitespace">` that can be dimmed with CSS. Copying the rendered code
+/// copies the markers too, so this is meant for reading and debugging indentation
+/// rather than for code meant to be copied.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::{Formatter, WhitespaceMode}};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::PlainText)
+///     .render_whitespace(WhitespaceMode::Boundary)
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("  a b", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.contains(r#"<span class="whitespace">··</span>a b"#));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum WhitespaceMode {
+    /// Render whitespace as is.
+    #[default]
+    None,
+    /// Render only leading and trailing whitespace of each line.
+    Boundary,
+    /// Render all whitespace.
+    All,
+}
+
+/// How the HTML formatters expand tabs into spaces.
+///
+/// Browsers align tabs inside `<pre>` to the CSS `tab-size`, but the alignment is lost
+/// where that isn't supported, like some email clients, or when the code is pasted
+/// somewhere with another tab size. Expanding tabs fixes the layout in the HTML itself.
+///
+/// [`TabMode::Stops`] moves to the next multiple of the width, counted from the start
+/// of the line, so comments and tables aligned with tabs line up as in an editor.
+/// Columns are counted as displayed: an entity like `&lt;` takes one column and wide
+/// characters take two.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::{Formatter, TabMode}};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::PlainText)
+///     .tab_mode(TabMode::Stops(4))
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("a\t= 1\nabc\t= 2", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.contains("a   = 1"));
+/// assert!(html.contains("abc = 2"));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TabMode {
+    /// Keep tabs as is.
+    #[default]
+    None,
+    /// Replace each tab with this many spaces.
+    Spaces(usize),
+    /// Expand each tab up to the next multiple of this many columns.
+    Stops(usize),
+}
+
+/// Which characters the HTML formatters escape and how the entities are written.
+///
+/// [`EscapeStrategy::Named`] is the default and writes entities like `&lt;` and
+/// `&lbrace;`. Named entities other than the five of XML are unknown to XML parsers,
+/// so use [`EscapeStrategy::Numeric`] when the output is parsed as XML or XHTML.
+/// Braces are only escaped with the `escape_braces` option, in the entity form of the
+/// strategy.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::{EscapeStrategy, Formatter}};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::PlainText)
+///     .escape_strategy(EscapeStrategy::Numeric)
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("a < b { }", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.contains("a &#60; b &#123; &#125;"));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EscapeStrategy {
+    /// Escape only `&`, `<` and `>`, keeping quotes as is.
+    Minimal,
+    /// Escape `&`, `<`, `>` and `"` with named entities, and `'` as `&#39;`, which has
+    /// no named entity in HTML 4.
+    #[default]
+    Named,
+    /// Escape the same characters as [`EscapeStrategy::Named`] with decimal entities.
+    Numeric,
+    /// Like [`EscapeStrategy::Numeric`], and also escape every non-ASCII character.
+    NonAscii,
+}
+
+/// Callback producing raw HTML for a line, given its 1-based line number.
+///
+/// Used by the `line_prefix` and `line_suffix` options of the HTML formatters to build
+/// custom gutters, copy buttons or diff markers. The returned string is not escaped.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::Formatter};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::PlainText)
+///     .line_prefix(|n| format!("<span class=\"ln\">{n}</span>"))
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("a\nb", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.contains(r#"<div class="line" data-line="2"><span class="ln">2</span>"#));
+/// ```
+#[derive(Clone)]
+pub struct LineCallback(Arc<LineFn>);
+
+type LineFn = dyn Fn(usize) -> String + Send + Sync;
+
+impl LineCallback {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        Self(Arc::new(callback))
+    }
+
+    pub fn html(&self, line_number: usize) -> String {
+        (self.0)(line_number)
+    }
+}
+
+impl std::fmt::Debug for LineCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LineCallback(..)")
+    }
+}
+
+// Closures can't be hashed, so formatters with line callbacks have no cache key.
+impl Hash for LineCallback {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl From<Arc<LineFn>> for LineCallback {
+    fn from(callback: Arc<LineFn>) -> Self {
+        Self(callback)
+    }
+}
+
+/// Callback returning the gutter marker for a line, given its 1-based line number.
+///
+/// Used by the `gutter` option of the HTML formatters to render an annotation column,
+/// such as `+`/`-` markers for changelog-style snippets, coverage hits or blame
+/// indicators, independent of the language grammar. Lines without a marker (`None`)
+/// get a blank gutter so the column stays aligned.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::Formatter};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::PlainText)
+///     .gutter(Some(|n| match n {
+///         1 => Some('-'),
+///         2 => Some('+'),
+///         _ => None,
+///     }))
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("old\nnew\nsame", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.contains(r#"data-line="2"><span class="gutter">+</span>new"#));
+/// assert!(html.contains(r#"data-line="3"><span class="gutter"> </span>same"#));
+/// ```
+pub type GutterCallback = fn(usize) -> Option<char>;
+
+/// Decides which language, if any, highlights an injected region.
+///
+/// HTML formatters call the resolver with the injection's language name (e.g. `"sql"`
+/// for SQL inside a string). Returning `None` skips the injection, so the region keeps
+/// only the styles of the surrounding language. Without a resolver, formatters follow
+/// every injection using [`Language::guess`].
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::Formatter};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::Markdown)
+///     .injection_resolver(|name| match name {
+///         "sql" => None,
+///         name => Some(Language::guess(Some(name), "")),
+///     })
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("```sql\nSELECT 1\n```", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(!html.contains("keyword"));
+/// ```
+#[derive(Clone)]
+pub struct InjectionResolver(Arc<ResolveFn>);
+
+type ResolveFn = dyn Fn(&str) -> Option<Language> + Send + Sync;
+
+impl InjectionResolver {
+    pub fn new<F>(resolver: F) -> Self
+    where
+        F: Fn(&str) -> Option<Language> + Send + Sync + 'static,
+    {
+        Self(Arc::new(resolver))
+    }
+
+    pub fn resolve(&self, injected: &str) -> Option<Language> {
+        (self.0)(injected)
+    }
+}
+
+impl std::fmt::Debug for InjectionResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("InjectionResolver(..)")
+    }
+}
+
+// Closures can't be hashed, so formatters with a resolver have no cache key.
+impl Hash for InjectionResolver {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl From<Arc<ResolveFn>> for InjectionResolver {
+    fn from(resolver: Arc<ResolveFn>) -> Self {
+        Self(resolver)
+    }
+}
+
+/// Returns the CSS class of a line, given its 1-based line number.
+///
+/// Used by the `line_class_fn` option of the HTML formatters to decorate lines beyond
+/// a single set of highlighted ranges, e.g. different classes for added, removed and
+/// annotated lines. When set, it replaces the `highlight_lines` option: lines for which
+/// it returns a class get that class appended to `line`, and the ranges and inline
+/// style of `highlight_lines` are ignored.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{HtmlLinkedBuilder, languages::Language, formatter::Formatter};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::PlainText)
+///     .line_class_fn(|n| match n {
+///         1 => Some("removed".to_string()),
+///         2 => Some("added".to_string()),
+///         _ => None,
+///     })
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format("old\nnew\nsame", &mut output).unwrap();
+/// let html = String::from_utf8(output).unwrap();
+/// assert!(html.contains(r#"<div class="line removed" data-line="1">old"#));
+/// assert!(html.contains(r#"<div class="line added" data-line="2">new"#));
+/// assert!(html.contains(r#"<div class="line" data-line="3">same"#));
+/// ```
+#[derive(Clone)]
+pub struct LineClassFn(Arc<ClassFn>);
+
+type ClassFn = dyn Fn(usize) -> Option<String> + Send + Sync;
+
+impl LineClassFn {
+    pub fn new<F>(class: F) -> Self
+    where
+        F: Fn(usize) -> Option<String> + Send + Sync + 'static,
+    {
+        Self(Arc::new(class))
+    }
+
+    pub fn class(&self, line_number: usize) -> Option<String> {
+        (self.0)(line_number)
+    }
+
+    // The class of a line as a suffix for the `line` class.
+    pub(crate) fn class_suffix(&self, line_number: usize) -> Option<String> {
+        self.class(line_number).map(|class| format!(" {class}"))
+    }
+}
+
+impl std::fmt::Debug for LineClassFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LineClassFn(..)")
+    }
+}
+
+// Closures can't be hashed, so formatters with a line class function have no cache key.
+impl Hash for LineClassFn {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl From<Arc<ClassFn>> for LineClassFn {
+    fn from(class: Arc<ClassFn>) -> Self {
+        Self(class)
+    }
+}
+
+// Classes replacing `language-{id}` on the `<code>` element, set with the
+// `language_class_map` option of the HTML formatters.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub(crate) struct LanguageClassMap(pub(crate) HashMap<Language, String>);
+
+impl LanguageClassMap {
+    pub(crate) fn class(&self, lang: &Language) -> String {
+        match self.0.get(lang) {
+            Some(class) => class.clone(),
+            None => format!("language-{}", lang.id_name()),
+        }
+    }
+}
+
+impl LanguageClassMap {
+    // Entries sorted by language id, independent of the map's hash order.
+    fn sorted(&self) -> Vec<(String, &String)> {
+        let mut entries: Vec<(String, &String)> = self
+            .0
+            .iter()
+            .map(|(lang, class)| (lang.id_name(), class))
+            .collect();
+        entries.sort();
+        entries
+    }
+}
+
+impl std::fmt::Debug for LanguageClassMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.sorted()).finish()
+    }
+}
+
+impl Hash for LanguageClassMap {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sorted().hash(state);
+    }
+}
+
+// Highlight configuration for an injected language, using the resolver when given.
+pub(crate) fn injection_config(
+    resolver: Option<&InjectionResolver>,
+    injected: &str,
+) -> Option<&'static HighlightConfiguration> {
+    match resolver {
+        Some(resolver) => resolver.resolve(injected).map(|lang| lang.config()),
+        None => Some(Language::guess(Some(injected), "").config()),
+    }
+}
+
 /// Trait for implementing custom syntax highlighting formatters.
 ///
 /// The `Formatter` trait allows you to create custom output formats for syntax highlighted code.
@@ -216,6 +760,11 @@ pub struct HtmlElement {
 ///
 /// - [`format`](Formatter::format) - Format source code with syntax highlighting
 ///
+/// # Provided Methods
+///
+/// - [`language`](Formatter::language) - The language the formatter highlights
+/// - [`cache_key`](Formatter::cache_key) - Hash of the source and the formatter options
+///
 /// # Creating Custom Formatters
 ///
 /// Use [`highlight_iter()`](crate::highlight::highlight_iter) to stream styled tokens:
@@ -278,10 +827,251 @@ pub trait Formatter: Send + Sync {
     /// formatter.format("fn main() {}", &mut output).unwrap();
     /// ```
     fn format(&self, source: &str, output: &mut dyn Write) -> io::Result<()>;
+
+    /// The language the formatter is configured to highlight.
+    ///
+    /// Built-in formatters return their `lang` option. The default implementation
+    /// returns [`Language::PlainText`], so custom formatters don't have to implement it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::{formatter::Formatter, HtmlLinkedBuilder, languages::Language};
+    ///
+    /// let formatter: Box<dyn Formatter> = Box::new(
+    ///     HtmlLinkedBuilder::new()
+    ///         .lang(Language::Rust)
+    ///         .build()
+    ///         .unwrap(),
+    /// );
+    ///
+    /// assert_eq!(formatter.language(), Language::Rust);
+    /// ```
+    fn language(&self) -> Language {
+        Language::PlainText
+    }
+
+    /// A hash of `source` and the formatter's configuration, to key cached output.
+    ///
+    /// Two formatters with the same options produce the same key for the same source,
+    /// also across runs, as long as the lumis version and the platform don't change.
+    /// Returns `None` when the formatter has callback options such as `gutter`,
+    /// `line_class_fn` or `injection_resolver`, whose output can't be told apart by
+    /// hashing, so it shouldn't be cached. The default implementation only hashes the
+    /// source and [`language`](Formatter::language); custom formatters with options of
+    /// their own should override it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::{formatter::Formatter, HtmlInlineBuilder, languages::Language, themes};
+    ///
+    /// let light = HtmlInlineBuilder::new()
+    ///     .lang(Language::Rust)
+    ///     .theme(themes::get("github_light").ok())
+    ///     .build()
+    ///     .unwrap();
+    /// let dark = HtmlInlineBuilder::new()
+    ///     .lang(Language::Rust)
+    ///     .theme(themes::get("github_dark").ok())
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert!(light.cache_key("fn main() {}").is_some());
+    /// assert_eq!(light.cache_key("fn main() {}"), light.clone().cache_key("fn main() {}"));
+    /// assert_ne!(light.cache_key("fn main() {}"), dark.cache_key("fn main() {}"));
+    /// assert_ne!(light.cache_key("fn main() {}"), light.cache_key("fn main() { }"));
+    /// ```
+    fn cache_key(&self, source: &str) -> Option<u64> {
+        Some(cache_key(source, &self.language()))
+    }
 }
 
 impl Formatter for Box<dyn Formatter> {
     fn format(&self, source: &str, output: &mut dyn Write) -> io::Result<()> {
         (**self).format(source, output)
     }
+
+    fn language(&self) -> Language {
+        (**self).language()
+    }
+
+    fn cache_key(&self, source: &str) -> Option<u64> {
+        (**self).cache_key(source)
+    }
+}
+
+// Hash the source along with the formatter options.
+pub(crate) fn cache_key(source: &str, options: &impl Hash) -> u64 {
+    let mut hasher = Fnv1a::default();
+    options.hash(&mut hasher);
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+// FNV-1a, used instead of the std hasher, whose output may change between Rust releases.
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Error returned by [`parse_line_ranges`] when a line spec is invalid.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LineSpecError {
+    /// A line number could not be parsed as a positive integer.
+    #[error("invalid line number: '{0}'")]
+    InvalidNumber(String),
+
+    /// Line numbers are 1-based, so `0` is never valid.
+    #[error("line numbers must be greater than 0")]
+    ZeroLine,
+
+    /// A range whose start is greater than its end, e.g. `"5-3"`.
+    #[error("start line ({start}) must be less than or equal to end line ({end})")]
+    InvalidRange {
+        /// The first line of the range.
+        start: usize,
+        /// The last line of the range.
+        end: usize,
+    },
+}
+
+/// Parses a line spec such as `"1,3-5,8"` into a list of 1-based line ranges.
+///
+/// This is the same format accepted by the `--highlight-lines` CLI option and can be
+/// used to build the `lines` field of [`html_inline::HighlightLines`] or
+/// [`html_linked::HighlightLines`].
+///
+/// Supported formats:
+/// - `"1"` (single line)
+/// - `"1,3,5"` (multiple single lines)
+/// - `"1-3"` (range from 1 to 3)
+/// - `"1,3-5,8"` (mix of single lines and ranges)
+///
+/// Whitespace around entries is ignored, as are empty entries.
+///
+/// # Errors
+///
+/// Returns a [`LineSpecError`] if an entry is not a number, is `0`,
+/// or is a range whose start is greater than its end.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::formatter::parse_line_ranges;
+///
+/// assert_eq!(parse_line_ranges("1,3-5").unwrap(), vec![1..=1, 3..=5]);
+/// assert!(parse_line_ranges("5-3").is_err());
+/// ```
+pub fn parse_line_ranges(input: &str) -> Result<Vec<RangeInclusive<usize>>, LineSpecError> {
+    fn parse_line(value: &str) -> Result<usize, LineSpecError> {
+        let value = value.trim();
+        let line: usize = value
+            .parse()
+            .map_err(|_| LineSpecError::InvalidNumber(value.to_string()))?;
+
+        if line == 0 {
+            return Err(LineSpecError::ZeroLine);
+        }
+
+        Ok(line)
+    }
+
+    let mut ranges = Vec::new();
+
+    for part in input.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+
+        if let Some((start, end)) = part.split_once('-') {
+            let start = parse_line(start)?;
+            let end = parse_line(end)?;
+
+            if start > end {
+                return Err(LineSpecError::InvalidRange { start, end });
+            }
+
+            ranges.push(start..=end);
+        } else {
+            let line = parse_line(part)?;
+            ranges.push(line..=line);
+        }
+    }
+
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language() {
+        struct Custom;
+
+        impl Formatter for Custom {
+            fn format(&self, _source: &str, _output: &mut dyn Write) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        assert_eq!(Custom.language(), Language::PlainText);
+
+        let terminal: Box<dyn Formatter> =
+            Box::new(TerminalBuilder::new().lang(Language::Rust).build().unwrap());
+        assert_eq!(terminal.language(), Language::Rust);
+    }
+
+    #[test]
+    fn test_parse_line_ranges() {
+        assert_eq!(parse_line_ranges("1").unwrap(), vec![1..=1]);
+        assert_eq!(
+            parse_line_ranges("1,3-5,8").unwrap(),
+            vec![1..=1, 3..=5, 8..=8]
+        );
+        assert_eq!(
+            parse_line_ranges(" 2 - 4 , ,7").unwrap(),
+            vec![2..=4, 7..=7]
+        );
+        assert!(parse_line_ranges("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_line_ranges_errors() {
+        assert_eq!(
+            parse_line_ranges("a"),
+            Err(LineSpecError::InvalidNumber("a".to_string()))
+        );
+        assert_eq!(
+            parse_line_ranges("1-x"),
+            Err(LineSpecError::InvalidNumber("x".to_string()))
+        );
+        assert_eq!(parse_line_ranges("0"), Err(LineSpecError::ZeroLine));
+        assert_eq!(parse_line_ranges("0-2"), Err(LineSpecError::ZeroLine));
+        assert_eq!(
+            parse_line_ranges("5-3"),
+            Err(LineSpecError::InvalidRange { start: 5, end: 3 })
+        );
+        assert_eq!(
+            parse_line_ranges("5-3").unwrap_err().to_string(),
+            "start line (5) must be less than or equal to end line (3)"
+        );
+    }
 }
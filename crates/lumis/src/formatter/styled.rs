@@ -0,0 +1,155 @@
+//! Structured, line-grouped output for TUI frameworks.
+//!
+//! This module provides [`styled_lines`], the structured analog of the
+//! [`Terminal`](super::Terminal) formatter: instead of ANSI escape sequences it returns
+//! the highlighted tokens of each line with their colors and font modifiers, which map
+//! directly to the `Line` and `Span` types of frameworks like Ratatui.
+//!
+//! # Example
+//!
+//! ```rust
+//! use lumis::formatter::styled_lines;
+//! use lumis::{languages::Language, themes};
+//!
+//! let theme = themes::get("dracula").ok();
+//! let lines = styled_lines("fn main() {}\nlet x = 1;", Language::Rust, theme).unwrap();
+//!
+//! assert_eq!(lines.len(), 2);
+//!
+//! // Each span maps to e.g. a Ratatui `Span::styled(span.text, style)`
+//! let span = &lines[0][0];
+//! assert_eq!(span.text, "fn");
+//! assert!(span.fg.is_some());
+//! ```
+
+use crate::highlight::{highlight_iter, HighlightError, Style};
+use crate::languages::Language;
+use crate::themes::{Theme, UnderlineStyle};
+
+/// An RGB color.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    /// Parse a `#rrggbb` hex color, as used by themes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::formatter::Rgb;
+    ///
+    /// assert_eq!(Rgb::from_hex("#ff5555"), Some(Rgb { r: 255, g: 85, b: 85 }));
+    /// assert_eq!(Rgb::from_hex("NONE"), None);
+    /// ```
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        super::ansi::hex_to_rgb(hex).map(|(r, g, b)| Self { r, g, b })
+    }
+}
+
+/// A highlighted piece of text within a line, see [`styled_lines`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StyledSpan {
+    /// The text, without line endings.
+    pub text: String,
+    /// Foreground color, if the theme defines one.
+    pub fg: Option<Rgb>,
+    /// Background color, if the theme defines one.
+    pub bg: Option<Rgb>,
+    pub bold: bool,
+    pub italic: bool,
+    /// Whether the text is underlined, in any underline style.
+    pub underline: bool,
+}
+
+impl StyledSpan {
+    fn new(text: &str, style: &Style) -> Self {
+        Self {
+            text: text.to_string(),
+            fg: style.fg.as_deref().and_then(Rgb::from_hex),
+            bg: style.bg.as_deref().and_then(Rgb::from_hex),
+            bold: style.bold,
+            italic: style.italic,
+            underline: style.text_decoration.underline != UnderlineStyle::None,
+        }
+    }
+}
+
+/// Highlight source code into lines of styled spans.
+///
+/// Tokens spanning several lines are split at the line endings, which are dropped,
+/// so each inner `Vec` holds the spans of one line and empty lines have no spans. A
+/// trailing newline doesn't produce an extra empty line. Without a theme, spans have
+/// no colors or modifiers.
+///
+/// # Errors
+///
+/// Returns an error if highlighting fails.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::formatter::{styled_lines, Rgb};
+/// use lumis::{languages::Language, themes};
+///
+/// let lines = styled_lines("fn main() {}\n", Language::Rust, themes::get("dracula").ok()).unwrap();
+///
+/// assert_eq!(lines.len(), 1);
+/// assert_eq!(lines[0][0].text, "fn");
+/// assert_eq!(lines[0][0].fg, Some(Rgb { r: 139, g: 233, b: 253 }));
+/// ```
+pub fn styled_lines(
+    source: &str,
+    lang: Language,
+    theme: Option<Theme>,
+) -> Result<Vec<Vec<StyledSpan>>, HighlightError> {
+    let mut lines: Vec<Vec<StyledSpan>> = vec![Vec::new()];
+
+    highlight_iter(source, lang, theme, |text, _range, _scope, style| {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Vec::new());
+            }
+
+            let part = part.trim_end_matches('\r');
+            if !part.is_empty() {
+                let line = lines.last_mut().expect("lines is never empty");
+                line.push(StyledSpan::new(part, style));
+            }
+        }
+
+        Ok::<_, std::io::Error>(())
+    })?;
+
+    if source.is_empty() || source.ends_with('\n') {
+        lines.pop();
+    }
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::themes;
+
+    #[test]
+    fn test_styled_lines() {
+        let theme = themes::get("dracula").ok();
+        let lines = styled_lines("/* a\r\n\r\nb */\nx", Language::Rust, theme).unwrap();
+
+        let texts: Vec<Vec<&str>> = lines
+            .iter()
+            .map(|line| line.iter().map(|span| span.text.as_str()).collect())
+            .collect();
+        assert_eq!(texts, vec![vec!["/* a"], vec![], vec!["b */"], vec!["x"]]);
+
+        let comment = &lines[0][0];
+        assert_eq!(comment.fg, Rgb::from_hex("#6272a4"));
+
+        assert!(styled_lines("", Language::Rust, None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,415 @@
+//! SVG formatter for standalone code images.
+//!
+//! This module provides the [`Svg`] formatter that lays out highlighted code as
+//! monospace `<text>`/`<tspan>` elements, suitable for social cards, READMEs and
+//! other places where HTML and CSS aren't available. Layout is computed from
+//! [`FontMetrics`], so no browser or font rasterizer is required.
+//!
+//! # Example Output
+//!
+//! For the Rust code `fn main() {}` with the `dracula` theme applied, the formatter
+//! generates SVG like:
+//!
+//! ```xml
+//! <svg xmlns="http://www.w3.org/2000/svg" width="132.8" height="53" viewBox="0 0 132.8 53" font-family="monospace" font-size="14" xml:space="preserve">
+//! <rect width="100%" height="100%" fill="#282a36"/>
+//! <text x="16" y="26.5" dominant-baseline="central" fill="#f8f8f2"><tspan fill="#8be9fd">fn</tspan> <tspan fill="#50fa7b">main</tspan><tspan fill="#f8f8f2">(</tspan><tspan fill="#f8f8f2">)</tspan> <tspan fill="#f8f8f2">{</tspan><tspan fill="#f8f8f2">}</tspan></text>
+//! </svg>
+//! ```
+//!
+//! See the [formatter](crate::formatter) module for more information and examples.
+
+use super::{ansi, Formatter};
+use crate::highlight::{highlight_iter, Style};
+use crate::languages::Language;
+use crate::themes::{Theme, UnderlineStyle};
+use derive_builder::Builder;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+
+/// Font metrics used to lay out monospace text in [`Svg`] output.
+///
+/// Sizes are in SVG user units (pixels). `char_width` and `line_height` are
+/// relative to `font_size`, so the defaults scale with it.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::formatter::svg::FontMetrics;
+///
+/// let metrics = FontMetrics {
+///     font_family: "'JetBrains Mono', monospace".to_string(),
+///     font_size: 16.0,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontMetrics {
+    /// Value of the `font-family` attribute.
+    pub font_family: String,
+    /// Font size in pixels.
+    pub font_size: f64,
+    /// Advance width of a single character, as a fraction of `font_size`.
+    pub char_width: f64,
+    /// Distance between baselines, as a multiple of `font_size`.
+    pub line_height: f64,
+    /// Space around the code on every side, in pixels.
+    pub padding: f64,
+}
+
+impl Hash for FontMetrics {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.font_family.hash(state);
+        for value in [
+            self.font_size,
+            self.char_width,
+            self.line_height,
+            self.padding,
+        ] {
+            value.to_bits().hash(state);
+        }
+    }
+}
+
+impl Default for FontMetrics {
+    fn default() -> Self {
+        Self {
+            font_family: "monospace".to_string(),
+            font_size: 14.0,
+            char_width: 0.6,
+            line_height: 1.5,
+            padding: 16.0,
+        }
+    }
+}
+
+/// SVG formatter for syntax highlighting.
+///
+/// Generates a self-contained SVG image with one `<text>` element per line and a
+/// `<tspan>` per styled token. Token backgrounds are not rendered; the theme's
+/// background fills the whole image. Tabs advance to the next multiple of `tab_width`
+/// columns and wide characters take two columns, as in the terminal. Use [`SvgBuilder`] to create instances.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{SvgBuilder, languages::Language, themes, formatter::Formatter};
+///
+/// let code = "fn main() {}";
+/// let theme = themes::get("dracula").unwrap();
+///
+/// let formatter = SvgBuilder::new()
+///     .lang(Language::Rust)
+///     .theme(Some(theme))
+///     .build()
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// formatter.format(code, &mut output).unwrap();
+/// let svg = String::from_utf8(output).unwrap();
+/// assert!(svg.starts_with("<svg"));
+/// ```
+#[derive(Builder, Clone, Debug, Hash)]
+#[builder(default)]
+pub struct Svg {
+    lang: Language,
+    theme: Option<Theme>,
+    italic: bool,
+    font_metrics: FontMetrics,
+    tab_width: usize,
+}
+
+impl SvgBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Svg {
+    pub fn new(
+        lang: Language,
+        theme: Option<Theme>,
+        italic: bool,
+        font_metrics: FontMetrics,
+        tab_width: usize,
+    ) -> Self {
+        Self {
+            lang,
+            theme,
+            italic,
+            font_metrics,
+            tab_width,
+        }
+    }
+
+    fn tspan_attrs(&self, style: &Style) -> String {
+        let mut attrs = String::new();
+
+        if let Some(fg) = &style.fg {
+            attrs.push_str(&format!(" fill=\"{}\"", escape(fg)));
+        }
+
+        if style.bold {
+            attrs.push_str(" font-weight=\"bold\"");
+        }
+
+        if self.italic && style.italic {
+            attrs.push_str(" font-style=\"italic\"");
+        }
+
+        let underline = style.text_decoration.underline != UnderlineStyle::None;
+        match (underline, style.text_decoration.strikethrough) {
+            (true, true) => attrs.push_str(" text-decoration=\"underline line-through\""),
+            (true, false) => attrs.push_str(" text-decoration=\"underline\""),
+            (false, true) => attrs.push_str(" text-decoration=\"line-through\""),
+            (false, false) => (),
+        }
+
+        attrs
+    }
+}
+
+impl Default for Svg {
+    fn default() -> Self {
+        Self {
+            lang: Language::PlainText,
+            theme: crate::themes::default(),
+            italic: false,
+            font_metrics: FontMetrics::default(),
+            tab_width: 4,
+        }
+    }
+}
+
+impl Formatter for Svg {
+    fn format(&self, source: &str, output: &mut dyn Write) -> io::Result<()> {
+        let mut lines: Vec<(String, usize)> = vec![(String::new(), 0)];
+
+        highlight_iter(
+            source,
+            self.lang,
+            self.theme.clone(),
+            |text, _range, _scope, style| {
+                let attrs = self.tspan_attrs(style);
+
+                for (i, part) in text.split('\n').enumerate() {
+                    if i > 0 {
+                        lines.push((String::new(), 0));
+                    }
+
+                    let (line, columns) = lines.last_mut().expect("lines is never empty");
+                    let mut expanded = String::with_capacity(part.len());
+                    for c in part.trim_end_matches('\r').chars() {
+                        let width = ansi::cell_width(c, *columns, self.tab_width);
+                        if c == '\t' {
+                            expanded.extend(std::iter::repeat_n(' ', width));
+                        } else {
+                            expanded.push(c);
+                        }
+                        *columns += width;
+                    }
+
+                    let part = expanded;
+                    if part.is_empty() {
+                        continue;
+                    }
+
+                    if attrs.is_empty() || part.trim().is_empty() {
+                        line.push_str(&escape(&part));
+                    } else {
+                        line.push_str(&format!("<tspan{attrs}>{}</tspan>", escape(&part)));
+                    }
+                }
+
+                Ok::<_, io::Error>(())
+            },
+        )
+        .map_err(io::Error::other)?;
+
+        if source.is_empty() || source.ends_with('\n') {
+            lines.pop();
+        }
+
+        let metrics = &self.font_metrics;
+        let line_height = metrics.font_size * metrics.line_height;
+        let columns = lines.iter().map(|(_, columns)| *columns).max().unwrap_or(0);
+        let width = 2.0 * metrics.padding + columns as f64 * metrics.char_width * metrics.font_size;
+        let height = 2.0 * metrics.padding + lines.len() as f64 * line_height;
+
+        writeln!(
+            output,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"{}\" font-size=\"{}\" xml:space=\"preserve\">",
+            number(width),
+            number(height),
+            number(width),
+            number(height),
+            escape(&metrics.font_family),
+            number(metrics.font_size)
+        )?;
+
+        if let Some(bg) = self.theme.as_ref().and_then(|theme| theme.bg()) {
+            writeln!(
+                output,
+                "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+                escape(bg)
+            )?;
+        }
+
+        let fill = self
+            .theme
+            .as_ref()
+            .and_then(|theme| theme.fg())
+            .map(|fg| format!(" fill=\"{}\"", escape(fg)))
+            .unwrap_or_default();
+
+        for (i, (line, _)) in lines.iter().enumerate() {
+            let y = metrics.padding + (i as f64 + 0.5) * line_height;
+            writeln!(
+                output,
+                "<text x=\"{}\" y=\"{}\" dominant-baseline=\"central\"{fill}>{line}</text>",
+                number(metrics.padding),
+                number(y)
+            )?;
+        }
+
+        write!(output, "</svg>")
+    }
+
+    fn language(&self) -> Language {
+        self.lang
+    }
+
+    fn cache_key(&self, source: &str) -> Option<u64> {
+        Some(super::cache_key(source, self))
+    }
+}
+
+// Escape text for use in XML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut buf = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            '"' => buf.push_str("&quot;"),
+            '\'' => buf.push_str("&#39;"),
+            _ => buf.push(c),
+        }
+    }
+
+    buf
+}
+
+// Format a dimension without trailing zeros, e.g. `26.5` or `53`.
+fn number(value: f64) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    format!("{rounded}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::themes;
+
+    #[test]
+    fn test_format_with_theme() {
+        let formatter = SvgBuilder::new()
+            .lang(Language::Rust)
+            .theme(themes::get("dracula").ok())
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("fn main() {}", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let expected = r##"<svg xmlns="http://www.w3.org/2000/svg" width="132.8" height="53" viewBox="0 0 132.8 53" font-family="monospace" font-size="14" xml:space="preserve">
+<rect width="100%" height="100%" fill="#282a36"/>
+<text x="16" y="26.5" dominant-baseline="central" fill="#f8f8f2"><tspan fill="#8be9fd">fn</tspan> <tspan fill="#50fa7b">main</tspan><tspan fill="#f8f8f2">(</tspan><tspan fill="#f8f8f2">)</tspan> <tspan fill="#f8f8f2">{</tspan><tspan fill="#f8f8f2">}</tspan></text>
+</svg>"##;
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_multiline_tokens_and_escaping() {
+        let formatter = SvgBuilder::new()
+            .lang(Language::Rust)
+            .theme(themes::get("dracula").ok())
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter
+            .format("/* <a>\n & */\nlet x = 1;\n", &mut buffer)
+            .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert_eq!(result.matches("<text ").count(), 3);
+        assert!(result.contains("&lt;a&gt;"));
+        assert!(result.contains(" &amp; */"));
+        assert!(!result.contains("\n</tspan>"));
+    }
+
+    #[test]
+    fn test_font_metrics_and_tabs() {
+        let formatter = SvgBuilder::new()
+            .lang(Language::PlainText)
+            .font_metrics(FontMetrics {
+                font_family: "Fira Code".to_string(),
+                font_size: 10.0,
+                char_width: 0.5,
+                line_height: 2.0,
+                padding: 0.0,
+            })
+            .tab_width(2)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("\tab\ncd", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"20\" height=\"40\" viewBox=\"0 0 20 40\" font-family=\"Fira Code\" font-size=\"10\""));
+        assert!(result.contains("<text x=\"0\" y=\"10\" dominant-baseline=\"central\">  ab</text>"));
+        assert!(result.contains("<text x=\"0\" y=\"30\" dominant-baseline=\"central\">cd</text>"));
+        assert!(!result.contains("<rect"));
+    }
+
+    #[test]
+    fn test_tab_stops_and_wide_characters() {
+        let formatter = SvgBuilder::new()
+            .lang(Language::PlainText)
+            .font_metrics(FontMetrics {
+                font_size: 10.0,
+                char_width: 0.5,
+                padding: 0.0,
+                ..FontMetrics::default()
+            })
+            .tab_width(4)
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("ab\tc\n日本語", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        // The tab stops at column 4 and each ideograph takes two columns
+        assert!(result.contains("ab  c</text>"));
+        assert!(result.contains(" width=\"30\" "));
+    }
+
+    #[test]
+    fn test_empty_source() {
+        let formatter = SvgBuilder::new().build().unwrap();
+
+        let mut buffer = Vec::new();
+        formatter.format("", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(!result.contains("<text"));
+        assert!(result.ends_with("</svg>"));
+    }
+}
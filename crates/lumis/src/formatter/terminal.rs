@@ -15,8 +15,12 @@
 //!
 //! See the [formatter](crate::formatter) module for more information and examples.
 
-use super::{ansi, Formatter};
-use crate::{languages::Language, themes::Theme};
+use super::{ansi, ansi::ColorMode, Formatter};
+use crate::{
+    highlight::Style,
+    languages::Language,
+    themes::{Appearance, Theme},
+};
 use derive_builder::Builder;
 use std::io::{self, Write};
 
@@ -24,6 +28,45 @@ use std::io::{self, Write};
 ///
 /// Generates terminal output with ANSI escape sequences. Use [`TerminalBuilder`] to create instances.
 ///
+/// Escape sequences are written straight to the output without allocating per
+/// token, so it's cheap enough to redraw a TUI viewport on every frame.
+///
+/// Theme colors are written as 24-bit RGB by default. Set `color_mode` to
+/// [`ColorMode::Ansi16`] for terminals limited to the 16 standard colors.
+///
+/// With a theme, the output always ends with a reset (`\x1b[0m`), even after a final
+/// newline, so no color bleeds into the shell prompt.
+///
+/// For debugging themes and queries, `include_highlights(true)` prints the scope of
+/// each token as a dim `[scope]` annotation right before it.
+///
+/// Set `wrap(Some(width))` to soft-wrap lines longer than `width` columns. Continuation
+/// rows are indented like the start of the line, so wrapped code keeps its structure.
+/// Widths are measured with [`ansi::display_width`], tabs advance to the next multiple
+/// of 8 columns.
+///
+/// Set `background(true)` to paint the theme's background behind the whole block, like
+/// the HTML output. Each row is filled up to the right edge of the terminal and reset
+/// before the line break, so the shell prompt after the output isn't tinted. Themes
+/// without a background color are written as usual.
+///
+/// To follow the terminal's light or dark background, set a pair of themes with
+/// `themes(light, dark)` and enable `auto_appearance(true)`. The background is guessed
+/// with [`ansi::terminal_appearance`] on every call to `format`, falling back to `theme`
+/// when it can't be detected.
+///
+/// ```rust
+/// use lumis::{TerminalBuilder, languages::Language, themes};
+///
+/// let formatter = TerminalBuilder::new()
+///     .lang(Language::Rust)
+///     .theme(themes::get("dracula").ok())
+///     .themes(themes::get("github_light").unwrap(), themes::get("github_dark").unwrap())
+///     .auto_appearance(true)
+///     .build()
+///     .unwrap();
+/// ```
+///
 /// # Example
 ///
 /// ```rust
@@ -43,22 +86,52 @@ use std::io::{self, Write};
 /// formatter.format(code, &mut output).unwrap();
 /// println!("{}", String::from_utf8(output).unwrap());
 /// ```
-#[derive(Builder, Clone, Debug)]
+#[derive(Builder, Clone, Debug, Hash)]
 #[builder(default)]
 pub struct Terminal {
     lang: Language,
     theme: Option<Theme>,
+    color_mode: ColorMode,
+    include_highlights: bool,
+    wrap: Option<usize>,
+    background: bool,
+    #[builder(setter(custom))]
+    themes: Option<(Theme, Theme)>,
+    auto_appearance: bool,
 }
 
 impl TerminalBuilder {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Set the themes for light and dark terminal backgrounds, used with `auto_appearance`.
+    pub fn themes(&mut self, light: Theme, dark: Theme) -> &mut Self {
+        self.themes = Some(Some((light, dark)));
+        self
+    }
 }
 
 impl Terminal {
     pub fn new(lang: Language, theme: Option<Theme>) -> Self {
-        Self { lang, theme }
+        Self {
+            lang,
+            theme,
+            color_mode: ColorMode::TrueColor,
+            include_highlights: false,
+            wrap: None,
+            background: false,
+            themes: None,
+            auto_appearance: false,
+        }
+    }
+
+    fn theme_for(&self, appearance: Option<Appearance>) -> Option<&Theme> {
+        match (&self.themes, appearance) {
+            (Some((light, _)), Some(Appearance::Light)) => Some(light),
+            (Some((_, dark)), Some(Appearance::Dark)) => Some(dark),
+            _ => self.theme.as_ref(),
+        }
     }
 }
 
@@ -66,23 +139,216 @@ impl Default for Terminal {
     fn default() -> Self {
         Self {
             lang: Language::PlainText,
-            theme: None,
+            theme: crate::themes::default(),
+            color_mode: ColorMode::TrueColor,
+            include_highlights: false,
+            wrap: None,
+            background: false,
+            themes: None,
+            auto_appearance: false,
         }
     }
 }
 
 impl Formatter for Terminal {
     fn format(&self, source: &str, output: &mut dyn Write) -> io::Result<()> {
-        crate::highlight::highlight_iter(
-            source,
-            self.lang,
-            self.theme.clone(),
-            |text, _range, _scope, style| {
-                let ansi_text = ansi::wrap_with_ansi(text, style);
-                write!(output, "{}", ansi_text)
-            },
-        )
-        .map_err(io::Error::other)
+        let mut wrapper = self.wrap.map(LineWrapper::new);
+        let appearance = if self.auto_appearance {
+            ansi::terminal_appearance()
+        } else {
+            None
+        };
+        let theme = self.theme_for(appearance).cloned();
+        let themed = theme.is_some();
+        let painter = Painter::new(
+            self.color_mode,
+            theme
+                .as_ref()
+                .filter(|_| self.background)
+                .and_then(Theme::bg),
+        );
+
+        crate::highlight::highlight_iter(source, self.lang, theme, |text, _range, scope, style| {
+            if self.include_highlights && !scope.is_empty() {
+                write!(output, "\u{1b}[2m[{}]{}", scope, ansi::ANSI_RESET)?;
+            }
+
+            match wrapper.as_mut() {
+                Some(wrapper) => wrapper.write(output, text, style, &painter),
+                None => painter.text(output, text, style),
+            }
+        })
+        .map_err(io::Error::other)?;
+
+        if !source.is_empty() && !source.ends_with('\n') {
+            painter.fill(output)?;
+        }
+
+        // End with a reset even after a trailing newline, so no color leaks into what
+        // the terminal prints next, like the shell prompt
+        if themed && !source.is_empty() {
+            output.write_all(ansi::ANSI_RESET.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn language(&self) -> Language {
+        self.lang
+    }
+
+    fn cache_key(&self, source: &str) -> Option<u64> {
+        Some(super::cache_key(source, self))
+    }
+}
+
+const TAB_STOP: usize = 8;
+
+// Erase from the cursor to the end of the row, painting it with the current background
+const ERASE_LINE: &[u8] = b"\x1b[K";
+
+// Writes tokens and line breaks, filling the rows with the block background when set.
+struct Painter {
+    mode: ColorMode,
+    // Escape sequence setting the background, empty when not filling
+    background: Vec<u8>,
+}
+
+impl Painter {
+    fn new(mode: ColorMode, background: Option<&str>) -> Self {
+        let style = Style {
+            bg: background.map(str::to_string),
+            ..Default::default()
+        };
+        let mut escape = Vec::new();
+        ansi::write_style_ansi_mode(&mut escape, &style, mode)
+            .expect("writing to a Vec never fails");
+
+        Self {
+            mode,
+            background: escape,
+        }
+    }
+
+    fn text(&self, output: &mut dyn Write, text: &str, style: &Style) -> io::Result<()> {
+        if self.background.is_empty() {
+            return ansi::write_with_ansi_mode(output, text, style, self.mode);
+        }
+
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.newline(output)?;
+            }
+
+            if !line.is_empty() {
+                output.write_all(ansi::ANSI_RESET.as_bytes())?;
+                output.write_all(&self.background)?;
+                ansi::write_style_ansi_mode(output, style, self.mode)?;
+                output.write_all(line.as_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn newline(&self, output: &mut dyn Write) -> io::Result<()> {
+        self.fill(output)?;
+        output.write_all(b"\n")
+    }
+
+    // Paint the rest of the row and reset, so nothing after the block is tinted
+    fn fill(&self, output: &mut dyn Write) -> io::Result<()> {
+        if self.background.is_empty() {
+            return Ok(());
+        }
+
+        output.write_all(ansi::ANSI_RESET.as_bytes())?;
+        output.write_all(&self.background)?;
+        output.write_all(ERASE_LINE)?;
+        output.write_all(ansi::ANSI_RESET.as_bytes())
+    }
+}
+
+// Tracks the current column across tokens to soft-wrap lines with a hanging indent.
+struct LineWrapper {
+    width: usize,
+    column: usize,
+    // Column where the current row starts, after the continuation indent
+    row_start: usize,
+    indent: String,
+    indent_width: usize,
+    at_line_start: bool,
+}
+
+impl LineWrapper {
+    fn new(width: usize) -> Self {
+        Self {
+            width,
+            column: 0,
+            row_start: 0,
+            indent: String::new(),
+            indent_width: 0,
+            at_line_start: true,
+        }
+    }
+
+    fn write(
+        &mut self,
+        output: &mut dyn Write,
+        text: &str,
+        style: &Style,
+        painter: &Painter,
+    ) -> io::Result<()> {
+        let mut start = 0;
+
+        for (i, c) in text.char_indices() {
+            if c == '\n' {
+                self.column = 0;
+                self.row_start = 0;
+                self.indent.clear();
+                self.indent_width = 0;
+                self.at_line_start = true;
+                continue;
+            }
+
+            let width = ansi::cell_width(c, self.column, TAB_STOP);
+
+            // Wrap unless the row is still empty, so characters wider than the row
+            // don't wrap forever
+            if self.column + width > self.width && self.column > self.row_start {
+                if start < i {
+                    painter.text(output, &text[start..i], style)?;
+                }
+                start = i;
+
+                let (indent, indent_width) = if self.indent_width < self.width {
+                    (self.indent.as_str(), self.indent_width)
+                } else {
+                    ("", 0)
+                };
+                painter.newline(output)?;
+                painter.text(output, indent, &Style::default())?;
+                self.column = indent_width;
+                self.row_start = indent_width;
+            }
+
+            if self.at_line_start {
+                if c == ' ' || c == '\t' {
+                    self.indent.push(c);
+                    self.indent_width = self.column + width;
+                } else {
+                    self.at_line_start = false;
+                }
+            }
+
+            self.column += width;
+        }
+
+        if start < text.len() {
+            painter.text(output, &text[start..], style)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -103,4 +369,152 @@ mod tests {
         assert!(result.contains(":rust"));
         // Without a theme, some tokens may not have styling, so just check the text is there
     }
+
+    #[test]
+    fn test_color_mode_ansi16() {
+        let formatter = TerminalBuilder::new()
+            .lang(Language::Rust)
+            .theme(crate::themes::get("dracula").ok())
+            .color_mode(ColorMode::Ansi16)
+            .build()
+            .unwrap();
+        let mut buffer = Vec::new();
+        formatter.format("fn main() {}", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.starts_with("\u{1b}[0m\u{1b}[37mfn\u{1b}[0m"));
+        assert!(!result.contains(";2;"));
+    }
+
+    #[test]
+    fn test_wrap() {
+        let formatter = TerminalBuilder::new()
+            .lang(Language::PlainText)
+            .wrap(Some(12))
+            .build()
+            .unwrap();
+        let mut buffer = Vec::new();
+        formatter
+            .format("short\n    indented line\n\t日本語のテキスト", &mut buffer)
+            .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert_eq!(
+            result,
+            "short\n    indented\n     line\n\t日本\n\t語の\n\tテキ\n\tスト"
+        );
+    }
+
+    #[test]
+    fn test_wrap_keeps_styles() {
+        let formatter = TerminalBuilder::new()
+            .lang(Language::Rust)
+            .theme(crate::themes::get("dracula").ok())
+            .wrap(Some(12))
+            .build()
+            .unwrap();
+        let mut buffer = Vec::new();
+        formatter
+            .format("let s = \"abcdef\";", &mut buffer)
+            .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        let string = "\u{1b}[0m\u{1b}[38;2;241;250;140m";
+        assert!(result.contains(&format!("{string}\"abc\u{1b}[0m\n{string}def\"\u{1b}[0m")));
+    }
+
+    #[test]
+    fn test_background() {
+        let render = |source: &str, background: bool, wrap: Option<usize>| {
+            let formatter = TerminalBuilder::new()
+                .lang(Language::PlainText)
+                .theme(crate::themes::get("dracula").ok())
+                .background(background)
+                .wrap(wrap)
+                .build()
+                .unwrap();
+            let mut buffer = Vec::new();
+            formatter.format(source, &mut buffer).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        let bg = "\u{1b}[48;2;40;42;54m";
+        let fill = format!("\u{1b}[0m{bg}\u{1b}[K\u{1b}[0m");
+
+        let reset = "\u{1b}[0m";
+
+        assert_eq!(
+            render("a\n\nb", true, None),
+            format!("\u{1b}[0m{bg}a{fill}\n{fill}\n\u{1b}[0m{bg}b{fill}{reset}")
+        );
+        assert_eq!(
+            render("a\n", true, None),
+            format!("\u{1b}[0m{bg}a{fill}\n{reset}")
+        );
+        assert_eq!(
+            render("  abcd", true, Some(4)),
+            format!("\u{1b}[0m{bg}  ab{fill}\n\u{1b}[0m{bg}  \u{1b}[0m{bg}cd{fill}{reset}")
+        );
+        assert_eq!(render("a\nb", false, None), format!("a\nb{reset}"));
+    }
+
+    #[test]
+    fn test_theme_for_appearance() {
+        let light = crate::themes::get("github_light").unwrap();
+        let dark = crate::themes::get("github_dark").unwrap();
+        let fallback = crate::themes::get("dracula").unwrap();
+        let formatter = TerminalBuilder::new()
+            .theme(Some(fallback.clone()))
+            .themes(light.clone(), dark.clone())
+            .auto_appearance(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(formatter.theme_for(Some(Appearance::Light)), Some(&light));
+        assert_eq!(formatter.theme_for(Some(Appearance::Dark)), Some(&dark));
+        assert_eq!(formatter.theme_for(None), Some(&fallback));
+
+        let formatter = TerminalBuilder::new().theme(None).build().unwrap();
+        assert_eq!(formatter.theme_for(Some(Appearance::Light)), None);
+    }
+
+    #[test]
+    fn test_include_highlights() {
+        let formatter = TerminalBuilder::new()
+            .lang(Language::Rust)
+            .include_highlights(true)
+            .build()
+            .unwrap();
+        let mut buffer = Vec::new();
+        formatter.format("fn main() {}", &mut buffer).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+
+        assert!(result.starts_with(
+            "\u{1b}[2m[keyword.function]\u{1b}[0mfn \u{1b}[2m[function]\u{1b}[0mmain"
+        ));
+    }
+
+    #[test]
+    fn test_ends_with_reset() {
+        let render = |source: &str, theme: Option<Theme>| {
+            let formatter = TerminalBuilder::new()
+                .lang(Language::Rust)
+                .theme(theme)
+                .build()
+                .unwrap();
+            let mut buffer = Vec::new();
+            formatter.format(source, &mut buffer).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+        let dracula = crate::themes::get("dracula").ok();
+
+        for source in ["let s = \"open", "fn main() {}\n", "// comment", "x"] {
+            assert!(
+                render(source, dracula.clone()).ends_with(ansi::ANSI_RESET),
+                "{source:?}"
+            );
+        }
+        assert_eq!(render("", dracula), "");
+        assert_eq!(render("x\n", None), "x\n");
+    }
 }
@@ -78,10 +78,14 @@
 //! ```
 
 use crate::constants::HIGHLIGHT_NAMES;
+use crate::formatter::{ansi, Formatter};
 use crate::languages::Language;
 use crate::themes::Theme;
 use crate::vendor::tree_sitter_highlight::{HighlightEvent, Highlighter as TSHighlighter};
+use serde::Deserialize;
 use smol_str::format_smolstr;
+use std::collections::HashSet;
+use std::io;
 use std::ops::Range;
 use std::sync::Arc;
 use thiserror::Error;
@@ -144,6 +148,41 @@ pub enum HighlightError {
 pub struct Highlighter {
     language: Language,
     theme: Option<Theme>,
+    scope_precedence: ScopePrecedence,
+}
+
+/// Which scope styles text covered by nested highlight scopes.
+///
+/// Tree-sitter captures can nest, like an interpolation inside a string or a
+/// variable inside that interpolation. Styles are not merged: the text gets the
+/// whole style of a single scope, and properties it leaves unset are not inherited
+/// from the other scopes.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::highlight::{Highlighter, ScopePrecedence};
+/// use lumis::languages::Language;
+/// use lumis::themes;
+///
+/// let theme = themes::get("dracula").unwrap();
+/// let highlighter = Highlighter::new(Language::Elixir, Some(theme))
+///     .scope_precedence(ScopePrecedence::Outer);
+///
+/// // The interpolation is rendered with the string style
+/// let segments = highlighter.highlight("\"a#{b}\"").unwrap();
+/// assert!(segments.iter().all(|(style, _)| style.fg.as_deref() == Some("#f1fa8c")));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ScopePrecedence {
+    /// The innermost scope wins, e.g. a variable inside a string interpolation gets
+    /// the variable style. Text in a scope the theme doesn't style gets the default
+    /// style, even when an outer scope is styled.
+    #[default]
+    Inner,
+    /// The outermost scope wins, e.g. a whole string including its interpolations
+    /// gets the string style.
+    Outer,
 }
 
 impl Highlighter {
@@ -169,7 +208,29 @@ impl Highlighter {
     /// let highlighter = Highlighter::new(Language::JavaScript, None);
     /// ```
     pub fn new(language: Language, theme: Option<Theme>) -> Self {
-        Self { language, theme }
+        Self {
+            language,
+            theme,
+            scope_precedence: ScopePrecedence::default(),
+        }
+    }
+
+    /// Set which scope styles text covered by nested scopes, see [`ScopePrecedence`].
+    ///
+    /// Defaults to [`ScopePrecedence::Inner`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::highlight::{Highlighter, ScopePrecedence};
+    /// use lumis::languages::Language;
+    ///
+    /// let highlighter = Highlighter::new(Language::Ruby, None)
+    ///     .scope_precedence(ScopePrecedence::Outer);
+    /// ```
+    pub fn scope_precedence(mut self, scope_precedence: ScopePrecedence) -> Self {
+        self.scope_precedence = scope_precedence;
+        self
     }
 
     /// Highlight the entire source code and return styled segments.
@@ -248,7 +309,12 @@ impl Highlighter {
                 HighlightEvent::Source { start, end } => {
                     let text = &source[start..end];
                     if !text.is_empty() {
-                        let current_style = style_stack.last().map(Arc::clone).unwrap_or_default();
+                        let current_style = match self.scope_precedence {
+                            ScopePrecedence::Inner => style_stack.last(),
+                            ScopePrecedence::Outer => style_stack.get(1).or(style_stack.last()),
+                        }
+                        .map(Arc::clone)
+                        .unwrap_or_default();
                         result.push((current_style, text));
                     }
                 }
@@ -272,6 +338,9 @@ impl Highlighter {
 /// This is a streaming API that processes tokens as they are produced by tree-sitter,
 /// avoiding the overhead of collecting all segments into a vector upfront.
 ///
+/// Text covered by nested scopes is reported with the innermost scope and its style,
+/// as with [`ScopePrecedence::Inner`].
+///
 /// # Arguments
 ///
 /// * `source` - Source code to highlight
@@ -321,7 +390,8 @@ where
         })
         .map_err(|e| HighlightError::HighlighterInit(format!("{:?}", e)))?;
 
-    let mut style_stack: Vec<Style> = vec![Style::default()];
+    let default_style = Style::default();
+    let mut style_stack: Vec<&Style> = vec![&default_style];
     let mut scope_stack: Vec<&'static str> = vec![""];
 
     for event in events {
@@ -335,22 +405,17 @@ where
                 let scope = HIGHLIGHT_NAMES[highlight.0];
                 let specialized_scope = format_smolstr!("{}.{}", scope, lang);
 
-                let new_style = if let Some(ref theme) = theme {
-                    theme
-                        .get_style(&specialized_scope)
-                        .cloned()
-                        .unwrap_or_default()
-                } else {
-                    Style::default()
-                };
+                let new_style = theme
+                    .as_ref()
+                    .and_then(|theme| theme.get_style(&specialized_scope))
+                    .unwrap_or(&default_style);
                 style_stack.push(new_style);
                 scope_stack.push(scope);
             }
             HighlightEvent::Source { start, end } => {
                 let text = &source[start..end];
                 if !text.is_empty() {
-                    let default_style = Style::default();
-                    let current_style = style_stack.last().unwrap_or(&default_style);
+                    let current_style = style_stack.last().copied().unwrap_or(&default_style);
                     let current_scope = scope_stack.last().copied().unwrap_or("");
                     on_event_source(text, start..end, current_scope, current_style)
                         .map_err(|e| HighlightError::EventProcessing(e.to_string()))?;
@@ -370,6 +435,654 @@ where
     Ok(())
 }
 
+/// Distinct scopes highlighted in `source`, including the scopes of injected languages.
+///
+/// Nested scopes are all included, not only the innermost one of each token, since
+/// [`HtmlLinked`](crate::formatter::HtmlLinked) renders a span for each of them. Useful
+/// to generate a stylesheet with only the classes a page uses. Returns an empty set if
+/// the source can't be highlighted.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{constants::{scope_index, CLASSES}, highlight, languages::Language};
+///
+/// let scopes = highlight::used_scopes("fn main() {}", Language::Rust);
+/// assert!(scopes.contains("keyword.function"));
+/// assert!(!scopes.contains("string"));
+///
+/// let classes: Vec<&str> = scopes
+///     .iter()
+///     .filter_map(|scope| scope_index(scope).map(|index| CLASSES[index]))
+///     .collect();
+/// assert!(classes.contains(&"keyword-function"));
+/// ```
+pub fn used_scopes(source: &str, language: Language) -> HashSet<&'static str> {
+    let mut ts_highlighter = TSHighlighter::new();
+    let Ok(events) =
+        ts_highlighter.highlight(language.config(), source.as_bytes(), None, |injected| {
+            Some(Language::guess(Some(injected), "").config())
+        })
+    else {
+        return HashSet::new();
+    };
+
+    events
+        .map_while(Result::ok)
+        .filter_map(|event| match event {
+            HighlightEvent::HighlightStart { highlight, .. } => Some(HIGHLIGHT_NAMES[highlight.0]),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Detect the language of `source` and highlight it in one call.
+///
+/// The language is guessed with [`Language::guess`] from `file_hint` (a language name,
+/// extension or path) and the source content, then passed to `formatter_factory` to
+/// build the formatter, which usually needs it for the `language-*` class. Returns the
+/// detected language along with the output, e.g. to display its name next to the code.
+///
+/// # Panics
+///
+/// Panics if the formatter fails, like [`crate::highlight()`].
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{highlight, languages::Language, HtmlLinkedBuilder};
+///
+/// let (language, html) = highlight::auto("#!/usr/bin/env python3\nprint('hi')", None, |lang| {
+///     HtmlLinkedBuilder::new().lang(lang).build().unwrap()
+/// });
+///
+/// assert_eq!(language, Language::Python);
+/// assert_eq!(language.name(), "Python");
+/// assert!(html.contains("language-python"));
+/// ```
+pub fn auto<F, B>(source: &str, file_hint: Option<&str>, formatter_factory: B) -> (Language, String)
+where
+    B: FnOnce(Language) -> F,
+    F: Formatter,
+{
+    let language = Language::guess(file_hint, source);
+    let output = crate::highlight(source, formatter_factory(language));
+    (language, output)
+}
+
+/// Render source code with the markup of `formatter` but without highlighting.
+///
+/// The output has the same `<pre>`, `<code>` and line `<div>` elements as
+/// `formatter.format(source)`, with the same language class, highlighted lines,
+/// header, container and line decorations, but no token `<span>`s. The text is
+/// escaped with the formatter's `escape_strategy`, braces are kept as-is. Tree-sitter is not invoked,
+/// so it's a cheap raw view to toggle with the highlighted one. Fold regions need the
+/// parse tree and are not rendered.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{highlight, languages::Language, HtmlLinkedBuilder};
+///
+/// let formatter = HtmlLinkedBuilder::new().lang(Language::Rust).build().unwrap();
+/// let html = highlight::plain("fn main() {}", &formatter);
+///
+/// assert_eq!(
+///     html,
+///     "<pre class=\"lumis\"><code class=\"language-rust\" translate=\"no\" tabindex=\"0\"><div class=\"line\" data-line=\"1\">fn main() {}\n</div></code></pre>"
+/// );
+/// ```
+pub fn plain(source: &str, formatter: &crate::formatter::HtmlLinked) -> String {
+    let mut buffer = Vec::new();
+    formatter
+        .format_plain(source, &mut buffer)
+        .expect("writing to a Vec never fails");
+    String::from_utf8(buffer).expect("rendered HTML is valid UTF-8")
+}
+
+/// Highlight the code segments of a document that mixes prose and code.
+///
+/// `segment_fn` defines the segment boundaries: it's called with every line outside a
+/// segment, without the line ending, and returns the language and the closing delimiter
+/// when the line opens a segment. The segment ends at the next line that equals the
+/// delimiter, ignoring surrounding whitespace, or at the end of the input.
+///
+/// The delimiter lines are dropped and the code in between is rendered by the
+/// [`HtmlLinked`](crate::formatter::HtmlLinked) formatter, so the output needs a
+/// linked theme stylesheet. Prose lines are copied to the output unchanged.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{highlight, languages::Language};
+///
+/// let doc = "Print a greeting:\n<<< ruby\nputs 'hi'\n>>>\nDone.\n";
+///
+/// let html = highlight::segments(doc, |line| {
+///     let hint = line.strip_prefix("<<<")?;
+///     Some((Language::guess(Some(hint.trim()), ""), ">>>"))
+/// });
+///
+/// assert!(html.starts_with("Print a greeting:\n<pre class=\"lumis\"><code class=\"language-ruby\""));
+/// assert!(html.ends_with("</code></pre>\nDone.\n"));
+/// ```
+pub fn segments(input: &str, segment_fn: impl Fn(&str) -> Option<(Language, &str)>) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut segment: Option<(Language, &str, usize)> = None;
+    let mut offset = 0;
+
+    for line in input.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let content = line.trim_end_matches(['\r', '\n']);
+
+        match segment {
+            Some((language, delimiter, code_start)) if content.trim() == delimiter.trim() => {
+                output.push_str(&highlight_segment(&input[code_start..start], language));
+                output.push_str(&line[content.len()..]);
+                segment = None;
+            }
+            Some(_) => {}
+            None => match segment_fn(content) {
+                Some((language, delimiter)) => segment = Some((language, delimiter, offset)),
+                None => output.push_str(line),
+            },
+        }
+    }
+
+    if let Some((language, _, code_start)) = segment {
+        output.push_str(&highlight_segment(&input[code_start..], language));
+    }
+
+    output
+}
+
+fn highlight_segment(source: &str, language: Language) -> String {
+    let formatter = crate::formatter::HtmlLinked::new(language, None, None, None);
+    crate::highlight(source, formatter)
+}
+
+/// Render a side-by-side diff of two versions of a file, with both sides highlighted.
+///
+/// The lines of `old` and `new` are diffed, then laid out in a `<table class="lumis-diff">`
+/// with one row per line: the old line number and code on the left, the new ones on the
+/// right. Rows are classed `unchanged`, `removed`, `added` or `changed`; removed and added
+/// lines next to each other are paired up as changed rows. Missing sides have empty
+/// cells classed `empty`.
+///
+/// `formatter_factory` builds the formatter for `lang`. Each side is highlighted once, so
+/// constructs spanning several lines such as block comments keep their context, then split
+/// at its `<div class="line">` elements: every cell holds one line, inside the formatter's
+/// `<pre>` and `<code>` tags. Formatters that don't render one such element per line
+/// format each line on its own instead.
+///
+/// # Panics
+///
+/// Panics if the formatter fails, like [`crate::highlight()`].
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{highlight, languages::Language, HtmlLinkedBuilder};
+///
+/// let old = "fn main() {\n    println!(\"hi\");\n}\n";
+/// let new = "fn main() {\n    println!(\"hello\");\n}\n";
+///
+/// let html = highlight::side_by_side(old, new, Language::Rust, |lang| {
+///     HtmlLinkedBuilder::new().lang(lang).build().unwrap()
+/// });
+///
+/// assert!(html.starts_with(r#"<table class="lumis-diff"><tbody><tr class="unchanged">"#));
+/// assert_eq!(html.matches(r#"<tr class="changed">"#).count(), 1);
+/// ```
+pub fn side_by_side<F, B>(old: &str, new: &str, lang: Language, formatter_factory: B) -> String
+where
+    B: FnOnce(Language) -> F,
+    F: Formatter,
+{
+    let formatter = formatter_factory(lang);
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let old_cells = diff_cells(&formatter, old, &old_lines);
+    let new_cells = diff_cells(&formatter, new, &new_lines);
+
+    let cell = |side: &str, line: Option<(usize, &str)>| match line {
+        Some((index, html)) => format!(
+            "<td class=\"lumis-diff-number\">{}</td><td class=\"lumis-diff-{side}\">{html}</td>",
+            index + 1
+        ),
+        None => format!(
+            "<td class=\"lumis-diff-number\"></td><td class=\"lumis-diff-{side} empty\"></td>"
+        ),
+    };
+
+    let mut output = String::from("<table class=\"lumis-diff\"><tbody>");
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+
+    let flush = |output: &mut String, removed: &mut Vec<usize>, added: &mut Vec<usize>| {
+        for row in 0..removed.len().max(added.len()) {
+            let old_line = removed.get(row).map(|&i| (i, old_cells[i].as_str()));
+            let new_line = added.get(row).map(|&j| (j, new_cells[j].as_str()));
+            let class = match (old_line, new_line) {
+                (Some(_), Some(_)) => "changed",
+                (Some(_), None) => "removed",
+                _ => "added",
+            };
+            output.push_str(&format!(
+                "<tr class=\"{class}\">{}{}</tr>",
+                cell("old", old_line),
+                cell("new", new_line)
+            ));
+        }
+        removed.clear();
+        added.clear();
+    };
+
+    for op in diff_lines(&old_lines, &new_lines) {
+        match op {
+            DiffOp::Removed(i) => removed.push(i),
+            DiffOp::Added(j) => added.push(j),
+            DiffOp::Unchanged(i, j) => {
+                flush(&mut output, &mut removed, &mut added);
+                output.push_str(&format!(
+                    "<tr class=\"unchanged\">{}{}</tr>",
+                    cell("old", Some((i, old_cells[i].as_str()))),
+                    cell("new", Some((j, new_cells[j].as_str())))
+                ));
+            }
+        }
+    }
+    flush(&mut output, &mut removed, &mut added);
+
+    output.push_str("</tbody></table>");
+    output
+}
+
+// Render the cell of each line of one side of a diff: the whole side is formatted once
+// and every line `<div>` is wrapped in the markup around the lines.
+fn diff_cells<F: Formatter>(formatter: &F, source: &str, lines: &[&str]) -> Vec<String> {
+    let format = |source: &str| {
+        let mut buffer = Vec::new();
+        formatter
+            .format(source, &mut buffer)
+            .expect("failed to format diff cell");
+        String::from_utf8_lossy(&buffer).into_owned()
+    };
+
+    let html = format(source);
+    let starts: Vec<usize> = html
+        .match_indices("<div class=\"line")
+        .map(|(i, _)| i)
+        .collect();
+    let end = html.rfind("</div>").map(|i| i + "</div>".len());
+
+    match (starts.first(), end) {
+        (Some(&first), Some(end)) if starts.len() == lines.len() && end > first => {
+            let (open, close) = (&html[..first], &html[end..]);
+            let bounds = starts.iter().skip(1).copied().chain([end]);
+            starts
+                .iter()
+                .zip(bounds)
+                .map(|(&start, end)| format!("{open}{}{close}", &html[start..end]))
+                .collect()
+        }
+        _ => lines.iter().map(|line| format(line)).collect(),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum DiffOp {
+    Unchanged(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+// Line diff with Myers' algorithm in linear space: after trimming the common prefix and
+// suffix, each step bisects the edit graph at a point on a shortest edit path and diffs
+// both halves.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    diff_ranges(old, new, 0..old.len(), 0..new.len(), &mut ops);
+    ops
+}
+
+fn diff_ranges(
+    old: &[&str],
+    new: &[&str],
+    mut old_range: Range<usize>,
+    mut new_range: Range<usize>,
+    ops: &mut Vec<DiffOp>,
+) {
+    while !old_range.is_empty()
+        && !new_range.is_empty()
+        && old[old_range.start] == new[new_range.start]
+    {
+        ops.push(DiffOp::Unchanged(old_range.start, new_range.start));
+        old_range.start += 1;
+        new_range.start += 1;
+    }
+
+    let mut suffix = 0;
+    while !old_range.is_empty()
+        && !new_range.is_empty()
+        && old[old_range.end - 1] == new[new_range.end - 1]
+    {
+        old_range.end -= 1;
+        new_range.end -= 1;
+        suffix += 1;
+    }
+
+    match bisect(old, new, old_range.clone(), new_range.clone()) {
+        Some((x, y)) => {
+            diff_ranges(old, new, old_range.start..x, new_range.start..y, ops);
+            diff_ranges(old, new, x..old_range.end, y..new_range.end, ops);
+        }
+        None => {
+            ops.extend(old_range.clone().map(DiffOp::Removed));
+            ops.extend(new_range.clone().map(DiffOp::Added));
+        }
+    }
+
+    ops.extend((0..suffix).map(|k| DiffOp::Unchanged(old_range.end + k, new_range.end + k)));
+}
+
+// Find where the furthest reaching forward and reverse paths meet, as the absolute
+// `(old, new)` indexes to split at. Returns `None` when either side is empty or the
+// sides have no line in common.
+fn bisect(
+    old: &[&str],
+    new: &[&str],
+    old_range: Range<usize>,
+    new_range: Range<usize>,
+) -> Option<(usize, usize)> {
+    let old = &old[old_range.clone()];
+    let new = &new[new_range.clone()];
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    if n == 0 || m == 0 {
+        return None;
+    }
+
+    // forward[offset + k] is the furthest x reached on diagonal k = x - y from the start,
+    // reverse[offset + k] the furthest reached from the end, counting backwards.
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d + 1;
+    let mut forward = vec![-1; (2 * offset + 1) as usize];
+    let mut reverse = forward.clone();
+    forward[(offset + 1) as usize] = 0;
+    reverse[(offset + 1) as usize] = 0;
+
+    let delta = n - m;
+    let front = delta % 2 != 0;
+    let split =
+        |x: isize, y: isize| Some((old_range.start + x as usize, new_range.start + y as usize));
+
+    // Diagonals that ran off the edit graph are skipped in later rounds.
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+
+    for d in 0..=max_d {
+        for k1 in (-d + k1_start..=d - k1_end).step_by(2) {
+            let i = (offset + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && forward[i - 1] < forward[i + 1]) {
+                forward[i + 1]
+            } else {
+                forward[i - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && old[x1 as usize] == new[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            forward[i] = x1;
+
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let j = offset + delta - k1;
+                if (0..reverse.len() as isize).contains(&j) && reverse[j as usize] != -1 {
+                    let x2 = n - reverse[j as usize];
+                    if x1 >= x2 {
+                        return split(x1, y1);
+                    }
+                }
+            }
+        }
+
+        for k2 in (-d + k2_start..=d - k2_end).step_by(2) {
+            let i = (offset + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && reverse[i - 1] < reverse[i + 1]) {
+                reverse[i + 1]
+            } else {
+                reverse[i - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && old[(n - x2 - 1) as usize] == new[(m - y2 - 1) as usize] {
+                x2 += 1;
+                y2 += 1;
+            }
+            reverse[i] = x2;
+
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let j = offset + delta - k2;
+                if (0..forward.len() as isize).contains(&j) && forward[j as usize] != -1 {
+                    let x1 = forward[j as usize];
+                    let y1 = x1 - (j - offset);
+                    if x1 >= n - x2 {
+                        return split(x1, y1);
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Highlight many snippets at once, in parallel with the `rayon` feature.
+///
+/// Each `(source, language)` item is rendered as inline-styled HTML with `theme`, by
+/// its own highlighter. With the `rayon` feature enabled the items are spread across
+/// the rayon thread pool, otherwise they're highlighted one after the other. The
+/// output is in the same order as `items` either way.
+///
+/// # Panics
+///
+/// Panics if formatting any item fails, like [`crate::highlight()`].
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{highlight, languages::Language, themes};
+///
+/// let snippets = vec![
+///     ("fn main() {}", Language::Rust),
+///     ("print('hi')", Language::Python),
+/// ];
+///
+/// let html = highlight::par_highlight(snippets, themes::get("dracula").ok());
+/// assert!(html[0].contains("language-rust"));
+/// assert!(html[1].contains("language-python"));
+/// ```
+pub fn par_highlight<S>(items: Vec<(S, Language)>, theme: Option<Theme>) -> Vec<String>
+where
+    S: AsRef<str> + Send,
+{
+    let highlight_item = |(source, lang): (S, Language)| {
+        let formatter =
+            crate::formatter::HtmlInline::new(lang, theme.clone(), None, false, false, None, None);
+        crate::highlight(source.as_ref(), formatter)
+    };
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        items.into_par_iter().map(highlight_item).collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        items.into_iter().map(highlight_item).collect()
+    }
+}
+
+/// A code cell of a Jupyter notebook rendered by [`notebook()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CellOutput {
+    /// Position of the cell in the notebook, counting cells of every type.
+    pub index: usize,
+    /// Language the cell was highlighted with.
+    pub language: Language,
+    /// The cell source rendered by the [`HtmlInline`](crate::formatter::HtmlInline) formatter.
+    pub html: String,
+}
+
+#[derive(Deserialize)]
+struct Notebook {
+    #[serde(default)]
+    metadata: NotebookMetadata,
+    cells: Vec<NotebookCell>,
+}
+
+#[derive(Default, Deserialize)]
+struct NotebookMetadata {
+    kernelspec: Option<KernelSpec>,
+    language_info: Option<LanguageInfo>,
+}
+
+#[derive(Deserialize)]
+struct KernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LanguageInfo {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NotebookCell {
+    cell_type: String,
+    source: CellSource,
+}
+
+// nbformat allows the source as a single string or as a list of lines.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CellSource {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+/// Highlight the code cells of a Jupyter notebook (`.ipynb`).
+///
+/// Parses the notebook JSON and renders each code cell as inline-styled HTML, using the
+/// kernel language from the notebook metadata (`language_info.name`, then
+/// `kernelspec.language`) and falling back to Python. Markdown and raw cells are
+/// skipped, but still count towards each [`CellOutput::index`].
+///
+/// # Errors
+///
+/// Returns an [`io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData) error if
+/// `json_src` isn't a valid notebook.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::highlight::notebook;
+/// use lumis::languages::Language;
+/// use lumis::themes;
+///
+/// let ipynb = r##"{
+///   "metadata": {"kernelspec": {"language": "python", "name": "python3"}},
+///   "cells": [
+///     {"cell_type": "markdown", "metadata": {}, "source": ["# Title"]},
+///     {"cell_type": "code", "metadata": {}, "outputs": [], "source": ["import os\n", "print(os.name)"]}
+///   ]
+/// }"##;
+///
+/// let cells = notebook(ipynb, themes::get("dracula").ok()).unwrap();
+/// assert_eq!(cells.len(), 1);
+/// assert_eq!(cells[0].index, 1);
+/// assert_eq!(cells[0].language, Language::Python);
+/// assert!(cells[0].html.contains("language-python"));
+/// ```
+pub fn notebook(json_src: &str, theme: Option<Theme>) -> io::Result<Vec<CellOutput>> {
+    let notebook: Notebook = serde_json::from_str(json_src)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let kernel_language = notebook
+        .metadata
+        .language_info
+        .and_then(|info| info.name)
+        .or_else(|| notebook.metadata.kernelspec.and_then(|spec| spec.language))
+        .unwrap_or_else(|| "python".to_string());
+    let language = Language::guess(Some(&kernel_language), "");
+
+    let formatter = crate::formatter::HtmlInlineBuilder::new()
+        .lang(language)
+        .theme(theme)
+        .build()
+        .map_err(io::Error::other)?;
+
+    let mut outputs = Vec::new();
+    for (index, cell) in notebook.cells.into_iter().enumerate() {
+        if cell.cell_type != "code" {
+            continue;
+        }
+
+        let source = match cell.source {
+            CellSource::Text(text) => text,
+            CellSource::Lines(lines) => lines.concat(),
+        };
+
+        let mut html = Vec::new();
+        formatter.format(&source, &mut html)?;
+
+        outputs.push(CellOutput {
+            index,
+            language,
+            html: String::from_utf8(html).map_err(io::Error::other)?,
+        });
+    }
+
+    Ok(outputs)
+}
+
+/// Returns the display width of the longest line of `source`, in monospace cells.
+///
+/// Tabs advance to the next multiple of `tab_width` columns, so a tab is never wider
+/// than `tab_width`. East Asian wide and fullwidth characters and emoji take two cells,
+/// while combining marks, zero-width characters and control characters take none.
+/// Line endings, `\n` or `\r\n`, are not counted. Useful to size code images or
+/// align columns before rendering.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::highlight;
+///
+/// assert_eq!(highlight::max_display_width("fn main() {}\nlet x = 1;", 4), 12);
+/// assert_eq!(highlight::max_display_width("\tab", 4), 6);
+/// assert_eq!(highlight::max_display_width("// 日本語", 4), 9);
+/// ```
+pub fn max_display_width(source: &str, tab_width: usize) -> usize {
+    source
+        .split('\n')
+        .map(|line| {
+            line.trim_end_matches('\r').chars().fold(0, |column, c| {
+                column + ansi::cell_width(c, column, tab_width)
+            })
+        })
+        .max()
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -414,6 +1127,49 @@ mod tests {
         assert_eq!(reconstructed, code);
     }
 
+    #[test]
+    fn test_scope_precedence() {
+        let code = "x = \"a#{b}\"";
+        let theme = crate::themes::get("dracula").ok();
+        let fg = |precedence| {
+            Highlighter::new(Language::Elixir, theme.clone())
+                .scope_precedence(precedence)
+                .highlight(code)
+                .unwrap()
+                .into_iter()
+                .map(|(style, text)| (text, style.fg.clone()))
+                .collect::<Vec<_>>()
+        };
+        let string = Some("#f1fa8c".to_string());
+
+        // The interpolation and the variable inside it override the string style
+        let inner = fg(ScopePrecedence::Inner);
+        assert!(inner.contains(&("\"a", string.clone())));
+        assert!(inner.contains(&("#{", Some("#50fa7b".to_string()))));
+        assert!(inner.contains(&("b", Some("#f8f8f2".to_string()))));
+
+        // The whole string keeps its style, the variable outside it is unaffected
+        let outer = fg(ScopePrecedence::Outer);
+        assert!(outer.contains(&("x", Some("#f8f8f2".to_string()))));
+        for text in ["\"a", "#{", "b", "}", "\""] {
+            assert!(outer.contains(&(text, string.clone())), "{text}");
+        }
+
+        // highlight_iter always lets the innermost scope win
+        let mut streamed = Vec::new();
+        highlight_iter(
+            code,
+            Language::Elixir,
+            theme.clone(),
+            |_text, range, _, style| {
+                streamed.push((&code[range], style.fg.clone()));
+                Ok::<_, std::io::Error>(())
+            },
+        )
+        .unwrap();
+        assert_eq!(streamed, inner);
+    }
+
     #[test]
     fn test_streaming_api() {
         let code = "let x = 42;";
@@ -459,6 +1215,23 @@ mod tests {
         assert!(has_colors, "Expected at least some segments with colors");
     }
 
+    #[test]
+    fn test_max_display_width() {
+        assert_eq!(max_display_width("", 4), 0);
+        assert_eq!(max_display_width("ab\r\nabc\r\n", 4), 3);
+        assert_eq!(max_display_width("a\tb\n\t\tc", 4), 9);
+        assert_eq!(max_display_width("abc\td", 4), 5);
+        assert_eq!(max_display_width("\tx", 0), 1);
+        assert_eq!(max_display_width("한국어 🦀", 4), 9);
+        assert_eq!(max_display_width("ｆｕｌｌ", 4), 8);
+        assert_eq!(max_display_width("e\u{301}\u{200b}", 4), 1);
+
+        // Same widths as the terminal formatter
+        for text in ["⚡ ok", "ก\u{e31}", "\u{feff}x", "🦀🦀"] {
+            assert_eq!(max_display_width(text, 4), ansi::display_width(text));
+        }
+    }
+
     #[test]
     fn test_empty_source() {
         let code = "";
@@ -477,4 +1250,428 @@ mod tests {
         let reconstructed: String = segments.iter().map(|(_, text)| *text).collect();
         assert_eq!(reconstructed, code);
     }
+
+    #[test]
+    fn test_segments() {
+        let doc = "intro\n```rust\nfn a() {}\n```\nmiddle\n```\nplain\n";
+
+        let html = segments(doc, |line| {
+            let hint = line.strip_prefix("```")?;
+            Some((Language::guess(Some(hint), ""), "```"))
+        });
+
+        let rust = crate::highlight(
+            "fn a() {}\n",
+            crate::formatter::HtmlLinked::new(Language::Rust, None, None, None),
+        );
+        let plain = crate::highlight(
+            "plain\n",
+            crate::formatter::HtmlLinked::new(Language::PlainText, None, None, None),
+        );
+        assert_eq!(html, format!("intro\n{rust}\nmiddle\n{plain}"));
+    }
+
+    #[test]
+    fn test_par_highlight() {
+        let theme = themes::get("dracula").ok();
+        let items: Vec<(String, Language)> = (0..16)
+            .map(|i| (format!("let x = {i};"), Language::Rust))
+            .collect();
+
+        let expected: Vec<String> = items
+            .iter()
+            .map(|(source, lang)| {
+                let formatter = crate::formatter::HtmlInline::new(
+                    *lang,
+                    theme.clone(),
+                    None,
+                    false,
+                    false,
+                    None,
+                    None,
+                );
+                crate::highlight(source, formatter)
+            })
+            .collect();
+
+        assert_eq!(par_highlight(items, theme), expected);
+    }
+
+    #[test]
+    fn test_auto() {
+        let (language, output) = auto("fn main() {}", Some("src/main.rs"), |lang| {
+            crate::TerminalBuilder::new().lang(lang).build().unwrap()
+        });
+
+        assert_eq!(language, Language::Rust);
+        assert_eq!(output, "fn main() {}");
+    }
+
+    #[test]
+    fn test_plain() {
+        use crate::formatter::html_linked::HighlightLines;
+
+        let formatter = crate::HtmlLinkedBuilder::new()
+            .lang(Language::Rust)
+            .pre_class(Some("raw".to_string()))
+            .highlight_lines(Some(HighlightLines {
+                lines: vec![2..=2],
+                ..Default::default()
+            }))
+            .build()
+            .unwrap();
+        let source = "let s = \"<a>\";\r\n\tif x { 'y' }\n";
+
+        assert_eq!(
+            plain(source, &formatter),
+            "<pre class=\"lumis raw\"><code class=\"language-rust\" translate=\"no\" tabindex=\"0\"><div class=\"line\" data-line=\"1\">let s = &quot;&lt;a&gt;&quot;;\n</div><div class=\"line highlighted\" data-line=\"2\">\tif x { &#39;y&#39; }\n</div></code></pre>"
+        );
+
+        // Same lines as the highlighted view once the token spans are removed
+        let highlighted = crate::highlight(source, formatter.clone());
+        let spans = regex::Regex::new("</?span[^>]*>").unwrap();
+        let highlighted = spans.replace_all(&highlighted, "");
+        assert_eq!(
+            highlighted
+                .replace("&lbrace;", "{")
+                .replace("&rbrace;", "}"),
+            plain(source, &formatter)
+        );
+
+        assert_eq!(
+            plain("", &formatter),
+            "<pre class=\"lumis raw\"><code class=\"language-rust\" translate=\"no\" tabindex=\"0\"></code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let ops = diff_lines(&["a", "b", "c", "d"], &["a", "x", "c", "d", "e"]);
+
+        assert_eq!(
+            ops,
+            vec![
+                DiffOp::Unchanged(0, 0),
+                DiffOp::Removed(1),
+                DiffOp::Added(1),
+                DiffOp::Unchanged(2, 2),
+                DiffOp::Unchanged(3, 3),
+                DiffOp::Added(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let html = side_by_side("a\nb\n", "a\n", Language::PlainText, |lang| {
+            crate::HtmlLinkedBuilder::new().lang(lang).build().unwrap()
+        });
+
+        let code = |line: usize, text: &str| {
+            format!("<pre class=\"lumis\"><code class=\"language-plaintext\" translate=\"no\" tabindex=\"0\"><div class=\"line\" data-line=\"{line}\">{text}\n</div></code></pre>")
+        };
+        assert_eq!(
+            html,
+            format!(
+                "<table class=\"lumis-diff\"><tbody><tr class=\"unchanged\"><td class=\"lumis-diff-number\">1</td><td class=\"lumis-diff-old\">{}</td><td class=\"lumis-diff-number\">1</td><td class=\"lumis-diff-new\">{}</td></tr><tr class=\"removed\"><td class=\"lumis-diff-number\">2</td><td class=\"lumis-diff-old\">{}</td><td class=\"lumis-diff-number\"></td><td class=\"lumis-diff-new empty\"></td></tr></tbody></table>",
+                code(1, "a"),
+                code(1, "a"),
+                code(2, "b")
+            )
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "lang-rust")]
+    fn test_side_by_side_keeps_multiline_context() {
+        let html = side_by_side("/* a\nb */\n", "/* a\nc */\n", Language::Rust, |lang| {
+            crate::HtmlLinkedBuilder::new().lang(lang).build().unwrap()
+        });
+
+        assert!(
+            html.contains(r#"<div class="line" data-line="2"><span class="comment">b */</span>"#),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"<div class="line" data-line="2"><span class="comment">c */</span>"#),
+            "{html}"
+        );
+    }
+
+    #[cfg(any(
+        feature = "lang-toml",
+        feature = "lang-yaml",
+        feature = "lang-protobuf",
+        feature = "lang-markdown",
+        feature = "lang-html",
+        feature = "lang-javascript",
+        feature = "lang-elixir"
+    ))]
+    fn value_scopes(source: &str, lang: Language) -> Vec<(String, &'static str)> {
+        let mut scopes = Vec::new();
+        highlight_iter(source, lang, None, |text, _range, scope, _style| {
+            if !text.trim().is_empty() {
+                scopes.push((text.to_string(), scope));
+            }
+            Ok::<_, std::io::Error>(())
+        })
+        .unwrap();
+        scopes
+    }
+
+    #[test]
+    #[cfg(feature = "lang-toml")]
+    fn test_toml_value_scopes() {
+        let source =
+            "title = \"lumis\"\nport = 8080\npi = 3.14\nenabled = true\nday = 1979-05-27\n";
+        let scopes = value_scopes(source, Language::Toml);
+
+        for (text, scope) in [
+            ("\"lumis\"", "string"),
+            ("8080", "number"),
+            ("3.14", "number.float"),
+            ("true", "boolean"),
+            ("1979-05-27", "string.special"),
+        ] {
+            assert!(
+                scopes.contains(&(text.to_string(), scope)),
+                "{text} should be {scope}: {scopes:?}"
+            );
+        }
+
+        for theme in themes::all() {
+            for scope in ["string", "number", "boolean"] {
+                let fg = theme.get_style(scope).and_then(|style| style.fg.as_ref());
+                assert!(fg.is_some(), "{} doesn't style {scope}", theme.name);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "lang-yaml")]
+    fn test_yaml_value_scopes() {
+        let source = "title: lumis\nport: 8080\npi: 3.14\nenabled: true\nnothing: null\n";
+        let scopes = value_scopes(source, Language::YAML);
+
+        for (text, scope) in [
+            ("lumis", "string"),
+            ("8080", "number"),
+            ("3.14", "number.float"),
+            ("true", "boolean"),
+            ("null", "constant.builtin"),
+        ] {
+            assert!(
+                scopes.contains(&(text.to_string(), scope)),
+                "{text} should be {scope}: {scopes:?}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "lang-protobuf")]
+    fn test_protobuf_scopes() {
+        let source = r#"syntax = "proto3";
+package acme.greeter.v1;
+import "google/protobuf/timestamp.proto";
+
+message HelloRequest {
+  string name = 1;
+  map<string, int32> counts = 2;
+  oneof target { string user = 3; }
+}
+
+service Greeter {
+  rpc SayHello (HelloRequest) returns (stream HelloReply);
+}
+"#;
+        let scopes = value_scopes(source, Language::ProtoBuf);
+
+        for (text, scope) in [
+            ("package", "keyword.import"),
+            ("greeter", "module"),
+            ("message", "keyword.type"),
+            ("HelloRequest", "type"),
+            ("name", "property"),
+            ("1", "number"),
+            ("map", "type.builtin"),
+            ("counts", "property"),
+            ("user", "property"),
+            ("service", "keyword.type"),
+            ("Greeter", "type"),
+            ("rpc", "keyword.function"),
+            ("SayHello", "function.method"),
+            ("stream", "keyword.modifier"),
+            ("HelloReply", "type"),
+        ] {
+            assert!(
+                scopes.contains(&(text.to_string(), scope)),
+                "{text} should be {scope}: {scopes:?}"
+            );
+        }
+
+        // Every theme tells rpc definitions apart from message types
+        for theme in themes::all() {
+            let fg = |scope| theme.get_style(scope).and_then(|style| style.fg.as_ref());
+            for scope in ["type", "function.method", "number", "keyword"] {
+                assert!(fg(scope).is_some(), "{} doesn't style {scope}", theme.name);
+            }
+            assert_ne!(fg("type"), fg("function.method"), "{}", theme.name);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "lang-rust", feature = "lang-markdown"))]
+    fn test_used_scopes() {
+        let scopes = used_scopes("# Title\n\n```rust\nlet x = 1;\n```\n", Language::Markdown);
+
+        for scope in ["markup.heading.1", "markup.raw.block", "keyword", "number"] {
+            assert!(scopes.contains(scope), "missing {scope} in {scopes:?}");
+        }
+        assert!(!scopes.contains("string"));
+        assert!(used_scopes("", Language::Rust).is_empty());
+    }
+
+    #[test]
+    #[cfg(all(feature = "lang-rust", feature = "lang-markdown"))]
+    fn test_rust_doc_comment_code_fences() {
+        let source = "/// Adds one.\n///\n/// ```rust\n/// let x = add(1);\n/// ```\n///\n/// ```\n/// plain(1);\n/// ```\nfn add(a: i32) -> i32 {\n    a + 1\n}\n";
+        let scopes = value_scopes(source, Language::Rust);
+
+        for (text, scope) in [
+            (" ```", "markup.raw.block"),
+            ("let", "keyword"),
+            ("add", "function.call"),
+            ("(", "punctuation.bracket"),
+            ("1", "number"),
+            (" plain(1);\n", "comment.documentation"),
+        ] {
+            assert!(
+                scopes.iter().any(|(t, s)| t == text && *s == scope),
+                "expected {text:?} as {scope}, got {scopes:?}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "lang-markdown", feature = "lang-rust"))]
+    fn test_markdown_fence_keeps_brackets() {
+        let scopes = value_scopes("```rust\nfn f() {}\n```\n", Language::Markdown);
+
+        assert!(scopes.contains(&("f".to_string(), "function")));
+        assert_eq!(
+            scopes
+                .iter()
+                .filter(|(_, scope)| *scope == "punctuation.bracket")
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "lang-eex",
+        feature = "lang-elixir",
+        feature = "lang-ejs",
+        feature = "lang-javascript",
+        feature = "lang-html"
+    ))]
+    fn test_combined_injections_in_document_order() {
+        for (source, lang) in [
+            ("<p><% if a %>{b}<% end %></p>", Language::EEx),
+            (
+                "<% if (user) { %><b><%= user.name %></b><% } %>",
+                Language::EJS,
+            ),
+        ] {
+            let mut highlighter = TSHighlighter::new();
+            let events = highlighter
+                .highlight(lang.config(), source.as_bytes(), None, |injected| {
+                    Some(Language::guess(Some(injected), "").config())
+                })
+                .unwrap();
+
+            let events: Vec<_> = events.map(Result::unwrap).collect();
+            let mut text = String::new();
+            for event in &events {
+                if let HighlightEvent::Source { start, end } = *event {
+                    assert!(start < end, "{lang:?}: empty source at {start}");
+                    text.push_str(&source[start..end]);
+                }
+            }
+
+            assert_eq!(text, source, "{lang:?}");
+            // Both documents start with a highlighted delimiter (`<` or `<%`).
+            assert!(
+                matches!(events[0], HighlightEvent::HighlightStart { .. }),
+                "{lang:?}: {:?}",
+                events[0]
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "lang-html",
+        feature = "lang-javascript",
+        feature = "lang-css",
+        feature = "lang-elixir",
+        feature = "lang-heex"
+    ))]
+    fn test_injections_keep_host_scopes() {
+        let html = value_scopes(
+            "<script>let x = [1];</script>\n<style>a { color: red; }</style>\n",
+            Language::HTML,
+        );
+        let js = value_scopes("const t = html`<b>${name}</b>`;\n", Language::JavaScript);
+        let heex = value_scopes(
+            "def f(assigns), do: ~H\"<p class={@c}>{@x}</p>\"\n",
+            Language::Elixir,
+        );
+
+        for (scopes, text, scope) in [
+            (&html, "script", "tag"),
+            (&html, "let", "keyword"),
+            (&html, "[", "punctuation.bracket"),
+            (&html, "1", "number"),
+            (&html, "style", "tag"),
+            (&html, "color", "property"),
+            (&html, "}", "punctuation.bracket"),
+            (&js, "html", "function.call"),
+            (&js, "b", "tag"),
+            (&js, "name", "variable"),
+            (&js, "}", "punctuation.special"),
+            (&js, "`", "string"),
+            (&heex, "p", "tag"),
+            (&heex, "class", "tag.attribute"),
+            (&heex, "{", "tag.delimiter"),
+            (&heex, "x", "constant"),
+        ] {
+            assert!(
+                scopes.contains(&(text.to_string(), scope)),
+                "{text} should be {scope}: {scopes:?}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "lang-r")]
+    fn test_notebook_kernel_language() {
+        let ipynb = r#"{
+          "metadata": {"language_info": {"name": "R"}},
+          "cells": [
+            {"cell_type": "code", "source": "x <- 1"},
+            {"cell_type": "raw", "source": "x <- 2"},
+            {"cell_type": "code", "source": ["y <- 2\n", "z <- 3"]}
+          ]
+        }"#;
+
+        let cells = notebook(ipynb, None).unwrap();
+        let indexes: Vec<_> = cells.iter().map(|cell| cell.index).collect();
+        assert_eq!(indexes, vec![0, 2]);
+        assert!(cells.iter().all(|cell| cell.language == Language::R));
+        assert!(cells[1].html.contains(r#"data-line="2""#));
+
+        let error = notebook("{\"cells\": 1}", None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
 }
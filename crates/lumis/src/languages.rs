@@ -11,9 +11,10 @@
 //! 2. **File path/name patterns** - `"app.ex"`, `"Dockerfile"`, `"Makefile"`, etc.
 //! 3. **File extension** - `".rs"`, `".js"`, `".py"`, etc.
 //! 4. **Emacs mode header** - `// -*- mode: rust -*-`
-//! 5. **Shebang** - `#!/usr/bin/env python`
-//! 6. **Content heuristics** - HTML doctype, XML declaration, etc.
-//! 7. **Fallback** - [`Language::PlainText`]
+//! 5. **Vim modeline** - `# vim: set ft=ruby:`
+//! 6. **Shebang** - `#!/usr/bin/env python`
+//! 7. **Content heuristics** - HTML doctype, XML declaration, etc.
+//! 8. **Fallback** - [`Language::PlainText`]
 //!
 //! # Examples
 //!
@@ -94,9 +95,12 @@ use crate::constants::HIGHLIGHT_NAMES;
 use crate::vendor::tree_sitter_highlight::HighlightConfiguration;
 use regex::Regex;
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::path::Path;
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
+use streaming_iterator::StreamingIterator;
 use strum::{EnumIter, IntoEnumIterator};
+use tree_sitter::{Parser, Query, QueryCursor};
 
 unsafe extern "C" {
     #[cfg(feature = "lang-angular")]
@@ -123,6 +127,8 @@ unsafe extern "C" {
     fn tree_sitter_glimmer() -> *const ();
     #[cfg(feature = "lang-graphql")]
     fn tree_sitter_graphql() -> *const ();
+    #[cfg(feature = "lang-http")]
+    fn tree_sitter_http() -> *const ();
     #[cfg(feature = "lang-iex")]
     fn tree_sitter_iex() -> *const ();
     #[cfg(feature = "lang-kotlin")]
@@ -194,6 +200,8 @@ pub enum Language {
     Diff,
     #[cfg(feature = "lang-dockerfile")]
     Dockerfile,
+    #[cfg(feature = "lang-clojure")]
+    EDN,
     #[cfg(feature = "lang-eex")]
     EEx,
     #[cfg(feature = "lang-ejs")]
@@ -222,6 +230,8 @@ pub enum Language {
     HEEx,
     #[cfg(feature = "lang-html")]
     HTML,
+    #[cfg(feature = "lang-http")]
+    HTTP,
     #[cfg(feature = "lang-haskell")]
     Haskell,
     #[cfg(feature = "lang-hcl")]
@@ -338,6 +348,15 @@ impl std::fmt::Display for LanguageParseError {
 
 impl std::error::Error for LanguageParseError {}
 
+/// Module system of a JavaScript or TypeScript file, see [`Language::module_kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ModuleKind {
+    /// ECMAScript modules, using `import` and `export`.
+    Esm,
+    /// CommonJS modules, using `require` and `module.exports`.
+    CommonJs,
+}
+
 impl std::str::FromStr for Language {
     type Err = LanguageParseError;
 
@@ -345,9 +364,15 @@ impl std::str::FromStr for Language {
     ///
     /// The input can be:
     /// - A language name (e.g., "rust", "python", "javascript")
+    /// - A VS Code / LSP language identifier (e.g., "typescriptreact", "shellscript")
     /// - A file extension (e.g., "rs", "py", "js")
     /// - A file path (e.g., "src/main.rs", "script.py")
     ///
+    /// Language names are tried first, then file globs and extensions, so `"c"` is
+    /// always C even if it was meant as a file named `c`. Use [`Language::from_path`]
+    /// when the input is known to be a path. Paths of templates ending in `.in`,
+    /// `.tmpl` or `.template` are detected by the file name without that suffix.
+    ///
     /// Returns an error if the language cannot be determined from the input.
     ///
     /// # Examples
@@ -372,6 +397,10 @@ impl std::str::FromStr for Language {
     /// let lang: Language = "src/main.rs".parse().unwrap();
     /// assert_eq!(lang, Language::Rust);
     ///
+    /// // From template path
+    /// let lang: Language = "setup.py.in".parse().unwrap();
+    /// assert_eq!(lang, Language::Python);
+    ///
     /// // Empty string defaults to PlainText
     /// let lang: Language = "".parse().unwrap();
     /// assert_eq!(lang, Language::PlainText);
@@ -387,7 +416,34 @@ impl std::str::FromStr for Language {
 
         let s_lower = s.to_ascii_lowercase();
 
-        let exact = match s_lower.as_str() {
+        if let Some(lang) = Self::from_name(&s_lower) {
+            return Ok(lang);
+        }
+
+        let path = Path::new(&s_lower);
+
+        if let Some(lang) = Self::from_glob(path) {
+            return Ok(lang);
+        }
+
+        if let Some(lang) = Self::strip_template_suffix(&s_lower)
+            .and_then(|inner| Self::from_path(Path::new(inner)))
+        {
+            return Ok(lang);
+        }
+
+        if let Some(lang) = Self::from_extension(&s_lower) {
+            return Ok(lang);
+        }
+
+        Err(LanguageParseError(s.to_string()))
+    }
+}
+
+impl Language {
+    // Match a lowercase language name or identifier, without globs or extensions.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
             #[cfg(feature = "lang-angular")]
             "angular" => Some(Language::Angular),
             #[cfg(feature = "lang-asm")]
@@ -395,13 +451,15 @@ impl std::str::FromStr for Language {
             #[cfg(feature = "lang-astro")]
             "astro" => Some(Language::Astro),
             #[cfg(feature = "lang-bash")]
-            "bash" => Some(Language::Bash),
+            "bash" | "shellscript" => Some(Language::Bash),
             #[cfg(feature = "lang-c")]
             "c" => Some(Language::C),
             #[cfg(feature = "lang-caddy")]
             "caddy" => Some(Language::Caddy),
             #[cfg(feature = "lang-clojure")]
             "clojure" => Some(Language::Clojure),
+            #[cfg(feature = "lang-clojure")]
+            "edn" => Some(Language::EDN),
             #[cfg(feature = "lang-comment")]
             "comment" => Some(Language::Comment),
             #[cfg(feature = "lang-commonlisp")]
@@ -419,6 +477,7 @@ impl std::str::FromStr for Language {
             #[cfg(feature = "lang-dart")]
             "dart" => Some(Language::Dart),
             "diff" => Some(Language::Diff),
+            "plaintext" => Some(Language::PlainText),
             #[cfg(feature = "lang-dockerfile")]
             "dockerfile" | "docker" => Some(Language::Dockerfile),
             #[cfg(feature = "lang-eex")]
@@ -453,14 +512,16 @@ impl std::str::FromStr for Language {
             "heex" => Some(Language::HEEx),
             #[cfg(feature = "lang-html")]
             "html" => Some(Language::HTML),
+            #[cfg(feature = "lang-http")]
+            "http" => Some(Language::HTTP),
             #[cfg(feature = "lang-iex")]
             "iex" => Some(Language::IEx),
             #[cfg(feature = "lang-java")]
             "java" => Some(Language::Java),
             #[cfg(feature = "lang-javascript")]
-            "jsx" | "javascript" => Some(Language::JavaScript),
+            "jsx" | "javascript" | "javascriptreact" => Some(Language::JavaScript),
             #[cfg(feature = "lang-json")]
-            "json" => Some(Language::JSON),
+            "json" | "jsonc" => Some(Language::JSON),
             #[cfg(feature = "lang-kotlin")]
             "kotlin" => Some(Language::Kotlin),
             #[cfg(feature = "lang-latex")]
@@ -510,7 +571,9 @@ impl std::str::FromStr for Language {
             #[cfg(feature = "lang-scss")]
             "scss" => Some(Language::SCSS),
             #[cfg(feature = "lang-sql")]
-            "sql" => Some(Language::SQL),
+            "sql" | "postgres" | "postgresql" | "plpgsql" | "mysql" | "mariadb" | "sqlite" => {
+                Some(Language::SQL)
+            }
             #[cfg(feature = "lang-surface")]
             "surface" => Some(Language::Surface),
             #[cfg(feature = "lang-svelte")]
@@ -522,7 +585,7 @@ impl std::str::FromStr for Language {
             #[cfg(feature = "lang-typescript")]
             "typescript" => Some(Language::TypeScript),
             #[cfg(feature = "lang-tsx")]
-            "tsx" => Some(Language::Tsx),
+            "tsx" | "typescriptreact" => Some(Language::Tsx),
             #[cfg(feature = "lang-typst")]
             "typst" => Some(Language::Typst),
             #[cfg(feature = "lang-vim")]
@@ -534,31 +597,13 @@ impl std::str::FromStr for Language {
             #[cfg(feature = "lang-xml")]
             "xml" => Some(Language::XML),
             #[cfg(feature = "lang-yaml")]
-            "yaml" => Some(Language::YAML),
+            "yaml" | "dockercompose" => Some(Language::YAML),
             #[cfg(feature = "lang-zig")]
             "zig" => Some(Language::Zig),
             _ => None,
-        };
-
-        if let Some(lang) = exact {
-            return Ok(lang);
-        }
-
-        let path = Path::new(&s_lower);
-
-        if let Some(lang) = Self::from_glob(path) {
-            return Ok(lang);
-        }
-
-        if let Some(lang) = Self::from_extension(&s_lower) {
-            return Ok(lang);
         }
-
-        Err(LanguageParseError(s.to_string()))
     }
-}
 
-impl Language {
     /// Guess the language based on an optional language hint and source content.
     ///
     /// # Arguments
@@ -570,16 +615,27 @@ impl Language {
     ///
     /// # Detection Strategy
     ///
+    /// Unless `language` is a language name, binary content (a NUL byte within the
+    /// first 8000 bytes of `src`) returns `PlainText`, so a hint like `video.ts` for an
+    /// MPEG transport stream doesn't claim TypeScript.
+    ///
     /// When `language` is `Some(...)`:
-    /// 1. Try to parse as language name/extension/path via `FromStr`
-    /// 2. If parsing succeeds, return that language
+    /// 1. Try to parse via `FromStr`, which matches language names before file
+    ///    globs and extensions (see [`Language::from_path`] for paths only)
+    /// 2. If parsing succeeds, return that language. When the hint is a file path or
+    ///    extension rather than a language name, a shell script with a fish shebang or
+    ///    fish `function ... end` blocks returns `Fish`, and a `.h` header returns
+    ///    `ObjC`, `CPlusPlus` or `C` depending on its content
     /// 3. If parsing fails, fall through to content-based detection
     ///
     /// When `language` is `None` or parsing fails:
     /// 1. Check for Emacs mode header (`// -*- mode: rust -*-`)
-    /// 2. Check for shebang (`#!/usr/bin/env python`)
-    /// 3. Apply content heuristics (HTML doctype, XML declaration, etc.)
-    /// 4. Default to `PlainText` if nothing matches
+    /// 2. Check for Vim modeline (`# vim: set ft=ruby:`) in the first or last 5 lines
+    /// 3. Check for shebang (`#!/usr/bin/env python`)
+    /// 4. Apply content heuristics (HTML doctype, XML declaration, etc.)
+    /// 5. As a last resort, detect scripts without a shebang as `Bash` when at least
+    ///    two lines use shell-only syntax such as `set -e`, `fi` or `export NAME=`
+    /// 6. Default to `PlainText` if nothing matches
     ///
     /// # Examples
     ///
@@ -599,9 +655,32 @@ impl Language {
     /// assert_eq!(lang, Language::Rust);
     /// ```
     pub fn guess(language: Option<&str>, src: &str) -> Self {
-        // If a language hint is provided, try to parse it
+        // An explicit language name is used as-is, even for binary-looking content
+        if let Some(lang) = language.and_then(|input| Self::from_name(&input.to_ascii_lowercase()))
+        {
+            return lang;
+        }
+
+        if Self::looks_binary(src) {
+            return Language::PlainText;
+        }
+
+        // If a file path or extension hint is provided, try to parse it
         if let Some(input) = language {
             if let Ok(lang) = input.parse() {
+                #[cfg(all(feature = "lang-bash", feature = "lang-fish"))]
+                if lang == Language::Bash && Self::looks_like_fish(src) {
+                    return Language::Fish;
+                }
+
+                #[cfg(any(feature = "lang-objc", feature = "lang-cpp"))]
+                if let Some(lang) = Self::resolve_header(
+                    Path::new(Self::strip_template_suffix(input).unwrap_or(input)),
+                    src,
+                ) {
+                    return lang;
+                }
+
                 return lang;
             }
             // If parsing fails, continue to content-based detection
@@ -612,6 +691,10 @@ impl Language {
             return lang;
         }
 
+        if let Some(lang) = Self::from_vim_modeline(src) {
+            return lang;
+        }
+
         if let Some(lang) = Self::from_shebang(src) {
             return lang;
         }
@@ -631,22 +714,171 @@ impl Language {
             return Language::ObjC;
         }
 
+        #[cfg(feature = "lang-bash")]
+        if Self::looks_like_shell(src) {
+            return Language::Bash;
+        }
+
         Language::PlainText
     }
 
+    /// Guess the language like [`Language::guess`], but reject unknown hints.
+    ///
+    /// A non-empty `language` hint that doesn't resolve to a grammar returns an error
+    /// instead of silently falling back to content detection or `PlainText`. Useful to
+    /// catch typos in code fence languages. Valid hints, and missing or empty ones, are
+    /// then passed to [`Language::guess`] along with the source, so binary content,
+    /// fish scripts hinted by a shell file name and `.h` headers are resolved the same way.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::languages::Language;
+    ///
+    /// assert_eq!(Language::try_guess(Some("rust"), ""), Ok(Language::Rust));
+    /// assert_eq!(Language::try_guess(None, "plain"), Ok(Language::PlainText));
+    /// assert!(Language::try_guess(Some("rusty"), "fn main() {}").is_err());
+    /// ```
+    pub fn try_guess(language: Option<&str>, src: &str) -> Result<Self, LanguageParseError> {
+        let language = language.map(str::trim).filter(|input| !input.is_empty());
+        if let Some(input) = language {
+            input.parse::<Language>()?;
+        }
+
+        Ok(Self::guess(language, src))
+    }
+
+    /// Detect the language of a file path using only glob and extension rules.
+    ///
+    /// Unlike [`FromStr`](std::str::FromStr) and [`Language::guess`], language names
+    /// are never matched, so a file named `c` or `rust` doesn't resolve to C or Rust.
+    /// The file name is matched as-is first and then lowercased, so both `Dockerfile`
+    /// and `MAIN.RS` are recognized. Templates ending in `.in`, `.tmpl` or `.template`,
+    /// like `config.h.in` from autotools, are detected by the file name without that
+    /// suffix when no glob matches the full name. Extension globs match the final
+    /// extension, so compound names like `Counter.svelte.ts` resolve to TypeScript.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::languages::Language;
+    /// use std::path::Path;
+    ///
+    /// assert_eq!(Language::from_path(Path::new("src/main.rs")), Some(Language::Rust));
+    /// assert_eq!(Language::from_path(Path::new("c")), None);
+    /// assert_eq!(Language::from_path(Path::new("index.html.tmpl")), Some(Language::HTML));
+    /// assert_eq!(Language::from_path(Path::new("Counter.svelte.ts")), Some(Language::TypeScript));
+    /// ```
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_glob(path)
+            .or_else(|| {
+                let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+                Self::from_glob(Path::new(&name))
+            })
+            .or_else(|| {
+                let name = path.file_name()?.to_str()?;
+                Self::from_path(Path::new(Self::strip_template_suffix(name)?))
+            })
+    }
+
+    // `config.h` for the `config.h.in` template, ignoring the case of the suffix.
+    fn strip_template_suffix(name: &str) -> Option<&str> {
+        [".in", ".tmpl", ".template"]
+            .into_iter()
+            .find_map(|suffix| {
+                let split = name.len().checked_sub(suffix.len())?;
+                if split > 0
+                    && name.is_char_boundary(split)
+                    && name[split..].eq_ignore_ascii_case(suffix)
+                {
+                    Some(&name[..split])
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Module system implied by the extension of a JavaScript or TypeScript file.
+    ///
+    /// `.mjs` and `.mts` files are always ES modules, `.cjs` and `.cts` files are
+    /// always CommonJS. Returns `None` for other files, including `.js` and `.ts`,
+    /// whose module system depends on the `type` field of the nearest `package.json`.
+    /// Highlighting is the same for both module systems.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::languages::{Language, ModuleKind};
+    /// use std::path::Path;
+    ///
+    /// assert_eq!(Language::module_kind(Path::new("index.mjs")), Some(ModuleKind::Esm));
+    /// assert_eq!(Language::module_kind(Path::new("index.cts")), Some(ModuleKind::CommonJs));
+    /// assert_eq!(Language::module_kind(Path::new("index.js")), None);
+    /// ```
+    pub fn module_kind(path: &Path) -> Option<ModuleKind> {
+        let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+
+        match extension.as_str() {
+            "mjs" | "mts" => Some(ModuleKind::Esm),
+            "cjs" | "cts" => Some(ModuleKind::CommonJs),
+            _ => None,
+        }
+    }
+
+    /// Whether the file has a visual rendering besides its source, to offer a preview.
+    ///
+    /// True for SVG images, Markdown and HTML documents. This is only a hint for tools
+    /// showing files: SVG is still highlighted as [`Language::XML`] source.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::languages::Language;
+    /// use std::path::Path;
+    ///
+    /// assert!(Language::is_previewable(Path::new("logo.svg")));
+    /// assert!(Language::is_previewable(Path::new("README.md")));
+    /// assert!(!Language::is_previewable(Path::new("pom.xml")));
+    /// ```
+    pub fn is_previewable(path: &Path) -> bool {
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"))
+        {
+            return true;
+        }
+
+        match Self::from_path(path) {
+            #[cfg(feature = "lang-html")]
+            Some(Language::HTML) => true,
+            #[cfg(feature = "lang-markdown")]
+            Some(Language::Markdown) => true,
+            _ => false,
+        }
+    }
+
     fn from_glob(path: &Path) -> Option<Self> {
         match path.file_name() {
             Some(name) => {
-                let name = name.to_string_lossy().into_owned();
-                for language in Language::iter() {
-                    for glob in Language::language_globs(language) {
-                        if glob.matches(&name) {
-                            return Some(language);
-                        }
+                let name = name.to_string_lossy();
+                let language = all_globs()
+                    .iter()
+                    .find(|(glob, _)| glob.matches(&name))
+                    .map(|(_, language)| *language)?;
+
+                // `Dockerfile.*` is meant for stage names like `Dockerfile.prod`, so a
+                // known extension like `Dockerfile.md` is detected by that extension
+                #[cfg(feature = "lang-dockerfile")]
+                if language == Language::Dockerfile {
+                    if let Some(lang) = path
+                        .extension()
+                        .and_then(|ext| Self::from_extension(&ext.to_string_lossy()))
+                    {
+                        return Some(lang);
                     }
                 }
 
-                None
+                Some(language)
             }
             None => None,
         }
@@ -655,14 +887,10 @@ impl Language {
     fn from_extension(token: &str) -> Option<Self> {
         let token_pattern = format!("*.{token}");
 
-        for language in Language::iter() {
-            for glob in Language::language_globs(language) {
-                if glob.matches(&token_pattern) {
-                    return Some(language);
-                }
-            }
-        }
-        None
+        all_globs()
+            .iter()
+            .find(|(glob, _)| glob.matches(&token_pattern))
+            .map(|(_, language)| *language)
     }
 
     // TODO: https://github.com/nvim-treesitter/nvim-treesitter/tree/master/queries/embedded_template
@@ -731,9 +959,11 @@ impl Language {
             Language::Caddy => &["Caddyfile", "caddyfile"],
             #[cfg(feature = "lang-clojure")]
             Language::Clojure => &[
-                "*.bb", "*.boot", "*.clj", "*.cljc", "*.clje", "*.cljs", "*.cljx", "*.edn",
-                "*.joke", "*.joker",
+                "*.bb", "*.boot", "*.clj", "*.cljc", "*.clje", "*.cljs", "*.cljx", "*.joke",
+                "*.joker",
             ],
+            #[cfg(feature = "lang-clojure")]
+            Language::EDN => &["*.edn"],
             #[cfg(feature = "lang-comment")]
             Language::Comment => &[],
             #[cfg(feature = "lang-commonlisp")]
@@ -757,8 +987,13 @@ impl Language {
             Language::Dockerfile => &[
                 "Dockerfile",
                 "dockerfile",
+                "Dockerfile.*",
+                "dockerfile.*",
                 "docker",
                 "Containerfile",
+                "containerfile",
+                "Containerfile.*",
+                "containerfile.*",
                 "container",
                 "*.dockerfile",
                 "*.docker",
@@ -798,7 +1033,7 @@ impl Language {
             #[cfg(feature = "lang-go")]
             Language::Go => &["*.go"],
             #[cfg(feature = "lang-graphql")]
-            Language::GraphQL => &[],
+            Language::GraphQL => &["*.graphql", "*.gql"],
             #[cfg(feature = "lang-haskell")]
             Language::Haskell => &["*.hs", "*.hs-boot"],
             #[cfg(feature = "lang-hcl")]
@@ -807,6 +1042,8 @@ impl Language {
             Language::HEEx => &["*.heex", "*.neex"],
             #[cfg(feature = "lang-html")]
             Language::HTML => &["*.html", "*.htm", "*.xhtml"],
+            #[cfg(feature = "lang-http")]
+            Language::HTTP => &["*.http", "*.rest"],
             #[cfg(feature = "lang-iex")]
             Language::IEx => &["*.iex"],
             #[cfg(feature = "lang-java")]
@@ -878,7 +1115,7 @@ impl Language {
                 "mkfile",
             ],
             #[cfg(feature = "lang-markdown")]
-            Language::Markdown => &["*.md", ".MD", "README", "LICENSE"],
+            Language::Markdown => &["*.md", "*.mdx", ".MD", "README", "LICENSE"],
             #[cfg(feature = "lang-markdown-inline")]
             Language::MarkdownInline => &[],
             #[cfg(feature = "lang-nix")]
@@ -924,7 +1161,7 @@ impl Language {
             #[cfg(feature = "lang-scss")]
             Language::SCSS => &["*.scss"],
             #[cfg(feature = "lang-sql")]
-            Language::SQL => &["*.sql", "*.pgsql"],
+            Language::SQL => &["*.sql", "*.pgsql", "*.psql"],
             #[cfg(feature = "lang-surface")]
             Language::Surface => &["*.surface", "*.sface"],
             #[cfg(feature = "lang-svelte")]
@@ -942,7 +1179,7 @@ impl Language {
                 "uv.lock",
             ],
             #[cfg(feature = "lang-typescript")]
-            Language::TypeScript => &["*.ts"],
+            Language::TypeScript => &["*.ts", "*.mts", "*.cts"],
             #[cfg(feature = "lang-tsx")]
             Language::Tsx => &["*.tsx"],
             #[cfg(feature = "lang-typst")]
@@ -1093,6 +1330,27 @@ impl Language {
         None
     }
 
+    /// Try to guess the language based on a Vim modeline setting the filetype,
+    /// like `# vim: set ft=ruby:` or `/* vi: filetype=c */`.
+    ///
+    /// As in Vim, only the first and last 5 lines are checked. The filetype is
+    /// resolved like a language hint, by name or extension.
+    ///
+    /// <https://vimhelp.org/options.txt.html#modeline>
+    fn from_vim_modeline(src: &str) -> Option<Language> {
+        const MODELINES: usize = 5;
+
+        static RE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"(?:^|\s)(?:vi|vim|ex):.*?\b(?:ft|filetype)=([\w+.-]+)").unwrap()
+        });
+
+        // Lines in both the head and the tail of short sources are just checked twice
+        src.lines()
+            .take(MODELINES)
+            .chain(src.rsplit('\n').take(MODELINES))
+            .find_map(|line| RE.captures(line).and_then(|cap| cap[1].parse().ok()))
+    }
+
     fn from_shebang(src: &str) -> Option<Language> {
         static RE: LazyLock<Regex> =
             LazyLock::new(|| Regex::new(r"#! *(?:/usr/bin/env )?([^ ]+)").unwrap());
@@ -1127,6 +1385,8 @@ impl Language {
                         "ruby" | "macruby" | "rake" | "jruby" | "rbx" => {
                             return Some(Language::Ruby);
                         }
+                        #[cfg(feature = "lang-fish")]
+                        "fish" => return Some(Language::Fish),
                         #[cfg(feature = "lang-nushell")]
                         "nu" => return Some(Language::Nushell),
                         #[cfg(feature = "lang-swift")]
@@ -1142,32 +1402,167 @@ impl Language {
         None
     }
 
-    /// Use a heuristic to determine if a '.h' file looks like Objective-C.
+    /// Fish scripts are often saved with a `.sh` extension. Trust the shebang when
+    /// there is one, otherwise look for a `function` block closed by a bare `end`,
+    /// which POSIX shells don't have.
+    #[cfg(feature = "lang-fish")]
+    fn looks_like_fish(src: &str) -> bool {
+        if src.starts_with("#!") {
+            return Self::from_shebang(src) == Some(Language::Fish);
+        }
+
+        let mut in_function = false;
+        for line in split_on_newlines(src).take(200) {
+            let line = line.trim();
+            if line.starts_with("function ") && !line.contains(['(', '{']) {
+                in_function = true;
+            } else if in_function && line == "end" {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Shell scripts without an extension or shebang, like a `deploy` script. Count the
+    /// lines near the top using syntax that other languages don't share, such as
+    /// `set -e`, a bare `fi`, `done` or `esac`, a `then` or `do` ending an `if` or
+    /// loop, `[ ... ]` tests or `export NAME=`, and require two of them. Files whose
+    /// first line is a Dockerfile-style instruction like `FROM` or `RUN` are skipped,
+    /// since their `RUN` commands are shell too.
+    #[cfg(feature = "lang-bash")]
+    fn looks_like_shell(src: &str) -> bool {
+        let is_name = |name: &str| {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        };
+
+        let mut lines = split_on_newlines(src)
+            .take(200)
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .peekable();
+
+        let instruction = lines
+            .peek()
+            .and_then(|line| line.split_once(' '))
+            .is_some_and(|(word, _)| {
+                word.len() > 1 && word.chars().all(|c| c.is_ascii_uppercase())
+            });
+        if instruction {
+            return false;
+        }
+
+        let is_shell = |line: &str| {
+            matches!(line, "fi" | "done" | "esac" | "then" | "do")
+                || line.ends_with("; then")
+                || line.ends_with("; do")
+                || [
+                    "if [ ",
+                    "if [[ ",
+                    "while [ ",
+                    "while [[ ",
+                    "elif [ ",
+                    "elif [[ ",
+                ]
+                .iter()
+                .any(|prefix| line.starts_with(prefix))
+                || line
+                    .strip_prefix("set -")
+                    .is_some_and(|flags| flags.starts_with(|c: char| c.is_ascii_alphabetic()))
+                || ["export ", "alias "].iter().any(|keyword| {
+                    line.strip_prefix(keyword)
+                        .and_then(|rest| rest.split_once('='))
+                        .is_some_and(|(name, _)| is_name(name))
+                })
+        };
+
+        lines.filter(|line| is_shell(line)).take(2).count() == 2
+    }
+
+    /// Use a heuristic to determine if a '.h' file looks like Objective-C, see
+    /// [`looks_like_objc_header`](Self::looks_like_objc_header).
+    #[cfg(feature = "lang-objc")]
+    fn looks_like_objc(path: &Path, src: &str) -> bool {
+        path.extension().is_some_and(|extension| extension == "h")
+            && Self::looks_like_objc_header(src)
+    }
+
+    /// Use a heuristic to determine if a header looks like Objective-C.
     /// We look for a line starting with '#import', '@interface' or '@protocol'
     /// near the top of the file.  These keywords are not valid C or C++, so this
     /// should not produce false positives.
-    fn looks_like_objc(path: &Path, src: &str) -> bool {
-        if let Some(extension) = path.extension() {
-            if extension == "h" {
-                return split_on_newlines(src).take(100).any(|line| {
-                    ["#import", "@interface", "@protocol"]
-                        .iter()
-                        .any(|keyword| line.starts_with(keyword))
-                });
-            }
+    #[cfg(feature = "lang-objc")]
+    fn looks_like_objc_header(src: &str) -> bool {
+        split_on_newlines(src).take(100).any(|line| {
+            ["#import", "@interface", "@protocol"]
+                .iter()
+                .any(|keyword| line.starts_with(keyword))
+        })
+    }
+
+    /// Resolve the language of a '.h' header, which can be C, C++ or Objective-C.
+    /// Headers using C++-only syntax near the top of the file are C++, the rest are C.
+    #[cfg(any(feature = "lang-objc", feature = "lang-cpp"))]
+    fn resolve_header(path: &Path, src: &str) -> Option<Language> {
+        if !path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("h"))
+        {
+            return None;
         }
 
-        false
+        #[cfg(feature = "lang-objc")]
+        if Self::looks_like_objc_header(src) {
+            return Some(Language::ObjC);
+        }
+
+        #[cfg(feature = "lang-cpp")]
+        if Self::looks_like_cpp(src) {
+            return Some(Language::CPlusPlus);
+        }
+
+        #[cfg(feature = "lang-c")]
+        return Some(Language::C);
+
+        #[cfg(not(feature = "lang-c"))]
+        None
     }
 
+    /// Use a heuristic to determine if a header looks like C++.
+    /// We look for `class`, `template` or `namespace` declarations, or a `::` scope
+    /// operator, outside of comments near the top of the file.
+    #[cfg(feature = "lang-cpp")]
+    fn looks_like_cpp(src: &str) -> bool {
+        split_on_newlines(src).take(200).any(|line| {
+            let line = line.trim_start();
+            if line.starts_with("//") || line.starts_with("/*") || line.starts_with('*') {
+                return false;
+            }
+
+            ["class ", "template<", "template <", "namespace "]
+                .iter()
+                .any(|keyword| line.starts_with(keyword))
+                || line.contains("::")
+        })
+    }
+
+    #[cfg(feature = "lang-xml")]
     fn looks_like_xml(src: &str) -> bool {
         src.to_lowercase().starts_with("<?xml")
     }
 
+    #[cfg(feature = "lang-html")]
     fn looks_like_html(src: &str) -> bool {
         src.to_lowercase().starts_with("<!doctype html")
     }
 
+    // Same heuristic as git: text files don't contain NUL bytes near the start.
+    fn looks_binary(src: &str) -> bool {
+        src.as_bytes().iter().take(8000).any(|&b| b == 0)
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             #[cfg(feature = "lang-angular")]
@@ -1184,6 +1579,8 @@ impl Language {
             Language::Caddy => "Caddy",
             #[cfg(feature = "lang-clojure")]
             Language::Clojure => "Clojure",
+            #[cfg(feature = "lang-clojure")]
+            Language::EDN => "EDN",
             #[cfg(feature = "lang-comment")]
             Language::Comment => "Comment",
             #[cfg(feature = "lang-commonlisp")]
@@ -1235,6 +1632,8 @@ impl Language {
             Language::HEEx => "HEEx",
             #[cfg(feature = "lang-html")]
             Language::HTML => "HTML",
+            #[cfg(feature = "lang-http")]
+            Language::HTTP => "HTTP",
             #[cfg(feature = "lang-iex")]
             Language::IEx => "IEx",
             #[cfg(feature = "lang-java")]
@@ -1327,6 +1726,197 @@ impl Language {
         self.name().to_ascii_lowercase().replace(" ", "")
     }
 
+    /// Returns `true` for grammars that only highlight regions injected by other languages.
+    ///
+    /// [`Language::Comment`] highlights the content of comments, such as `TODO:` notes,
+    /// and [`Language::MarkdownInline`] the inline markup of [`Language::Markdown`]
+    /// paragraphs. They have no file globs and aren't meant to highlight a whole file,
+    /// so language pickers and listings usually leave them out.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::languages::Language;
+    /// use strum::IntoEnumIterator;
+    ///
+    /// assert!(Language::MarkdownInline.is_injection_only());
+    /// assert!(!Language::Markdown.is_injection_only());
+    ///
+    /// let selectable: Vec<Language> = Language::iter()
+    ///     .filter(|language| !language.is_injection_only())
+    ///     .collect();
+    /// assert!(!selectable.contains(&Language::Comment));
+    /// ```
+    pub fn is_injection_only(&self) -> bool {
+        match self {
+            #[cfg(feature = "lang-comment")]
+            Language::Comment => true,
+            #[cfg(feature = "lang-markdown-inline")]
+            Language::MarkdownInline => true,
+            _ => false,
+        }
+    }
+
+    // The `lang-*` feature that compiles this language in.
+    fn feature(&self) -> Option<&'static str> {
+        match self {
+            #[cfg(feature = "lang-angular")]
+            Language::Angular => Some("lang-angular"),
+            #[cfg(feature = "lang-asm")]
+            Language::Assembly => Some("lang-asm"),
+            #[cfg(feature = "lang-astro")]
+            Language::Astro => Some("lang-astro"),
+            #[cfg(feature = "lang-bash")]
+            Language::Bash => Some("lang-bash"),
+            #[cfg(feature = "lang-c")]
+            Language::C => Some("lang-c"),
+            #[cfg(feature = "lang-caddy")]
+            Language::Caddy => Some("lang-caddy"),
+            #[cfg(feature = "lang-cmake")]
+            Language::CMake => Some("lang-cmake"),
+            #[cfg(feature = "lang-cpp")]
+            Language::CPlusPlus => Some("lang-cpp"),
+            #[cfg(feature = "lang-css")]
+            Language::CSS => Some("lang-css"),
+            #[cfg(feature = "lang-csv")]
+            Language::CSV => Some("lang-csv"),
+            #[cfg(feature = "lang-csharp")]
+            Language::CSharp => Some("lang-csharp"),
+            #[cfg(feature = "lang-clojure")]
+            Language::Clojure => Some("lang-clojure"),
+            #[cfg(feature = "lang-clojure")]
+            Language::EDN => Some("lang-clojure"),
+            #[cfg(feature = "lang-comment")]
+            Language::Comment => Some("lang-comment"),
+            #[cfg(feature = "lang-commonlisp")]
+            Language::CommonLisp => Some("lang-commonlisp"),
+            #[cfg(feature = "lang-dart")]
+            Language::Dart => Some("lang-dart"),
+            #[cfg(feature = "lang-dockerfile")]
+            Language::Dockerfile => Some("lang-dockerfile"),
+            #[cfg(feature = "lang-eex")]
+            Language::EEx => Some("lang-eex"),
+            #[cfg(feature = "lang-ejs")]
+            Language::EJS => Some("lang-ejs"),
+            #[cfg(feature = "lang-erb")]
+            Language::ERB => Some("lang-erb"),
+            #[cfg(feature = "lang-elixir")]
+            Language::Elixir => Some("lang-elixir"),
+            #[cfg(feature = "lang-elm")]
+            Language::Elm => Some("lang-elm"),
+            #[cfg(feature = "lang-erlang")]
+            Language::Erlang => Some("lang-erlang"),
+            #[cfg(feature = "lang-fish")]
+            Language::Fish => Some("lang-fish"),
+            #[cfg(feature = "lang-fsharp")]
+            Language::FSharp => Some("lang-fsharp"),
+            #[cfg(feature = "lang-gleam")]
+            Language::Gleam => Some("lang-gleam"),
+            #[cfg(feature = "lang-glimmer")]
+            Language::Glimmer => Some("lang-glimmer"),
+            #[cfg(feature = "lang-go")]
+            Language::Go => Some("lang-go"),
+            #[cfg(feature = "lang-graphql")]
+            Language::GraphQL => Some("lang-graphql"),
+            #[cfg(feature = "lang-heex")]
+            Language::HEEx => Some("lang-heex"),
+            #[cfg(feature = "lang-html")]
+            Language::HTML => Some("lang-html"),
+            #[cfg(feature = "lang-http")]
+            Language::HTTP => Some("lang-http"),
+            #[cfg(feature = "lang-haskell")]
+            Language::Haskell => Some("lang-haskell"),
+            #[cfg(feature = "lang-hcl")]
+            Language::HCL => Some("lang-hcl"),
+            #[cfg(feature = "lang-iex")]
+            Language::IEx => Some("lang-iex"),
+            #[cfg(feature = "lang-json")]
+            Language::JSON => Some("lang-json"),
+            #[cfg(feature = "lang-java")]
+            Language::Java => Some("lang-java"),
+            #[cfg(feature = "lang-javascript")]
+            Language::JavaScript => Some("lang-javascript"),
+            #[cfg(feature = "lang-kotlin")]
+            Language::Kotlin => Some("lang-kotlin"),
+            #[cfg(feature = "lang-latex")]
+            Language::LaTeX => Some("lang-latex"),
+            #[cfg(feature = "lang-liquid")]
+            Language::Liquid => Some("lang-liquid"),
+            #[cfg(feature = "lang-llvm")]
+            Language::Llvm => Some("lang-llvm"),
+            #[cfg(feature = "lang-lua")]
+            Language::Lua => Some("lang-lua"),
+            #[cfg(feature = "lang-make")]
+            Language::Make => Some("lang-make"),
+            #[cfg(feature = "lang-markdown")]
+            Language::Markdown => Some("lang-markdown"),
+            #[cfg(feature = "lang-markdown-inline")]
+            Language::MarkdownInline => Some("lang-markdown-inline"),
+            #[cfg(feature = "lang-nix")]
+            Language::Nix => Some("lang-nix"),
+            #[cfg(feature = "lang-nushell")]
+            Language::Nushell => Some("lang-nushell"),
+            #[cfg(feature = "lang-ocaml")]
+            Language::OCaml => Some("lang-ocaml"),
+            #[cfg(feature = "lang-ocaml")]
+            Language::OCamlInterface => Some("lang-ocaml"),
+            #[cfg(feature = "lang-objc")]
+            Language::ObjC => Some("lang-objc"),
+            #[cfg(feature = "lang-perl")]
+            Language::Perl => Some("lang-perl"),
+            #[cfg(feature = "lang-php")]
+            Language::Php => Some("lang-php"),
+            #[cfg(feature = "lang-powershell")]
+            Language::PowerShell => Some("lang-powershell"),
+            #[cfg(feature = "lang-protobuf")]
+            Language::ProtoBuf => Some("lang-protobuf"),
+            #[cfg(feature = "lang-python")]
+            Language::Python => Some("lang-python"),
+            #[cfg(feature = "lang-r")]
+            Language::R => Some("lang-r"),
+            #[cfg(feature = "lang-regex")]
+            Language::Regex => Some("lang-regex"),
+            #[cfg(feature = "lang-ruby")]
+            Language::Ruby => Some("lang-ruby"),
+            #[cfg(feature = "lang-rust")]
+            Language::Rust => Some("lang-rust"),
+            #[cfg(feature = "lang-scss")]
+            Language::SCSS => Some("lang-scss"),
+            #[cfg(feature = "lang-sql")]
+            Language::SQL => Some("lang-sql"),
+            #[cfg(feature = "lang-scala")]
+            Language::Scala => Some("lang-scala"),
+            #[cfg(feature = "lang-surface")]
+            Language::Surface => Some("lang-surface"),
+            #[cfg(feature = "lang-svelte")]
+            Language::Svelte => Some("lang-svelte"),
+            #[cfg(feature = "lang-swift")]
+            Language::Swift => Some("lang-swift"),
+            #[cfg(feature = "lang-toml")]
+            Language::Toml => Some("lang-toml"),
+            #[cfg(feature = "lang-tsx")]
+            Language::Tsx => Some("lang-tsx"),
+            #[cfg(feature = "lang-typescript")]
+            Language::TypeScript => Some("lang-typescript"),
+            #[cfg(feature = "lang-typst")]
+            Language::Typst => Some("lang-typst"),
+            #[cfg(feature = "lang-vim")]
+            Language::Vim => Some("lang-vim"),
+            #[cfg(feature = "lang-vue")]
+            Language::Vue => Some("lang-vue"),
+            #[cfg(feature = "lang-wat")]
+            Language::Wat => Some("lang-wat"),
+            #[cfg(feature = "lang-xml")]
+            Language::XML => Some("lang-xml"),
+            #[cfg(feature = "lang-yaml")]
+            Language::YAML => Some("lang-yaml"),
+            #[cfg(feature = "lang-zig")]
+            Language::Zig => Some("lang-zig"),
+            Language::Diff => cfg!(feature = "lang-diff").then_some("lang-diff"),
+            Language::PlainText => None,
+        }
+    }
+
     pub fn config(&self) -> &'static HighlightConfiguration {
         match self {
             #[cfg(feature = "lang-angular")]
@@ -1343,6 +1933,8 @@ impl Language {
             Language::Caddy => &CADDY_CONFIG,
             #[cfg(feature = "lang-clojure")]
             Language::Clojure => &CLOJURE_CONFIG,
+            #[cfg(feature = "lang-clojure")]
+            Language::EDN => &EDN_CONFIG,
             #[cfg(feature = "lang-comment")]
             Language::Comment => &COMMENT_CONFIG,
             #[cfg(feature = "lang-commonlisp")]
@@ -1394,6 +1986,8 @@ impl Language {
             Language::HEEx => &HEEX_CONFIG,
             #[cfg(feature = "lang-html")]
             Language::HTML => &HTML_CONFIG,
+            #[cfg(feature = "lang-http")]
+            Language::HTTP => &HTTP_CONFIG,
             #[cfg(feature = "lang-iex")]
             Language::IEx => &IEX_CONFIG,
             #[cfg(feature = "lang-java")]
@@ -1419,66 +2013,218 @@ impl Language {
             #[cfg(feature = "lang-ocaml")]
             Language::OCamlInterface => &OCAML_INTERFACE_CONFIG,
             #[cfg(feature = "lang-make")]
-            Language::Make => &MAKE_CONFIG,
+            Language::Make => &MAKE_CONFIG,
+            #[cfg(feature = "lang-markdown")]
+            Language::Markdown => &MARKDOWN_CONFIG,
+            #[cfg(feature = "lang-markdown-inline")]
+            Language::MarkdownInline => &MARKDOWN_INLINE_CONFIG,
+            #[cfg(feature = "lang-nix")]
+            Language::Nix => &NIX_CONFIG,
+            #[cfg(feature = "lang-nushell")]
+            Language::Nushell => &NUSHELL_CONFIG,
+            #[cfg(feature = "lang-perl")]
+            Language::Perl => &PERL_CONFIG,
+            #[cfg(feature = "lang-php")]
+            Language::Php => &PHP_CONFIG,
+            #[cfg(feature = "lang-powershell")]
+            Language::PowerShell => &POWERSHELL_CONFIG,
+            #[cfg(feature = "lang-protobuf")]
+            Language::ProtoBuf => &PROTO_BUF_CONFIG,
+            #[cfg(feature = "lang-python")]
+            Language::Python => &PYTHON_CONFIG,
+            #[cfg(feature = "lang-r")]
+            Language::R => &R_CONFIG,
+            #[cfg(feature = "lang-regex")]
+            Language::Regex => &REGEX_CONFIG,
+            #[cfg(feature = "lang-ruby")]
+            Language::Ruby => &RUBY_CONFIG,
+            #[cfg(feature = "lang-rust")]
+            Language::Rust => &RUST_CONFIG,
+            #[cfg(feature = "lang-scala")]
+            Language::Scala => &SCALA_CONFIG,
+            #[cfg(feature = "lang-scss")]
+            Language::SCSS => &SCSS_CONFIG,
+            #[cfg(feature = "lang-sql")]
+            Language::SQL => &SQL_CONFIG,
+            #[cfg(feature = "lang-surface")]
+            Language::Surface => &SURFACE_CONFIG,
+            #[cfg(feature = "lang-svelte")]
+            Language::Svelte => &SVELTE_CONFIG,
+            #[cfg(feature = "lang-swift")]
+            Language::Swift => &SWIFT_CONFIG,
+            #[cfg(feature = "lang-toml")]
+            Language::Toml => &TOML_CONFIG,
+            #[cfg(feature = "lang-typescript")]
+            Language::TypeScript => &TYPESCRIPT_CONFIG,
+            #[cfg(feature = "lang-tsx")]
+            Language::Tsx => &TSX_CONFIG,
+            #[cfg(feature = "lang-typst")]
+            Language::Typst => &TYPST_CONFIG,
+            #[cfg(feature = "lang-vim")]
+            Language::Vim => &VIM_CONFIG,
+            #[cfg(feature = "lang-vue")]
+            Language::Vue => &VUE_CONFIG,
+            #[cfg(feature = "lang-wat")]
+            Language::Wat => &WAT_CONFIG,
+            #[cfg(feature = "lang-xml")]
+            Language::XML => &XML_CONFIG,
+            #[cfg(feature = "lang-yaml")]
+            Language::YAML => &YAML_CONFIG,
+            #[cfg(feature = "lang-zig")]
+            Language::Zig => &ZIG_CONFIG,
+            _ => &PLAIN_TEXT_CONFIG,
+        }
+    }
+
+    /// Compute the foldable line ranges of `source` using the language's fold query.
+    ///
+    /// Ranges are 1-based and inclusive, cover at least two lines, and are sorted by
+    /// start line. They are properly nested: at most one range starts on a given line
+    /// and ranges never partially overlap, so they can be rendered as nested regions.
+    ///
+    /// Returns an empty vector for languages that don't ship a `folds.scm` query.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::languages::Language;
+    ///
+    /// let source = "fun main() {\n    println(1)\n}\n";
+    /// assert_eq!(Language::Kotlin.fold_ranges(source), vec![1..=3]);
+    ///
+    /// assert!(Language::PlainText.fold_ranges(source).is_empty());
+    /// ```
+    pub fn fold_ranges(&self, source: &str) -> Vec<RangeInclusive<usize>> {
+        let Some(query) = self.fold_query() else {
+            return Vec::new();
+        };
+
+        let mut parser = Parser::new();
+        if parser.set_language(&self.config().language).is_err() {
+            return Vec::new();
+        }
+
+        let Some(tree) = parser.parse(source, None) else {
+            return Vec::new();
+        };
+
+        let mut ranges = Vec::new();
+        let mut cursor = QueryCursor::new();
+        let mut captures = cursor.captures(query.as_ref(), tree.root_node(), source.as_bytes());
+
+        while let Some((query_match, index)) = captures.next() {
+            let node = query_match.captures[*index].node;
+            let start = node.start_position().row + 1;
+            let mut end = node.end_position().row + 1;
+
+            // A node ending at column 0 finishes on the previous line.
+            if node.end_position().column == 0 {
+                end -= 1;
+            }
+
+            if end > start {
+                ranges.push(start..=end);
+            }
+        }
+
+        ranges.sort_by(|a, b| a.start().cmp(b.start()).then(b.end().cmp(a.end())));
+
+        let mut nested: Vec<RangeInclusive<usize>> = Vec::new();
+        let mut open: Vec<usize> = Vec::new();
+
+        for range in ranges {
+            if nested
+                .last()
+                .is_some_and(|last| last.start() == range.start())
+            {
+                continue;
+            }
+
+            while open.last().is_some_and(|end| *end < *range.start()) {
+                open.pop();
+            }
+
+            if open.last().is_some_and(|end| end < range.end()) {
+                continue;
+            }
+
+            open.push(*range.end());
+            nested.push(range);
+        }
+
+        nested
+    }
+
+    /// The compiled fold query, built once per language and shared by later calls.
+    ///
+    /// The lock only guards the map of per-language cells, so compiling the query of
+    /// one language doesn't block callers folding another.
+    fn fold_query(&self) -> Option<Arc<Query>> {
+        type Cell = Arc<OnceLock<Option<Arc<Query>>>>;
+        static QUERIES: LazyLock<Mutex<HashMap<Language, Cell>>> = LazyLock::new(Default::default);
+
+        let cell = QUERIES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(*self)
+            .or_default()
+            .clone();
+
+        cell.get_or_init(|| {
+            let folds = self.folds_query();
+            if folds.is_empty() {
+                return None;
+            }
+
+            Query::new(&self.config().language, folds)
+                .ok()
+                .filter(|query| query.pattern_count() > 0)
+                .map(Arc::new)
+        })
+        .clone()
+    }
+
+    fn folds_query(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "lang-angular")]
+            Language::Angular => ANGULAR_FOLDS,
+            #[cfg(feature = "lang-astro")]
+            Language::Astro => ASTRO_FOLDS,
+            #[cfg(feature = "lang-caddy")]
+            Language::Caddy => CADDY_FOLDS,
+            #[cfg(feature = "lang-clojure")]
+            Language::Clojure | Language::EDN => CLOJURE_FOLDS,
+            #[cfg(feature = "lang-commonlisp")]
+            Language::CommonLisp => COMMONLISP_FOLDS,
+            #[cfg(feature = "lang-dart")]
+            Language::Dart => DART_FOLDS,
+            #[cfg(feature = "lang-fish")]
+            Language::Fish => FISH_FOLDS,
+            #[cfg(feature = "lang-glimmer")]
+            Language::Glimmer => GLIMMER_FOLDS,
+            #[cfg(feature = "lang-http")]
+            Language::HTTP => HTTP_FOLDS,
+            #[cfg(feature = "lang-kotlin")]
+            Language::Kotlin => KOTLIN_FOLDS,
+            #[cfg(feature = "lang-latex")]
+            Language::LaTeX => LATEX_FOLDS,
+            #[cfg(feature = "lang-make")]
+            Language::Make => MAKE_FOLDS,
             #[cfg(feature = "lang-markdown")]
-            Language::Markdown => &MARKDOWN_CONFIG,
-            #[cfg(feature = "lang-markdown-inline")]
-            Language::MarkdownInline => &MARKDOWN_INLINE_CONFIG,
-            #[cfg(feature = "lang-nix")]
-            Language::Nix => &NIX_CONFIG,
-            #[cfg(feature = "lang-nushell")]
-            Language::Nushell => &NUSHELL_CONFIG,
+            Language::Markdown => MARKDOWN_FOLDS,
             #[cfg(feature = "lang-perl")]
-            Language::Perl => &PERL_CONFIG,
-            #[cfg(feature = "lang-php")]
-            Language::Php => &PHP_CONFIG,
+            Language::Perl => PERL_FOLDS,
             #[cfg(feature = "lang-powershell")]
-            Language::PowerShell => &POWERSHELL_CONFIG,
-            #[cfg(feature = "lang-protobuf")]
-            Language::ProtoBuf => &PROTO_BUF_CONFIG,
-            #[cfg(feature = "lang-python")]
-            Language::Python => &PYTHON_CONFIG,
-            #[cfg(feature = "lang-r")]
-            Language::R => &R_CONFIG,
-            #[cfg(feature = "lang-regex")]
-            Language::Regex => &REGEX_CONFIG,
-            #[cfg(feature = "lang-ruby")]
-            Language::Ruby => &RUBY_CONFIG,
-            #[cfg(feature = "lang-rust")]
-            Language::Rust => &RUST_CONFIG,
-            #[cfg(feature = "lang-scala")]
-            Language::Scala => &SCALA_CONFIG,
+            Language::PowerShell => POWERSHELL_FOLDS,
             #[cfg(feature = "lang-scss")]
-            Language::SCSS => &SCSS_CONFIG,
-            #[cfg(feature = "lang-sql")]
-            Language::SQL => &SQL_CONFIG,
+            Language::SCSS => SCSS_FOLDS,
             #[cfg(feature = "lang-surface")]
-            Language::Surface => &SURFACE_CONFIG,
-            #[cfg(feature = "lang-svelte")]
-            Language::Svelte => &SVELTE_CONFIG,
-            #[cfg(feature = "lang-swift")]
-            Language::Swift => &SWIFT_CONFIG,
-            #[cfg(feature = "lang-toml")]
-            Language::Toml => &TOML_CONFIG,
-            #[cfg(feature = "lang-typescript")]
-            Language::TypeScript => &TYPESCRIPT_CONFIG,
-            #[cfg(feature = "lang-tsx")]
-            Language::Tsx => &TSX_CONFIG,
-            #[cfg(feature = "lang-typst")]
-            Language::Typst => &TYPST_CONFIG,
+            Language::Surface => SURFACE_FOLDS,
             #[cfg(feature = "lang-vim")]
-            Language::Vim => &VIM_CONFIG,
+            Language::Vim => VIM_FOLDS,
             #[cfg(feature = "lang-vue")]
-            Language::Vue => &VUE_CONFIG,
-            #[cfg(feature = "lang-wat")]
-            Language::Wat => &WAT_CONFIG,
-            #[cfg(feature = "lang-xml")]
-            Language::XML => &XML_CONFIG,
-            #[cfg(feature = "lang-yaml")]
-            Language::YAML => &YAML_CONFIG,
-            #[cfg(feature = "lang-zig")]
-            Language::Zig => &ZIG_CONFIG,
-            _ => &PLAIN_TEXT_CONFIG,
+            Language::Vue => VUE_FOLDS,
+            _ => "",
         }
     }
 }
@@ -1490,6 +2236,9 @@ impl Language {
 /// - The friendly name (e.g. "Elixir", "Common Lisp")
 /// - A Vec of file extensions/patterns
 ///
+/// Injection-only grammars like `comment` and `markdowninline` are included without
+/// file patterns, use [`Language::is_injection_only`] to filter them out.
+///
 /// # Examples
 ///
 /// ## Basic usage - listing all languages
@@ -1581,6 +2330,63 @@ pub fn available_languages() -> HashMap<String, (String, Vec<String>)> {
     languages
 }
 
+/// Returns the `lang-*` features this crate was compiled with, sorted by name.
+///
+/// Each feature compiles in a language grammar, so this is the build-time counterpart
+/// of [`Language::iter`](strum::IntoEnumIterator::iter). Useful in diagnostics to
+/// explain why a language falls back to plain text in a build with a reduced set of
+/// features.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::languages::enabled_features;
+///
+/// let features = enabled_features();
+/// assert!(features.contains(&"lang-rust"));
+/// assert!(features.is_sorted());
+/// ```
+pub fn enabled_features() -> Vec<&'static str> {
+    let mut features: Vec<&'static str> = Language::iter()
+        .filter_map(|language| language.feature())
+        .collect();
+    features.sort_unstable();
+    features.dedup();
+    features
+}
+
+/// Returns the file globs of all available languages, paired with their language.
+///
+/// Globs are listed in the order they're matched when guessing a language from a file
+/// name, so the first glob matching a name determines its language. The globs are
+/// compiled once, on first use.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::languages::{all_globs, Language};
+///
+/// let language = all_globs()
+///     .iter()
+///     .find(|(glob, _)| glob.matches("main.rs"))
+///     .map(|(_, language)| *language);
+///
+/// assert_eq!(language, Some(Language::Rust));
+/// ```
+pub fn all_globs() -> &'static [(glob::Pattern, Language)] {
+    static GLOBS: LazyLock<Vec<(glob::Pattern, Language)>> = LazyLock::new(|| {
+        Language::iter()
+            .flat_map(|language| {
+                Language::language_globs(language)
+                    .into_iter()
+                    .map(move |glob| (glob, language))
+            })
+            .collect()
+    });
+
+    &GLOBS
+}
+
 fn split_on_newlines(s: &str) -> impl Iterator<Item = &str> {
     s.split('\n').map(|l| {
         if let Some(l) = l.strip_suffix('\r') {
@@ -1697,6 +2503,22 @@ static CLOJURE_CONFIG: LazyLock<HighlightConfiguration> = LazyLock::new(|| {
     config
 });
 
+#[cfg(feature = "lang-clojure")]
+static EDN_CONFIG: LazyLock<HighlightConfiguration> = LazyLock::new(|| {
+    let language_fn = unsafe { tree_sitter_language::LanguageFn::from_raw(tree_sitter_clojure) };
+
+    let mut config = HighlightConfiguration::new(
+        tree_sitter::Language::new(language_fn),
+        "edn",
+        EDN_HIGHLIGHTS,
+        EDN_INJECTIONS,
+        EDN_LOCALS,
+    )
+    .expect("failed to create edn highlight configuration");
+    config.configure(&HIGHLIGHT_NAMES);
+    config
+});
+
 #[cfg(feature = "lang-comment")]
 static COMMENT_CONFIG: LazyLock<HighlightConfiguration> = LazyLock::new(|| {
     let mut config = HighlightConfiguration::new(
@@ -1882,7 +2704,7 @@ static EJS_CONFIG: LazyLock<HighlightConfiguration> = LazyLock::new(|| {
         tree_sitter::Language::new(tree_sitter_embedded_template::LANGUAGE),
         "ejs",
         EMBEDDED_TEMPLATE_HIGHLIGHTS,
-        EMBEDDED_TEMPLATE_INJECTIONS,
+        EJS_INJECTIONS,
         EMBEDDED_TEMPLATE_LOCALS,
     )
     .expect("failed to create ejs highlight configuration");
@@ -2076,6 +2898,22 @@ static HTML_CONFIG: LazyLock<HighlightConfiguration> = LazyLock::new(|| {
     config
 });
 
+#[cfg(feature = "lang-http")]
+static HTTP_CONFIG: LazyLock<HighlightConfiguration> = LazyLock::new(|| {
+    let language_fn = unsafe { tree_sitter_language::LanguageFn::from_raw(tree_sitter_http) };
+
+    let mut config = HighlightConfiguration::new(
+        tree_sitter::Language::new(language_fn),
+        "http",
+        HTTP_HIGHLIGHTS,
+        HTTP_INJECTIONS,
+        HTTP_LOCALS,
+    )
+    .expect("failed to create http highlight configuration");
+    config.configure(&HIGHLIGHT_NAMES);
+    config
+});
+
 #[cfg(feature = "lang-iex")]
 static IEX_CONFIG: LazyLock<HighlightConfiguration> = LazyLock::new(|| {
     let language_fn = unsafe { tree_sitter_language::LanguageFn::from_raw(tree_sitter_iex) };
@@ -2711,7 +3549,7 @@ static ZIG_CONFIG: LazyLock<HighlightConfiguration> = LazyLock::new(|| {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::vendor::tree_sitter_highlight::Highlighter;
+    use crate::vendor::tree_sitter_highlight::{HighlightEvent, Highlighter};
 
     #[test]
     fn test_match_exact_name() {
@@ -2740,12 +3578,536 @@ mod tests {
         assert_eq!(lang.name(), "Elixir");
     }
 
+    #[test]
+    #[cfg(feature = "lang-markdown")]
+    fn test_match_mdx() {
+        assert_eq!(
+            Language::guess(Some("docs/intro.mdx"), ""),
+            Language::Markdown
+        );
+        assert_eq!(Language::guess(Some("mdx"), ""), Language::Markdown);
+    }
+
+    #[test]
+    fn test_match_vscode_language_ids() {
+        let cases = [
+            ("plaintext", Language::PlainText),
+            #[cfg(feature = "lang-bash")]
+            ("shellscript", Language::Bash),
+            #[cfg(feature = "lang-csharp")]
+            ("csharp", Language::CSharp),
+            #[cfg(feature = "lang-fsharp")]
+            ("fsharp", Language::FSharp),
+            #[cfg(feature = "lang-javascript")]
+            ("javascriptreact", Language::JavaScript),
+            #[cfg(feature = "lang-json")]
+            ("jsonc", Language::JSON),
+            #[cfg(feature = "lang-latex")]
+            ("tex", Language::LaTeX),
+            #[cfg(feature = "lang-make")]
+            ("makefile", Language::Make),
+            #[cfg(feature = "lang-objc")]
+            ("objective-c", Language::ObjC),
+            #[cfg(feature = "lang-tsx")]
+            ("typescriptreact", Language::Tsx),
+            #[cfg(feature = "lang-yaml")]
+            ("dockercompose", Language::YAML),
+        ];
+
+        for (id, expected) in cases {
+            assert_eq!(id.parse::<Language>().unwrap(), expected, "{id}");
+            assert_eq!(Language::guess(Some(id), ""), expected, "{id}");
+        }
+    }
+
+    #[test]
+    fn test_from_path_ignores_language_names() {
+        assert_eq!(Language::from_path(Path::new("c")), None);
+        assert_eq!(Language::from_path(Path::new("elixir")), None);
+        assert_eq!(
+            Language::from_path(Path::new("lib/app.ex")),
+            Some(Language::Elixir)
+        );
+        assert_eq!(
+            Language::from_path(Path::new("LIB/APP.EX")),
+            Some(Language::Elixir)
+        );
+
+        #[cfg(feature = "lang-dockerfile")]
+        assert_eq!(
+            Language::from_path(Path::new("docker/Dockerfile")),
+            Some(Language::Dockerfile)
+        );
+    }
+
+    // Language of the innermost highlight for each non-blank token.
+    fn token_languages(lang: Language, source: &str) -> Vec<(String, String)> {
+        let mut highlighter = Highlighter::new();
+        let events = highlighter
+            .highlight(lang.config(), source.as_bytes(), None, |injected| {
+                Some(Language::guess(Some(injected), "").config())
+            })
+            .unwrap();
+
+        let mut stack = Vec::new();
+        let mut tokens = Vec::new();
+        for event in events {
+            match event.unwrap() {
+                HighlightEvent::HighlightStart { language, .. } => stack.push(language),
+                HighlightEvent::HighlightEnd => {
+                    stack.pop();
+                }
+                HighlightEvent::Source { start, end } => {
+                    if let Some(language) = stack.last() {
+                        tokens.push((source[start..end].to_string(), language.clone()));
+                    }
+                }
+            }
+        }
+        tokens
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "lang-astro",
+        feature = "lang-svelte",
+        feature = "lang-vue",
+        feature = "lang-typescript"
+    ))]
+    fn test_component_scripts_inject_typescript() {
+        let cases = [
+            (
+                Language::Astro,
+                "---\ninterface Props { title: string }\nconst { title } = Astro.props as Props;\n---\n<h1>{title}</h1>\n",
+            ),
+            (
+                Language::Svelte,
+                "<script lang=\"ts\">\n  let count: number = 0;\n</script>\n<p>{count}</p>\n",
+            ),
+            (
+                Language::Vue,
+                "<script setup lang=\"ts\">\nconst count: number = 0\n</script>\n<template><p>{{ count }}</p></template>\n",
+            ),
+        ];
+
+        for (lang, source) in cases {
+            let tokens = token_languages(lang, source);
+            let language_of = |text: &str| {
+                tokens
+                    .iter()
+                    .find(|(token, _)| token == text)
+                    .map(|(_, language)| language.as_str())
+            };
+
+            let annotation = if lang == Language::Astro {
+                "string"
+            } else {
+                "number"
+            };
+            assert_eq!(language_of(annotation), Some("typescript"), "{lang:?}");
+            assert_eq!(language_of("<"), Some(lang.id_name().as_str()), "{lang:?}");
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "lang-bash", feature = "lang-fish"))]
+    fn test_guess_fish() {
+        assert_eq!(
+            Language::guess(None, "#!/usr/bin/env fish\necho hi"),
+            Language::Fish
+        );
+        assert_eq!(
+            Language::guess(Some("setup.sh"), "#!/usr/bin/fish\necho hi"),
+            Language::Fish
+        );
+        assert_eq!(
+            Language::guess(Some("setup.sh"), "function greet\n    echo hi\nend\n"),
+            Language::Fish
+        );
+        assert_eq!(
+            Language::guess(Some("setup.sh"), "#!/bin/bash\nfunction greet\nend\n"),
+            Language::Bash
+        );
+        assert_eq!(
+            Language::guess(Some("bash"), "#!/usr/bin/env fish\necho hi"),
+            Language::Bash
+        );
+        assert_eq!(
+            Language::guess(Some("bash"), "function greet\n    echo hi\nend\n"),
+            Language::Bash
+        );
+        assert_eq!(
+            Language::guess(Some("setup.sh"), "function greet {\n  echo hi\n}\n"),
+            Language::Bash
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "all-languages")]
+    fn test_enabled_features() {
+        let manifest = include_str!("../Cargo.toml");
+        let start = manifest.find("all-languages = [").unwrap();
+        let end = start + manifest[start..].find(']').unwrap();
+        let mut expected: Vec<&str> = manifest[start..end].split('"').skip(1).step_by(2).collect();
+        expected.sort_unstable();
+
+        assert_eq!(enabled_features(), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "lang-bash")]
+    fn test_guess_shell_without_shebang() {
+        let deploy = "# Deploy the site\nset -euo pipefail\n\nexport TARGET=/srv/www\nif [ -d build ]; then\n  rsync -a build/ \"$TARGET\"\nfi\n";
+        assert_eq!(Language::guess(None, deploy), Language::Bash);
+        assert_eq!(Language::guess(Some("deploy"), deploy), Language::Bash);
+        assert_eq!(
+            Language::guess(None, "for f in *.log\ndo\n  gzip \"$f\"\ndone\n"),
+            Language::Bash
+        );
+
+        // A single shell-like line isn't enough
+        assert_eq!(
+            Language::guess(None, "export FOO=bar\n"),
+            Language::PlainText
+        );
+        assert_eq!(
+            Language::guess(None, "Then we are done.\nfi\n"),
+            Language::PlainText
+        );
+        assert_eq!(
+            Language::guess(
+                None,
+                "FROM alpine\nRUN set -e\nRUN if [ -f x ]; then rm x; fi\n"
+            ),
+            Language::PlainText
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "lang-ruby", feature = "lang-perl", feature = "lang-c"))]
+    fn test_guess_vim_modeline() {
+        assert_eq!(
+            Language::guess(None, "# vim: set ft=ruby:\nputs 1\n"),
+            Language::Ruby
+        );
+        assert_eq!(
+            Language::guess(None, "print 1;\n\n# vim: set ts=4 sw=4 filetype=perl :\n"),
+            Language::Perl
+        );
+        assert_eq!(Language::guess(None, "/* vi: ft=c */\nint x;"), Language::C);
+        assert_eq!(
+            Language::guess(None, "1\n2\n3\n4\n5\n# vim: ft=ruby\n7\n8\n9\n10\n11"),
+            Language::PlainText
+        );
+        assert_eq!(
+            Language::guess(None, "# vim: ft=unknown\n"),
+            Language::PlainText
+        );
+
+        let long = format!("{}# vim: ft=ruby\r\n", "x\n".repeat(1000));
+        assert_eq!(Language::guess(None, &long), Language::Ruby);
+    }
+
+    #[test]
+    #[cfg(all(feature = "lang-c", feature = "lang-cpp", feature = "lang-objc"))]
+    fn test_guess_header() {
+        assert_eq!(
+            Language::guess(Some("include/point.h"), "struct point { int x; };\n"),
+            Language::C
+        );
+        assert_eq!(
+            Language::guess(Some("include/point.h"), "// a class of points\nint x;\n"),
+            Language::C
+        );
+        assert_eq!(
+            Language::guess(
+                Some("include/point.h"),
+                "namespace geo {\nclass Point {};\n}\n"
+            ),
+            Language::CPlusPlus
+        );
+        assert_eq!(
+            Language::guess(Some("POINT.H"), "template<typename T>\nT max(T a, T b);\n"),
+            Language::CPlusPlus
+        );
+        assert_eq!(
+            Language::guess(
+                Some("include/point.h"),
+                "#import <Foundation/Foundation.h>\n"
+            ),
+            Language::ObjC
+        );
+        assert_eq!(
+            Language::guess(Some("point.hpp"), "int x;\n"),
+            Language::CPlusPlus
+        );
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "lang-python",
+        feature = "lang-c",
+        feature = "lang-cpp",
+        feature = "lang-make",
+        feature = "lang-html",
+        feature = "lang-json",
+        feature = "lang-bash"
+    ))]
+    fn test_template_suffix() {
+        for (path, language) in [
+            ("foo.py.in", Language::Python),
+            ("config.h.in", Language::CPlusPlus),
+            ("Makefile.in", Language::Make),
+            ("templates/page.html.tmpl", Language::HTML),
+            ("settings.json.TEMPLATE", Language::JSON),
+            ("build.sh.in", Language::Bash),
+        ] {
+            assert_eq!(
+                Language::from_path(Path::new(path)),
+                Some(language),
+                "{path}"
+            );
+            assert_eq!(path.parse::<Language>(), Ok(language), "{path}");
+        }
+
+        assert_eq!(
+            Language::guess(Some("config.h.in"), "#define VERSION \"@VERSION@\"\n"),
+            Language::C
+        );
+        assert_eq!(
+            Language::guess(
+                Some("config.h.in"),
+                "class Config {\npublic:\n  int x;\n};\n"
+            ),
+            Language::CPlusPlus
+        );
+        assert_eq!(Language::from_path(Path::new("c.in")), None);
+        assert!("c.in".parse::<Language>().is_err());
+        assert_eq!(Language::from_path(Path::new(".in")), None);
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "lang-typescript",
+        feature = "lang-javascript",
+        feature = "lang-svelte",
+        feature = "lang-vue",
+        feature = "lang-astro"
+    ))]
+    fn test_compound_extensions() {
+        for (path, language) in [
+            ("src/lib/Counter.svelte.ts", Language::TypeScript),
+            ("src/lib/store.svelte.js", Language::JavaScript),
+            ("composables/useCounter.vue.ts", Language::TypeScript),
+            ("Counter.SVELTE.TS", Language::TypeScript),
+            ("svelte.config.js", Language::JavaScript),
+            ("astro.config.mjs", Language::JavaScript),
+            ("vite.config.mts", Language::TypeScript),
+            ("src/App.vue", Language::Vue),
+            ("src/routes/+page.svelte", Language::Svelte),
+            ("src/pages/index.astro", Language::Astro),
+        ] {
+            assert_eq!(
+                Language::from_path(Path::new(path)),
+                Some(language),
+                "{path}"
+            );
+            assert_eq!(path.parse::<Language>(), Ok(language), "{path}");
+            assert_eq!(Language::guess(Some(path), ""), language, "{path}");
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "lang-xml", feature = "lang-html", feature = "lang-markdown"))]
+    fn test_is_previewable() {
+        for path in [
+            "icon.svg",
+            "ICON.SVG",
+            "index.html",
+            "page.htm",
+            "README.md",
+            "docs/guide.md",
+        ] {
+            assert!(Language::is_previewable(Path::new(path)), "{path:?}");
+        }
+        for path in ["pom.xml", "main.rs", "Makefile", "svg"] {
+            assert!(!Language::is_previewable(Path::new(path)), "{path:?}");
+        }
+        assert_eq!(
+            Language::from_path(Path::new("icon.svg")),
+            Some(Language::XML)
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "lang-javascript", feature = "lang-typescript"))]
+    fn test_module_kind() {
+        for (path, language, kind) in [
+            ("a.mjs", Language::JavaScript, Some(ModuleKind::Esm)),
+            ("a.cjs", Language::JavaScript, Some(ModuleKind::CommonJs)),
+            ("a.MTS", Language::TypeScript, Some(ModuleKind::Esm)),
+            ("a.cts", Language::TypeScript, Some(ModuleKind::CommonJs)),
+            ("a.js", Language::JavaScript, None),
+            ("a.jsx", Language::JavaScript, None),
+        ] {
+            let path = Path::new(path);
+            assert_eq!(Language::from_path(path), Some(language), "{path:?}");
+            assert_eq!(Language::module_kind(path), kind, "{path:?}");
+        }
+        assert_eq!(Language::module_kind(Path::new("Makefile")), None);
+    }
+
+    #[test]
+    #[cfg(feature = "lang-javascript")]
+    fn test_jsx_and_esm_scopes() {
+        let scopes = |path: &str, source: &str| {
+            let language = Language::from_path(Path::new(path)).unwrap();
+            let mut scopes = Vec::new();
+            crate::highlight::highlight_iter(source, language, None, |text, _, scope, _| {
+                scopes.push((text.to_string(), scope));
+                Ok::<_, std::io::Error>(())
+            })
+            .unwrap();
+            scopes
+        };
+
+        let jsx = scopes("a.jsx", "const a = <Foo bar=\"x\">hi</Foo>;");
+        assert!(jsx.contains(&("Foo".to_string(), "tag")));
+        assert!(jsx.contains(&("bar".to_string(), "tag.attribute")));
+
+        let esm = scopes("a.mjs", "import x from \"y\";\nexport default x;");
+        assert!(esm.contains(&("import".to_string(), "keyword.import")));
+        assert!(esm.contains(&("from".to_string(), "keyword.import")));
+        assert!(esm.contains(&("export".to_string(), "keyword.import")));
+    }
+
+    #[test]
+    #[cfg(feature = "lang-sql")]
+    fn test_sql_dialects() {
+        for hint in ["postgresql", "mysql", "sqlite", "schema.psql"] {
+            assert_eq!(Language::guess(Some(hint), ""), Language::SQL, "{hint}");
+        }
+
+        // The grammar covers the common dialects, e.g. PostgreSQL's `ILIKE` and `RETURNING`
+        let mut keywords = Vec::new();
+        crate::highlight::highlight_iter(
+            "DELETE FROM t WHERE a ILIKE 'x' RETURNING id;",
+            Language::SQL,
+            None,
+            |text, _range, scope, _style| {
+                if scope == "keyword" {
+                    keywords.push(text.to_string());
+                }
+                Ok::<_, std::io::Error>(())
+            },
+        )
+        .unwrap();
+        assert!(keywords.contains(&"ILIKE".to_string()));
+        assert!(keywords.contains(&"RETURNING".to_string()));
+    }
+
+    #[test]
+    fn test_all_globs() {
+        let globs = all_globs();
+
+        for language in Language::iter() {
+            let count = globs.iter().filter(|(_, lang)| *lang == language).count();
+            assert_eq!(count, Language::language_globs(language).len());
+        }
+
+        assert!(globs
+            .iter()
+            .any(|(glob, lang)| glob.as_str() == "*.rs" && *lang == Language::Rust));
+
+        // Compiled once and shared by every lookup
+        assert!(std::ptr::eq(globs, all_globs()));
+    }
+
     #[test]
     fn test_no_match_fallbacks_to_plain_text() {
         let lang = Language::guess(Some("none"), "");
         assert_eq!(lang.name(), "Plain Text");
     }
 
+    #[test]
+    #[cfg(feature = "lang-typescript")]
+    fn test_guess_binary_content() {
+        let mpeg = "G\u{0}\u{11}\u{10}\u{0}B\u{f0}%";
+        assert_eq!(Language::guess(Some("video.ts"), mpeg), Language::PlainText);
+        assert_eq!(
+            Language::guess(Some("typescript"), mpeg),
+            Language::TypeScript
+        );
+        assert_eq!(Language::guess(None, mpeg), Language::PlainText);
+
+        let nul_after_prefix = format!("{}\u{0}", "a".repeat(8000));
+        assert_eq!(
+            Language::guess(Some("video.ts"), &nul_after_prefix),
+            Language::TypeScript
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "lang-fish")]
+    fn test_fold_ranges_are_nested() {
+        let source = "function greet\n    if true\n        echo hi\n    end\nend\necho done\n";
+        assert_eq!(Language::Fish.fold_ranges(source), vec![1..=5, 2..=4]);
+    }
+
+    #[test]
+    #[cfg(feature = "lang-http")]
+    fn test_fold_ranges_http() {
+        let source = "GET https://example.com\nAccept: application/json\n\n###\n\nPOST https://example.com\nContent-Type: application/json\n\n{\n  \"a\": 1\n}\n";
+        assert_eq!(
+            Language::HTTP.fold_ranges(source),
+            vec![1..=3, 6..=11, 9..=11]
+        );
+    }
+
+    #[test]
+    fn test_fold_ranges_without_fold_query() {
+        assert!(Language::PlainText.fold_ranges("a\nb\n").is_empty());
+    }
+
+    #[test]
+    fn test_try_guess_rejects_unknown_hint() {
+        assert!(Language::try_guess(Some("none"), "").is_err());
+        assert!(Language::try_guess(Some("rsut"), "#!/usr/bin/env python3").is_err());
+        assert_eq!(Language::try_guess(Some("rs"), ""), Ok(Language::Rust));
+        assert_eq!(
+            Language::try_guess(Some(" "), "#!/usr/bin/env python3"),
+            Ok(Language::Python)
+        );
+        assert_eq!(
+            Language::try_guess(None, "#!/usr/bin/env python3"),
+            Ok(Language::Python)
+        );
+    }
+
+    #[test]
+    fn test_try_guess_matches_guess() {
+        assert_eq!(
+            Language::try_guess(Some("rust"), "\0\0\0"),
+            Ok(Language::Rust)
+        );
+        assert_eq!(
+            Language::try_guess(Some("main.rs"), "\0\0\0"),
+            Ok(Language::PlainText)
+        );
+
+        #[cfg(all(feature = "lang-bash", feature = "lang-fish"))]
+        assert_eq!(
+            Language::try_guess(Some("setup.sh"), "function greet\n    echo hi\nend\n"),
+            Ok(Language::Fish)
+        );
+
+        #[cfg(feature = "lang-cpp")]
+        assert_eq!(
+            Language::try_guess(Some("main.h"), "class Foo {};\n"),
+            Ok(Language::CPlusPlus)
+        );
+    }
+
     #[test]
     #[cfg(feature = "lang-angular")]
     fn test_angular_config_loads() {
@@ -2890,6 +4252,48 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    #[cfg(feature = "lang-clojure")]
+    fn test_edn_config_loads() {
+        let lang = Language::EDN;
+        let config = lang.config();
+        assert_eq!(lang.name(), "EDN");
+
+        let mut highlighter = Highlighter::new();
+        let _ = highlighter
+            .highlight(config, "".as_bytes(), None, |_| None)
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "lang-clojure")]
+    fn test_edn_data_scopes() {
+        let source = "{:deps {org.clojure/clojure {:mvn/version \"1.11.1\"}}\n :list (foo 1 nil)\n :at #inst \"2020-01-01\"}";
+        assert_eq!(Language::guess(Some("deps.edn"), source), Language::EDN);
+        assert_eq!(Language::guess(Some("core.clj"), source), Language::Clojure);
+
+        let mut scopes = Vec::new();
+        crate::highlight::highlight_iter(source, Language::EDN, None, |text, _, scope, _| {
+            scopes.push((text.to_string(), scope));
+            Ok::<_, std::io::Error>(())
+        })
+        .unwrap();
+
+        for (text, scope) in [
+            (":mvn/version", "string.special.symbol"),
+            ("org.clojure/clojure", "variable"),
+            ("foo", "variable"),
+            ("1", "number"),
+            ("nil", "constant.builtin"),
+            ("inst", "type"),
+        ] {
+            assert!(
+                scopes.contains(&(text.to_string(), scope)),
+                "{text} should be {scope}: {scopes:?}"
+            );
+        }
+    }
+
     #[test]
     #[cfg(feature = "lang-comment")]
     fn test_comment_config_loads() {
@@ -2941,6 +4345,42 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    #[cfg(feature = "lang-dockerfile")]
+    fn test_guess_dockerfile_variants() {
+        for name in [
+            "Dockerfile.prod",
+            "dockerfile.dev",
+            "api.Dockerfile",
+            "build/Dockerfile.test",
+            "Containerfile",
+            "Containerfile.prod",
+        ] {
+            assert_eq!(
+                Language::guess(Some(name), ""),
+                Language::Dockerfile,
+                "{name}"
+            );
+            assert_eq!(
+                Language::from_path(Path::new(name)),
+                Some(Language::Dockerfile),
+                "{name}"
+            );
+        }
+
+        #[cfg(all(feature = "lang-markdown", feature = "lang-rust"))]
+        {
+            assert_eq!(
+                Language::from_path(Path::new("Dockerfile.md")),
+                Some(Language::Markdown)
+            );
+            assert_eq!(
+                Language::guess(Some("docs/Containerfile.rs"), ""),
+                Language::Rust
+            );
+        }
+    }
+
     #[test]
     #[cfg(feature = "lang-eex")]
     fn test_eex_config_loads() {
@@ -3097,6 +4537,43 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn test_is_injection_only() {
+        let injection_only: Vec<Language> = Language::iter()
+            .filter(Language::is_injection_only)
+            .collect();
+
+        for language in &injection_only {
+            assert!(
+                Language::language_globs(*language).is_empty(),
+                "{language:?}"
+            );
+        }
+
+        #[cfg(all(feature = "lang-comment", feature = "lang-markdown-inline"))]
+        assert_eq!(
+            injection_only,
+            vec![Language::Comment, Language::MarkdownInline]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "lang-graphql")]
+    fn test_guess_graphql_by_extension() {
+        assert_eq!(
+            Language::guess(Some("schema.graphql"), ""),
+            Language::GraphQL
+        );
+        assert_eq!(
+            Language::guess(Some("queries/user.gql"), ""),
+            Language::GraphQL
+        );
+        assert_eq!(
+            Language::from_path(Path::new("SCHEMA.GQL")),
+            Some(Language::GraphQL)
+        );
+    }
+
     #[test]
     #[cfg(feature = "lang-heex")]
     fn test_heex_config_loads() {
@@ -3123,6 +4600,19 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    #[cfg(feature = "lang-http")]
+    fn test_http_config_loads() {
+        let lang = Language::HTTP;
+        let config = lang.config();
+        assert_eq!(lang.name(), "HTTP");
+
+        let mut highlighter = Highlighter::new();
+        let _ = highlighter
+            .highlight(config, "".as_bytes(), None, |_| None)
+            .unwrap();
+    }
+
     #[test]
     #[cfg(feature = "lang-haskell")]
     fn test_haskell_config_loads() {
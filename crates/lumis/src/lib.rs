@@ -73,6 +73,16 @@
 //! lumis = { version = "0.1", features = ["all-languages"] }
 //! ```
 //!
+//! Every language feature enables the `highlight` feature, which holds Tree-sitter and
+//! the [`highlight`], [`languages`] and [`formatter`] modules. Without any of them, only
+//! the [`themes`] and [`constants`] modules are built, which is enough to read theme
+//! colors without compiling any grammar:
+//!
+//! ```toml
+//! [dependencies]
+//! lumis = { version = "0.1", default-features = false }
+//! ```
+//!
 //! ## Formatters
 //!
 //! | Formatter | Output | Use When |
@@ -80,6 +90,8 @@
 //! | [`HtmlInlineBuilder`] | HTML with inline styles | Need standalone HTML, email, no external CSS |
 //! | [`HtmlMultiThemesBuilder`] | HTML (inline) with multiple themes | Support light/dark mode, theme switching |
 //! | [`HtmlLinkedBuilder`] | HTML with CSS classes | Multiple code blocks, custom styling |
+//! | [`HtmlVarsBuilder`] | HTML colored by CSS variables | Design tokens, existing color palette |
+//! | [`HtmlAtomicBuilder`] | HTML with generated atomic classes, plus their CSS | CSS-in-JS, large documents |
 //! | [`TerminalBuilder`] | ANSI escape codes | CLI tools, terminal output |
 //!
 //! See the [`formatter`] module for advanced features like line highlighting and custom formatters.
@@ -103,6 +115,26 @@
 //!
 //! ## Languages available
 //!
+//! Language names from user input, such as a CLI flag or a config file, can be parsed
+//! before building the formatter, so unknown names are reported early instead of being
+//! highlighted as plain text. Formatters keep the parsed [`Language`](languages::Language)
+//! and don't detect it again on every call to `format`:
+//!
+//! ```rust
+//! use lumis::{HtmlLinkedBuilder, formatter::HtmlLinked, languages::{Language, LanguageParseError}};
+//!
+//! fn formatter_for(name: &str) -> Result<HtmlLinked, LanguageParseError> {
+//!     let lang: Language = name.parse()?;
+//!     Ok(HtmlLinkedBuilder::new().lang(lang).build().unwrap())
+//! }
+//!
+//! assert!(formatter_for("rust").is_ok());
+//! assert_eq!(
+//!     formatter_for("rustt").unwrap_err().to_string(),
+//!     "unknown language or file type: rustt"
+//! );
+//! ```
+//!
 //! | Language | File Extensions |
 //! |----------|-----------------|
 //! | Angular | *.angular, component.html |
@@ -116,12 +148,13 @@
 //! | CSS | *.css |
 //! | CSV | *.csv |
 //! | C# | *.cs |
-//! | Clojure | *.bb, *.boot, *.clj, *.cljc, *.clje, *.cljs, *.cljx, *.edn, *.joke, *.joker |
+//! | Clojure | *.bb, *.boot, *.clj, *.cljc, *.clje, *.cljs, *.cljx, *.joke, *.joker |
 //! | Comment | |
 //! | Common Lisp | *.lisp, *.lsp, *.asd |
 //! | Dart | *.dart |
 //! | Diff | *.diff |
 //! | Dockerfile | Dockerfile, dockerfile, docker, Containerfile, container, *.dockerfile, *.docker, *.container |
+//! | EDN | *.edn |
 //! | EEx | *.eex |
 //! | EJS | *.ejs |
 //! | ERB | *.erb |
@@ -136,6 +169,7 @@
 //! | GraphQL | |
 //! | HEEx | *.heex, *.neex |
 //! | HTML | *.html, *.htm, *.xhtml |
+//! | HTTP | *.http, *.rest |
 //! | Haskell | *.hs, *.hs-boot |
 //! | HCL | *.hcl, *.nomad, *.tf, *.tfvars, *.workflow |
 //! | IEx | *.iex |
@@ -305,22 +339,32 @@
 //! | zephyr_dark |
 
 pub mod constants;
+#[cfg(feature = "highlight")]
 pub mod formatter;
+#[cfg(feature = "highlight")]
 pub mod highlight;
+#[cfg(feature = "highlight")]
 pub mod languages;
 pub mod themes;
+#[cfg(feature = "highlight")]
 pub mod vendor;
 
 // Re-export helper modules from formatter for convenience
+#[cfg(feature = "highlight")]
 pub use formatter::ansi;
+#[cfg(feature = "highlight")]
 pub use formatter::html;
 
+#[cfg(feature = "highlight")]
 use crate::formatter::Formatter;
-use std::io::{self, Write};
+#[cfg(feature = "highlight")]
+use std::io::{self, Read, Write};
 
 // Re-export builders for easier access
+#[cfg(feature = "highlight")]
 pub use crate::formatter::{
-    HtmlInlineBuilder, HtmlLinkedBuilder, HtmlMultiThemesBuilder, TerminalBuilder,
+    HtmlAtomicBuilder, HtmlInlineBuilder, HtmlLinkedBuilder, HtmlMultiThemesBuilder,
+    HtmlVarsBuilder, SvgBuilder, TerminalBuilder,
 };
 
 /// Highlights source code and returns it as a string.
@@ -352,6 +396,7 @@ pub use crate::formatter::{
 ///
 /// let html = highlight(code, formatter);
 /// ```
+#[cfg(feature = "highlight")]
 pub fn highlight<F: Formatter>(source: &str, formatter: F) -> String {
     let mut buffer = Vec::new();
     formatter
@@ -387,6 +432,7 @@ pub fn highlight<F: Formatter>(source: &str, formatter: F) -> String {
 /// write_highlight(&mut file, code, formatter)?;
 /// # Ok::<(), std::io::Error>(())
 /// ```
+#[cfg(feature = "highlight")]
 pub fn write_highlight<F: Formatter>(
     output: &mut dyn Write,
     source: &str,
@@ -395,11 +441,136 @@ pub fn write_highlight<F: Formatter>(
     formatter.format(source, output)
 }
 
-#[cfg(test)]
+/// Read source code from a reader and write syntax highlighted output to a writer.
+///
+/// Tree-sitter needs the whole source in memory, so the reader is drained into a
+/// buffer before formatting. This saves file-processing tools from handling the read
+/// and UTF-8 validation themselves.
+///
+/// # Arguments
+///
+/// * `output` - The writer to send highlighted output to.
+/// * `reader` - The reader to take the source code from, e.g. a file or stdin.
+/// * `formatter` - A configured formatter.
+///
+/// # Errors
+///
+/// Returns any error from reading or writing. Input that isn't valid UTF-8 fails with
+/// [`io::ErrorKind::InvalidData`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use lumis::{write_highlight_reader, TerminalBuilder, languages::Language};
+/// use std::fs::File;
+///
+/// let formatter = TerminalBuilder::new()
+///     .lang(Language::Rust)
+///     .build()
+///     .unwrap();
+///
+/// let mut file = File::open("src/main.rs")?;
+/// write_highlight_reader(&mut std::io::stdout(), &mut file, formatter)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "highlight")]
+pub fn write_highlight_reader<F: Formatter>(
+    output: &mut dyn Write,
+    reader: &mut dyn Read,
+    formatter: F,
+) -> io::Result<()> {
+    let mut source = String::new();
+    reader.read_to_string(&mut source)?;
+    formatter.format(&source, output)
+}
+
+/// Highlight source code and return the output escaped as a JSON string value.
+///
+/// The result is quoted and has quotes, backslashes and control characters escaped,
+/// ready to be embedded as a value in a JSON document such as an API response. The
+/// output is escaped as the formatter writes it, without an intermediate string.
+///
+/// # Panics
+///
+/// Panics if the formatter fails to format the source code, like [`highlight()`].
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::{highlight_json_escaped, HtmlLinkedBuilder, languages::Language};
+///
+/// let formatter = HtmlLinkedBuilder::new()
+///     .lang(Language::PlainText)
+///     .build()
+///     .unwrap();
+///
+/// let json = highlight_json_escaped("say \"hi\"", formatter);
+/// assert_eq!(
+///     json,
+///     r#""<pre class=\"lumis\"><code class=\"language-plaintext\" translate=\"no\" tabindex=\"0\"><div class=\"line\" data-line=\"1\">say &quot;hi&quot;\n</div></code></pre>""#
+/// );
+/// ```
+#[cfg(feature = "highlight")]
+pub fn highlight_json_escaped<F: Formatter>(source: &str, formatter: F) -> String {
+    let mut buffer = vec![b'"'];
+    formatter
+        .format(source, &mut JsonEscape(&mut buffer))
+        .expect("formatter failed to format source code");
+    buffer.push(b'"');
+    String::from_utf8(buffer).expect("formatter produced invalid UTF-8")
+}
+
+// Writer escaping bytes for a JSON string. Multi-byte UTF-8 sequences never contain
+// ASCII bytes, so escaping byte by byte keeps them intact.
+#[cfg(feature = "highlight")]
+struct JsonEscape<'a>(&'a mut Vec<u8>);
+
+#[cfg(feature = "highlight")]
+impl Write for JsonEscape<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            match byte {
+                b'"' => self.0.extend_from_slice(b"\\\""),
+                b'\\' => self.0.extend_from_slice(b"\\\\"),
+                b'\n' => self.0.extend_from_slice(b"\\n"),
+                b'\r' => self.0.extend_from_slice(b"\\r"),
+                b'\t' => self.0.extend_from_slice(b"\\t"),
+                0x08 => self.0.extend_from_slice(b"\\b"),
+                0x0c => self.0.extend_from_slice(b"\\f"),
+                0x00..=0x1f => write!(self.0, "\\u{:04x}", byte)?,
+                _ => self.0.push(byte),
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "highlight"))]
 mod tests {
     use super::*;
     use crate::languages::Language;
 
+    #[test]
+    fn test_highlight_json_escaped() {
+        let code = "let s = \"a\\tb\";\n\tlet c = '\u{1b}é';\n";
+        let formatter = || {
+            TerminalBuilder::new()
+                .lang(Language::Rust)
+                .theme(themes::get("dracula").ok())
+                .build()
+                .unwrap()
+        };
+
+        let json = highlight_json_escaped(code, formatter());
+        let expected = serde_json::to_string(&highlight(code, formatter())).unwrap();
+        assert_eq!(json, expected);
+    }
+
     // println!("{}", result);
     // std::fs::write("result.html", result.clone()).unwrap();
 
@@ -425,6 +596,26 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_write_highlight_reader() {
+        let code = "const = 1";
+        let formatter = HtmlInlineBuilder::default()
+            .lang(Language::JavaScript)
+            .build()
+            .unwrap();
+
+        let mut expected = Vec::new();
+        write_highlight(&mut expected, code, formatter.clone()).unwrap();
+
+        let mut buffer = Vec::new();
+        write_highlight_reader(&mut buffer, &mut code.as_bytes(), formatter.clone()).unwrap();
+        assert_eq!(buffer, expected);
+
+        let error =
+            write_highlight_reader(&mut Vec::new(), &mut &b"\xff\xfe"[..], formatter).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
     #[test]
     fn test_highlight_html_inline() {
         let code = r#"defmodule Foo do
@@ -551,6 +742,101 @@ end
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_highlight_erb_injects_html_and_ruby() {
+        let code = "<p class=\"x\"><%= @user.name %></p>";
+        let formatter = HtmlLinkedBuilder::default()
+            .lang(Language::ERB)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            highlight(code, formatter),
+            r#"<pre class="lumis"><code class="language-erb" translate="no" tabindex="0"><div class="line" data-line="1"><span class="punctuation-bracket">&lt;</span><span class="tag">p</span> <span class="attribute">class</span><span class="operator">=</span><span class="string">&quot;<span class="string">x</span>&quot;</span><span class="punctuation-bracket">&gt;</span><span class="keyword">&lt;%=</span> <span class="variable-member">@user</span><span class="punctuation-delimiter">.</span><span class="function-call">name</span> <span class="keyword">%&gt;</span><span class="punctuation-bracket">&lt;/</span><span class="tag">p</span><span class="punctuation-bracket">&gt;</span>
+</div></code></pre>"#
+        );
+    }
+
+    #[test]
+    fn test_highlight_ejs_injects_javascript() {
+        let code = "<% if (user) { %><b><%= user.name %></b><% } %>";
+        let formatter = HtmlLinkedBuilder::default()
+            .lang(Language::EJS)
+            .build()
+            .unwrap();
+
+        let result = highlight(code, formatter);
+
+        assert!(result.contains(
+            r#"<span class="keyword">&lt;%</span> <span class="keyword-conditional">if</span>"#
+        ));
+        assert!(result.contains(r#"<span class="punctuation-bracket">&lbrace;</span> <span class="keyword">%&gt;</span>"#));
+        assert!(result.contains(r#"<span class="variable-member">name</span>"#));
+        assert!(result.contains(r#"<span class="tag">b</span>"#));
+        assert!(!result.contains("&amp;lt;"));
+    }
+
+    #[test]
+    fn test_highlight_eex_injects_html() {
+        let code = "<p><% if a %>{b}<% end %></p>";
+        let formatter = HtmlInlineBuilder::default()
+            .lang(Language::EEx)
+            .theme(themes::get("dracula").ok())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            highlight(code, formatter),
+            r#"<pre class="lumis" style="color: #f8f8f2; background-color: #282a36;"><code class="language-eex" translate="no" tabindex="0"><div class="line" data-line="1"><span style="color: #f8f8f2;">&lt;</span><span style="color: #8be9fd;">p</span><span style="color: #f8f8f2;">&gt;</span><span style="color: #8be9fd;">&lt;%</span> <span style="color: #ff79c6;">if</span> <span style="color: #f8f8f2;">a</span><span style="color: #8be9fd;"> %&gt;</span>&lbrace;b&rbrace;<span style="color: #8be9fd;">&lt;%</span> <span style="color: #f8f8f2;">end</span><span style="color: #8be9fd;"> %&gt;</span><span style="color: #f8f8f2;">&lt;/</span><span style="color: #8be9fd;">p</span><span style="color: #f8f8f2;">&gt;</span>
+</div></code></pre>"#
+        );
+    }
+
+    #[test]
+    fn test_highlight_php_heredoc_and_nowdoc() {
+        let code = "$a = <<<EOT\nHello {$name}\nEOT;\n$b = <<<'EOT'\nraw $x\nEOT;\necho $a;";
+        let formatter = HtmlLinkedBuilder::default()
+            .lang(Language::Php)
+            .build()
+            .unwrap();
+
+        let result = highlight(code, formatter);
+
+        // Heredocs interpolate variables, nowdocs don't
+        assert!(result.contains(r#"<span class="string">Hello <span class="punctuation-bracket">&lbrace;</span><span class="variable">$name</span><span class="punctuation-bracket">&rbrace;</span></span>"#));
+        assert!(result.contains(r#"<span class="string">raw $x</span>"#));
+        assert_eq!(
+            result.matches(r#"<span class="label">EOT</span>"#).count(),
+            4
+        );
+        // Code after the closing identifiers is highlighted as usual
+        assert!(result
+            .contains(r#"<span class="keyword">echo</span> <span class="variable">$a</span>"#));
+    }
+
+    #[test]
+    fn test_highlight_perl_heredoc_and_pod() {
+        let code =
+            "my $x = <<\"END\";\nhello $name\nEND\n\n=pod\n\nSome B<docs>\n\n=cut\n\nprint $x;";
+        let formatter = HtmlLinkedBuilder::default()
+            .lang(Language::Perl)
+            .build()
+            .unwrap();
+
+        let result = highlight(code, formatter);
+
+        assert!(
+            result.contains(r#"<span class="keyword">my</span> <span class="variable">$x</span>"#)
+        );
+        assert!(result
+            .contains(r#"<span class="string">hello <span class="variable">$name</span></span>"#));
+        assert!(result.contains(r#"<span class="label">END</span>"#));
+        // POD is left unstyled and doesn't leak into the code around it
+        assert!(result.contains(r#"<div class="line" data-line="7">Some B&lt;docs&gt;"#));
+        assert!(result
+            .contains(r#"<span class="function">print</span> <span class="variable">$x</span>"#));
+    }
+
     #[test]
     fn test_guess_language_by_file_name() {
         let code = "foo = 1";
@@ -663,4 +949,149 @@ end
         assert!(linked_result.ends_with("</section>"));
         assert!(linked_result.contains("<pre class=\"lumis\">"));
     }
+
+    #[test]
+    fn test_highlight_empty_source() {
+        let formatter = HtmlLinkedBuilder::default()
+            .lang(Language::Rust)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            highlight("", formatter),
+            r#"<pre class="lumis"><code class="language-rust" translate="no" tabindex="0"></code></pre>"#
+        );
+
+        let formatter = HtmlInlineBuilder::default()
+            .lang(Language::Rust)
+            .theme(themes::get("dracula").ok())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            highlight("", formatter),
+            r#"<pre class="lumis" style="color: #f8f8f2; background-color: #282a36;"><code class="language-rust" translate="no" tabindex="0"></code></pre>"#
+        );
+
+        let formatter = TerminalBuilder::default()
+            .lang(Language::Rust)
+            .theme(themes::get("dracula").ok())
+            .build()
+            .unwrap();
+
+        assert_eq!(highlight("", formatter), "");
+    }
+
+    #[test]
+    fn test_highlight_line_numbering_without_trailing_newline() {
+        for (code, lines) in [
+            ("a", 1),
+            ("a\n", 1),
+            ("a\nb", 2),
+            ("a\nb\n", 2),
+            ("\n\n", 2),
+        ] {
+            let formatter = HtmlLinkedBuilder::default()
+                .lang(Language::Rust)
+                .build()
+                .unwrap();
+
+            let result = highlight(code, formatter);
+
+            assert_eq!(
+                result.matches("<div class=\"line\"").count(),
+                lines,
+                "{code:?}"
+            );
+            assert!(
+                result.contains(&format!("data-line=\"{lines}\"")),
+                "{code:?}"
+            );
+            assert!(
+                !result.contains(&format!("data-line=\"{}\"", lines + 1)),
+                "{code:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_highlight_huge_single_line() {
+        let code = "x".repeat(1_000_000);
+        let formatter = HtmlInlineBuilder::default()
+            .lang(Language::PlainText)
+            .build()
+            .unwrap();
+
+        let result = highlight(&code, formatter);
+
+        assert_eq!(result.matches("data-line=").count(), 1);
+        assert!(result.contains(&code));
+
+        let code = "{}".repeat(500_000);
+        let formatter = HtmlLinkedBuilder::default()
+            .lang(Language::PlainText)
+            .build()
+            .unwrap();
+
+        let result = highlight(&code, formatter);
+
+        assert_eq!(result.matches("data-line=").count(), 1);
+        assert_eq!(result.matches("&lbrace;").count(), 500_000);
+        assert_eq!(result.matches("&rbrace;").count(), 500_000);
+    }
+
+    #[test]
+    fn test_highlight_escapes_by_char_not_byte() {
+        // U+013E and U+0126 share their low byte with `>` and `&`
+        let code = "ľĦ<&>";
+        let formatter = HtmlLinkedBuilder::default()
+            .lang(Language::PlainText)
+            .build()
+            .unwrap();
+
+        let result = highlight(code, formatter);
+
+        assert!(result.contains("ľĦ&lt;&amp;&gt;"));
+    }
+
+    #[test]
+    fn test_highlight_arbitrary_input_does_not_panic() {
+        // Deterministic xorshift so failures are reproducible.
+        let mut seed: u32 = 0x9e37_79b9;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+        let alphabet = [
+            "a", "1", " ", "\t", "\n", "\r", "\r\n", "{", "}", "<", ">", "&", "\"", "'", "\\",
+            "/*", "*/", "//", "#", "ľ", "é", "😀", "\u{0}", "\u{feff}",
+        ];
+
+        for _ in 0..50 {
+            let len = (next() % 200) as usize;
+            let code: String = (0..len)
+                .map(|_| alphabet[next() as usize % alphabet.len()])
+                .collect();
+            let expected_lines = code.split_inclusive('\n').count();
+
+            for lang in [Language::PlainText, Language::Rust, Language::HTML] {
+                let formatter = HtmlLinkedBuilder::default().lang(lang).build().unwrap();
+                let result = highlight(&code, formatter);
+                assert_eq!(
+                    result.matches("data-line=").count(),
+                    expected_lines,
+                    "{lang:?} {code:?}"
+                );
+
+                let formatter = TerminalBuilder::default()
+                    .lang(lang)
+                    .theme(themes::get("dracula").ok())
+                    .build()
+                    .unwrap();
+                let _ = highlight(&code, formatter);
+            }
+        }
+    }
 }
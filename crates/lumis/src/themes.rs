@@ -79,7 +79,13 @@
 //! for a complete example of building themes programmatically.
 
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, fs, path::Path, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::{PoisonError, RwLock},
+};
 
 /// Error type for theme operations.
 #[derive(Debug, Clone)]
@@ -143,7 +149,7 @@ impl std::error::Error for ThemeParseError {}
 /// - `Double` - double underline (`underdouble` in Neovim)
 /// - `Dotted` - dotted underline (`underdotted` in Neovim)
 /// - `Dashed` - dashed underline (`underdashed` in Neovim)
-#[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum UnderlineStyle {
     #[default]
@@ -174,7 +180,7 @@ pub enum UnderlineStyle {
 ///     strikethrough: true,
 /// };
 /// ```
-#[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct TextDecoration {
     /// The underline style to apply.
     #[serde(default)]
@@ -207,7 +213,23 @@ impl std::fmt::Display for Appearance {
     }
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+/// Editor UI colors of a theme, as returned by [`Theme::ui_colors`].
+///
+/// Colors are hex strings (e.g., "#44475a"). Each one is `None` when the theme
+/// doesn't define it and has nothing to fall back to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UiColors {
+    /// Background of selected text (Neovim `Visual`).
+    pub selection_bg: Option<String>,
+    /// Color of the cursor (Neovim `Cursor`).
+    pub cursor: Option<String>,
+    /// Background of the line under the cursor (Neovim `CursorLine`).
+    pub cursor_line_bg: Option<String>,
+    /// Foreground of line numbers in the gutter (Neovim `LineNr`).
+    pub line_number_fg: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
 /// A theme for syntax highlighting.
 ///
 /// A theme consists of a name, appearance (light/dark), revision (commit) and a collection of highlight styles
@@ -321,7 +343,7 @@ impl FromStr for Theme {
 ///     ..Default::default()
 /// };
 /// ```
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct Style {
     /// The foreground color in hex format (e.g., "#ff79c6").
     pub fg: Option<String>,
@@ -534,6 +556,64 @@ pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Theme, ThemeError> {
     from_json(&json)
 }
 
+/// Load every `*.json` theme file in a directory.
+///
+/// Each file is parsed with [`from_file`] and paired with a name derived from its
+/// file stem, so `themes/my_theme.json` is returned as `"my_theme"`. Subdirectories
+/// and files with other extensions are ignored. Results are sorted by name.
+///
+/// Loaded themes are not added to the built-in list returned by [`get`] and
+/// [`available_themes`]; keep the returned pairs to look them up.
+///
+/// # Errors
+///
+/// Returns [`ThemeError::FileNotFound`] if the directory doesn't exist,
+/// [`ThemeError::FileReadError`] if it or one of its files can't be read,
+/// or [`ThemeError::InvalidJson`] (prefixed with the file path) for the first
+/// malformed theme file.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lumis::themes;
+///
+/// for (name, theme) in themes::load_dir("themes").expect("Failed to load themes") {
+///     println!("{name}: {}", theme.appearance);
+/// }
+/// ```
+pub fn load_dir<P: AsRef<Path>>(path: P) -> Result<Vec<(String, Theme)>, ThemeError> {
+    let path = path.as_ref();
+    let entries = fs::read_dir(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            ThemeError::FileNotFound(path.display().to_string())
+        } else {
+            ThemeError::FileReadError(e.to_string())
+        }
+    })?;
+
+    let mut themes = Vec::new();
+    for entry in entries {
+        let file = entry?.path();
+        if !file.is_file() || file.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let Some(name) = file.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+
+        let theme = from_file(&file).map_err(|err| match err {
+            ThemeError::InvalidJson(msg) => {
+                ThemeError::InvalidJson(format!("{}: {msg}", file.display()))
+            }
+            err => err,
+        })?;
+        themes.push((name.to_string(), theme));
+    }
+
+    themes.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(themes)
+}
+
 /// Parse a theme from a JSON string.
 ///
 /// This function parses a JSON string containing theme definition data and creates
@@ -635,6 +715,417 @@ pub fn from_json(json: &str) -> Result<Theme, ThemeError> {
     Ok(theme)
 }
 
+/// Convert a VS Code color theme to a Lumis theme.
+///
+/// Styles are read from the theme's `tokenColors` rules by looking up the TextMate
+/// scopes that correspond to each Lumis scope, e.g. `entity.name.function` for
+/// `function` or `keyword.control.import` for `keyword.import`. Like in VS Code, the
+/// most specific matching rule wins, so a rule for `keyword` also styles
+/// `keyword.control.import`. Scopes that no rule matches are left out and fall back
+/// to their parent scope, see [`Theme::get_style`].
+///
+/// The `normal`, `highlighted`, `selection`, `cursor` and `line_number` highlights are
+/// taken from the `editor.foreground`, `editor.background`,
+/// `editor.lineHighlightBackground`, `editor.selectionBackground`,
+/// `editorCursor.foreground` and `editorLineNumber.foreground` colors. Colors with
+/// an alpha channel are blended into the background. The appearance comes from the
+/// theme `type`, or from the background when it's missing.
+///
+/// Comments and trailing commas are accepted, as in VS Code theme files. Semantic
+/// token colors are ignored. The theme is named after its `name`, or `custom`, with
+/// a `vscode` revision.
+///
+/// # Errors
+///
+/// Returns [`ThemeError::InvalidJson`] if the JSON is malformed.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::themes::{self, Appearance};
+///
+/// let json = r##"{
+///     "name": "My Theme",
+///     "type": "light",
+///     "colors": { "editor.foreground": "#24292e", "editor.background": "#ffffff" },
+///     "tokenColors": [
+///         // Comments are allowed
+///         { "scope": "comment", "settings": { "foreground": "#6a737d", "fontStyle": "italic" } },
+///         { "scope": ["keyword", "storage"], "settings": { "foreground": "#d73a49" } },
+///     ]
+/// }"##;
+///
+/// let theme = themes::from_vscode_json(json).unwrap();
+///
+/// assert_eq!(theme.name, "My Theme");
+/// assert_eq!(theme.appearance, Appearance::Light);
+/// assert_eq!(theme.bg(), Some("#ffffff"));
+/// assert!(theme.get_style("comment").unwrap().italic);
+/// assert_eq!(theme.get_style("keyword.import").unwrap().fg.as_deref(), Some("#d73a49"));
+/// ```
+pub fn from_vscode_json(json: &str) -> Result<Theme, ThemeError> {
+    let vscode: VscodeTheme = serde_json::from_str(&strip_jsonc(json))?;
+
+    let rules: Vec<(&str, &VscodeSettings)> = vscode
+        .token_colors
+        .iter()
+        .flat_map(|rule| {
+            let selectors: Vec<&str> = match &rule.scope {
+                Some(VscodeScope::One(scope)) => scope.split(',').collect(),
+                Some(VscodeScope::Many(scopes)) => scopes.iter().map(String::as_str).collect(),
+                None => Vec::new(),
+            };
+
+            // Descendant and exclusion selectors can't be matched without the
+            // scope hierarchy of a token, so only plain scopes are kept
+            selectors
+                .into_iter()
+                .map(str::trim)
+                .filter(|selector| !selector.is_empty() && !selector.contains(' '))
+                .map(move |selector| (selector, &rule.settings))
+        })
+        .collect();
+
+    let editor_color = |key: &str| vscode.colors.get(key).cloned().flatten();
+    // Rules without a scope hold the default colors in older themes
+    let global = vscode.token_colors.iter().find(|rule| rule.scope.is_none());
+    let background = editor_color("editor.background")
+        .or_else(|| global.and_then(|rule| rule.settings.background.clone()))
+        .and_then(|color| vscode_color(&color, None));
+    let foreground = editor_color("editor.foreground")
+        .or_else(|| global.and_then(|rule| rule.settings.foreground.clone()));
+    let color = |color: &str| vscode_color(color, background.as_deref());
+
+    let mut highlights = BTreeMap::new();
+
+    let normal = Style {
+        fg: foreground.as_deref().and_then(color),
+        bg: background.clone(),
+        ..Default::default()
+    };
+    if normal != Style::default() {
+        highlights.insert("normal".to_string(), normal);
+    }
+
+    for (scope, key, is_bg) in [
+        ("highlighted", "editor.lineHighlightBackground", true),
+        ("selection", "editor.selectionBackground", true),
+        ("cursor", "editorCursor.foreground", true),
+        ("line_number", "editorLineNumber.foreground", false),
+    ] {
+        if let Some(value) = editor_color(key).as_deref().and_then(color) {
+            let style = if is_bg {
+                Style {
+                    bg: Some(value),
+                    ..Default::default()
+                }
+            } else {
+                Style {
+                    fg: Some(value),
+                    ..Default::default()
+                }
+            };
+            highlights.insert(scope.to_string(), style);
+        }
+    }
+
+    for (scope, textmate_scopes) in VSCODE_SCOPES {
+        let style = textmate_scopes.iter().find_map(|textmate_scope| {
+            let fg = vscode_rule(&rules, textmate_scope, |s| s.foreground.as_deref());
+            let bg = vscode_rule(&rules, textmate_scope, |s| s.background.as_deref());
+            let font_style = vscode_rule(&rules, textmate_scope, |s| s.font_style.as_deref());
+
+            if fg.is_none() && bg.is_none() && font_style.is_none() {
+                return None;
+            }
+
+            let font_style: Vec<&str> = font_style.unwrap_or_default().split_whitespace().collect();
+            Some(Style {
+                fg: fg.and_then(color),
+                bg: bg.and_then(color),
+                bold: font_style.contains(&"bold"),
+                italic: font_style.contains(&"italic"),
+                text_decoration: TextDecoration {
+                    underline: if font_style.contains(&"underline") {
+                        UnderlineStyle::Solid
+                    } else {
+                        UnderlineStyle::None
+                    },
+                    strikethrough: font_style.contains(&"strikethrough"),
+                },
+            })
+        });
+
+        if let Some(style) = style {
+            highlights.insert(scope.to_string(), style);
+        }
+    }
+
+    let mut theme = Theme::new(
+        vscode.name.unwrap_or_else(|| "custom".to_string()),
+        Appearance::Dark,
+        "vscode".to_string(),
+        highlights,
+    );
+    theme.appearance = match vscode.kind.as_deref() {
+        Some("light" | "hcLight") => Appearance::Light,
+        Some("dark" | "hc" | "hcDark") => Appearance::Dark,
+        _ => theme.detect_appearance(),
+    };
+
+    Ok(theme)
+}
+
+#[derive(Deserialize)]
+struct VscodeTheme {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    colors: HashMap<String, Option<String>>,
+    #[serde(default, rename = "tokenColors")]
+    token_colors: Vec<VscodeTokenColor>,
+}
+
+#[derive(Deserialize)]
+struct VscodeTokenColor {
+    #[serde(default)]
+    scope: Option<VscodeScope>,
+    #[serde(default)]
+    settings: VscodeSettings,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VscodeScope {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Default, Deserialize)]
+struct VscodeSettings {
+    #[serde(default)]
+    foreground: Option<String>,
+    #[serde(default)]
+    background: Option<String>,
+    #[serde(default, rename = "fontStyle")]
+    font_style: Option<String>,
+}
+
+// TextMate scopes to read the style of each scope from, in order of preference.
+const VSCODE_SCOPES: &[(&str, &[&str])] = &[
+    ("attribute", &["entity.other.attribute-name"]),
+    (
+        "boolean",
+        &["constant.language.boolean", "constant.language"],
+    ),
+    ("character", &["constant.character"]),
+    ("character.special", &["constant.character.escape"]),
+    ("comment", &["comment"]),
+    ("comment.documentation", &["comment.block.documentation"]),
+    ("constant", &["variable.other.constant", "constant.other"]),
+    (
+        "constant.builtin",
+        &["constant.language", "support.constant"],
+    ),
+    (
+        "constructor",
+        &["entity.name.function.constructor", "entity.name.class"],
+    ),
+    ("diff.delta", &["markup.changed"]),
+    ("diff.minus", &["markup.deleted"]),
+    ("diff.plus", &["markup.inserted"]),
+    ("function", &["entity.name.function"]),
+    ("function.builtin", &["support.function"]),
+    (
+        "function.macro",
+        &["entity.name.function.macro", "support.function.macro"],
+    ),
+    ("function.method", &["entity.name.function.member"]),
+    ("keyword", &["keyword.control", "storage.type"]),
+    ("keyword.conditional", &["keyword.control.conditional"]),
+    (
+        "keyword.exception",
+        &["keyword.control.exception", "keyword.control.trycatch"],
+    ),
+    ("keyword.function", &["storage.type.function"]),
+    ("keyword.import", &["keyword.control.import"]),
+    ("keyword.modifier", &["storage.modifier"]),
+    (
+        "keyword.operator",
+        &["keyword.operator.expression", "keyword.operator"],
+    ),
+    ("keyword.repeat", &["keyword.control.loop"]),
+    ("keyword.return", &["keyword.control.flow"]),
+    ("keyword.type", &["storage.type"]),
+    ("label", &["entity.name.label"]),
+    ("markup.heading", &["markup.heading", "entity.name.section"]),
+    ("markup.italic", &["markup.italic"]),
+    ("markup.link", &["markup.underline.link"]),
+    ("markup.link.label", &["string.other.link"]),
+    (
+        "markup.list",
+        &["markup.list", "punctuation.definition.list"],
+    ),
+    ("markup.quote", &["markup.quote"]),
+    ("markup.raw", &["markup.inline.raw", "markup.raw"]),
+    ("markup.strikethrough", &["markup.strikethrough"]),
+    ("markup.strong", &["markup.bold"]),
+    ("markup.underline", &["markup.underline"]),
+    ("module", &["entity.name.namespace", "entity.name.module"]),
+    (
+        "namespace",
+        &["entity.name.namespace", "entity.name.module"],
+    ),
+    ("number", &["constant.numeric"]),
+    ("operator", &["keyword.operator"]),
+    (
+        "property",
+        &["variable.other.property", "support.type.property-name"],
+    ),
+    (
+        "punctuation.bracket",
+        &["punctuation.section", "punctuation"],
+    ),
+    (
+        "punctuation.delimiter",
+        &[
+            "punctuation.separator",
+            "punctuation.terminator",
+            "punctuation",
+        ],
+    ),
+    (
+        "punctuation.special",
+        &["punctuation.definition.template-expression", "punctuation"],
+    ),
+    ("string", &["string"]),
+    ("string.escape", &["constant.character.escape"]),
+    ("string.regexp", &["string.regexp"]),
+    ("string.special", &["string.other"]),
+    ("string.special.symbol", &["constant.other.symbol"]),
+    ("string.special.url", &["markup.underline.link"]),
+    ("tag", &["entity.name.tag"]),
+    ("tag.attribute", &["entity.other.attribute-name"]),
+    ("tag.delimiter", &["punctuation.definition.tag"]),
+    (
+        "type",
+        &["entity.name.type", "support.type", "support.class"],
+    ),
+    ("type.builtin", &["support.type.primitive", "support.type"]),
+    ("variable", &["variable.other.readwrite", "variable"]),
+    ("variable.builtin", &["variable.language"]),
+    (
+        "variable.member",
+        &["variable.other.property", "variable.other.member"],
+    ),
+    ("variable.parameter", &["variable.parameter"]),
+];
+
+// Value of the most specific rule matching `scope` that sets `property`, later rules
+// winning ties as in VS Code.
+fn vscode_rule<'a>(
+    rules: &[(&str, &'a VscodeSettings)],
+    scope: &str,
+    property: impl Fn(&'a VscodeSettings) -> Option<&'a str>,
+) -> Option<&'a str> {
+    let mut best: Option<(usize, &str)> = None;
+
+    for (selector, settings) in rules {
+        let matches = scope == *selector
+            || scope
+                .strip_prefix(selector)
+                .is_some_and(|rest| rest.starts_with('.'));
+
+        if let Some(value) = property(settings).filter(|_| matches) {
+            if best.is_none_or(|(len, _)| selector.len() >= len) {
+                best = Some((selector.len(), value));
+            }
+        }
+    }
+
+    best.map(|(_, value)| value)
+}
+
+// Normalize `#rgb`, `#rgba`, `#rrggbb` and `#rrggbbaa` colors to `#rrggbb`, blending
+// translucent colors into `background` when it's known.
+fn vscode_color(color: &str, background: Option<&str>) -> Option<String> {
+    let hex = color.strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let hex: String = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => hex.to_string(),
+        _ => return None,
+    };
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+    };
+    let (r, g, b) = (channel(0)?, channel(2)?, channel(4)?);
+
+    let (r, g, b) = match (channel(6), background.and_then(hex_to_rgb)) {
+        (Some(alpha), Some((br, bg, bb))) if hex.len() == 8 => {
+            let alpha = alpha as f32 / 255.0;
+            let blend = |c: u8, b: u8| (c as f32 * alpha + b as f32 * (1.0 - alpha)).round() as u8;
+            (blend(r, br), blend(g, bg), blend(b, bb))
+        }
+        _ => (r, g, b),
+    };
+
+    Some(format!("#{r:02x}{g:02x}{b:02x}"))
+}
+
+// Remove comments and trailing commas, which VS Code allows in theme files.
+fn strip_jsonc(json: &str) -> String {
+    let mut output = String::with_capacity(json.len());
+    let mut chars = json.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            match c {
+                '\\' => output.extend(chars.next()),
+                '"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                output.push(c);
+            }
+            ('/', Some('/')) => while chars.next_if(|&c| c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            (']' | '}', _) => {
+                let trimmed = output.trim_end().len();
+                if output[..trimmed].ends_with(',') {
+                    output.remove(trimmed - 1);
+                }
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
 impl Theme {
     pub fn new(
         name: String,
@@ -650,18 +1141,169 @@ impl Theme {
         }
     }
 
+    /// Create a minimal theme from a handful of scope styles.
+    ///
+    /// Useful for tests, brand-colored code blocks or UIs that only need a few colors
+    /// instead of a full Neovim theme. Scopes resolve like any other theme, so a style
+    /// for `"keyword"` also applies to `"keyword.function"`. Add a `"normal"` style to
+    /// set the block's foreground and background. The theme is named `custom` with
+    /// an `unknown` revision.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::themes::{Appearance, Style, Theme};
+    /// use std::collections::HashMap;
+    ///
+    /// let theme = Theme::from_scope_map(
+    ///     HashMap::from([
+    ///         ("keyword".to_string(), Style { fg: Some("#0000ff".to_string()), ..Default::default() }),
+    ///         ("string".to_string(), Style { fg: Some("#008000".to_string()), ..Default::default() }),
+    ///     ]),
+    ///     Appearance::Light,
+    /// );
+    ///
+    /// let style = theme.get_style("keyword.function").unwrap();
+    /// assert_eq!(style.fg.as_deref(), Some("#0000ff"));
+    /// ```
+    pub fn from_scope_map(highlights: HashMap<String, Style>, appearance: Appearance) -> Self {
+        Theme::new(
+            "custom".to_string(),
+            appearance,
+            "unknown".to_string(),
+            highlights.into_iter().collect(),
+        )
+    }
+
     pub fn css(&self, enable_italic: bool) -> String {
+        self.css_rules("", enable_italic, true)
+    }
+
+    /// Generate the linked stylesheet with every scope class prefixed by `prefix`.
+    ///
+    /// Scope classes are emitted as `.{prefix}{scope}` (e.g. `.hl-keyword` for the
+    /// prefix `hl-`) so the CSS matches HTML rendered with prefixed class names.
+    /// When `include_base` is `false` the header comment and the `pre.lumis` rule
+    /// are omitted, leaving only the scope rules. Italic styles are only emitted with
+    /// `enable_italic`, like [`css`](Self::css).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::themes;
+    ///
+    /// let theme = themes::get("dracula").unwrap();
+    /// let css = theme.css_with_prefix("hl-", true, true);
+    ///
+    /// assert!(css.contains("pre.lumis {"));
+    /// assert!(css.contains(".hl-keyword {"));
+    /// ```
+    pub fn css_with_prefix(&self, prefix: &str, enable_italic: bool, include_base: bool) -> String {
+        self.css_rules(prefix, enable_italic, include_base)
+    }
+
+    /// Generate the CSS rule styling highlighted lines in linked HTML output.
+    ///
+    /// [`HtmlLinked`](crate::HtmlLinked) only adds `class` to the highlighted line
+    /// elements, see [`HighlightLines`](crate::formatter::html_linked::HighlightLines).
+    /// This returns a `.line.{class}` rule with the theme's `highlighted` style, the
+    /// same colors [`HighlightLinesStyle::Theme`](crate::formatter::html_inline::HighlightLinesStyle::Theme)
+    /// inlines. A class made of several space-separated names matches all of them.
+    /// Returns an empty string if the theme has no `highlighted` style.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::themes;
+    ///
+    /// let theme = themes::get("dracula").unwrap();
+    /// let css = theme.highlight_line_css("highlighted");
+    ///
+    /// assert_eq!(css, ".line.highlighted {\n  background-color: #44475a;\n}\n");
+    /// ```
+    pub fn highlight_line_css(&self, class: &str) -> String {
+        let style_css = self
+            .get_style("highlighted")
+            .map(|style| style.css(true, "\n  "))
+            .unwrap_or_default();
+
+        if style_css.is_empty() {
+            return String::new();
+        }
+
+        let selector: String = class
+            .split_whitespace()
+            .map(|name| format!(".{name}"))
+            .collect();
+
+        format!(".line{selector} {{\n  {style_css}\n}}\n")
+    }
+
+    /// Generate an HTML legend showing the color of each scope, for theme galleries.
+    ///
+    /// Returns a `<div class="lumis-legend">` grid with the theme's foreground and
+    /// background, holding a `<div class="lumis-legend-item">` per styled scope in
+    /// alphabetical order. Each item has a `<span class="lumis-legend-swatch">` filled
+    /// with the scope's foreground, or its background when it has no foreground, and the
+    /// scope name rendered in the scope's style. Scopes without any style are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::themes;
+    ///
+    /// let theme = themes::get("dracula").unwrap();
+    /// let html = theme.legend_html();
+    ///
+    /// assert!(html.starts_with(r#"<div class="lumis-legend""#));
+    /// assert!(html.contains(r#"<span style="color: #8be9fd;">keyword.function</span>"#));
+    /// ```
+    pub fn legend_html(&self) -> String {
+        let mut html = format!(
+            "<div class=\"lumis-legend\" style=\"display: grid; grid-template-columns: repeat(auto-fill, minmax(16em, 1fr)); gap: 0.25em 1em; padding: 1em; font-family: monospace;{}\">",
+            self.pre_style(" ")
+                .map(|style| format!(" {}", v_htmlescape::escape(&style)))
+                .unwrap_or_default()
+        );
+
+        for (scope, style) in &self.highlights {
+            let style_css = style.css(true, " ");
+            if style_css.is_empty() {
+                continue;
+            }
+
+            let swatch = style
+                .fg
+                .as_deref()
+                .or(style.bg.as_deref())
+                .map(|color| format!(" background-color: {};", v_htmlescape::escape(color)))
+                .unwrap_or_default();
+            let style_css = v_htmlescape::escape(&style_css);
+            let scope = v_htmlescape::escape(scope);
+
+            html.push_str(&format!(
+                "<div class=\"lumis-legend-item\"><span class=\"lumis-legend-swatch\" style=\"display: inline-block; width: 1em; height: 1em; margin-right: 0.5em; vertical-align: middle;{swatch}\"></span><span style=\"{style_css}\">{scope}</span></div>"
+            ));
+        }
+
+        html.push_str("</div>");
+        html
+    }
+
+    fn css_rules(&self, prefix: &str, enable_italic: bool, include_base: bool) -> String {
         let mut rules = Vec::new();
 
-        rules.push(format!(
-            "/* {}\n * revision: {}\n */\n\npre.lumis",
-            self.name, self.revision
-        ));
+        if include_base {
+            rules.push(format!(
+                "/* {}\n * revision: {}\n */\n\npre.lumis",
+                self.name, self.revision
+            ));
 
-        if let Some(pre_style) = &self.pre_style("\n  ") {
-            rules.push(format!(" {{\n  {pre_style}\n}}\n"));
-        } else {
-            rules.push(" {}\n".to_string());
+            if let Some(pre_style) = &self.pre_style("\n  ") {
+                rules.push(format!(" {{\n  {pre_style}\n}}\n"));
+            } else {
+                rules.push(" {}\n".to_string());
+            }
         }
 
         for (scope, style) in &self.highlights {
@@ -669,7 +1311,8 @@ impl Theme {
 
             if !style_css.is_empty() {
                 rules.push(format!(
-                    ".{} {{\n  {}\n}}\n",
+                    ".{}{} {{\n  {}\n}}\n",
+                    prefix,
                     scope.replace('.', "-"),
                     style_css
                 ))
@@ -719,6 +1362,45 @@ impl Theme {
         }
     }
 
+    /// Returns `true` if any highlight scope resolves to a style for the given language.
+    #[cfg(feature = "highlight")]
+    pub(crate) fn resolves_any_scope(&self, lang: &crate::languages::Language) -> bool {
+        let lang_id = lang.id_name();
+        crate::constants::HIGHLIGHT_NAMES
+            .iter()
+            .any(|scope| self.get_style(&format!("{}.{}", scope, lang_id)).is_some())
+    }
+
+    /// Returns the highlight scopes this theme doesn't style.
+    ///
+    /// Every scope in [`HIGHLIGHT_NAMES`](crate::constants::HIGHLIGHT_NAMES) is looked up
+    /// with [`get_style`](Theme::get_style), so a scope styled through a parent scope,
+    /// like `keyword.function` through `keyword`, is not missing. Scopes are returned in
+    /// the order of `HIGHLIGHT_NAMES`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lumis::themes::{Appearance, Style, Theme};
+    /// use std::collections::HashMap;
+    ///
+    /// let theme = Theme::from_scope_map(
+    ///     HashMap::from([("keyword".to_string(), Style::default())]),
+    ///     Appearance::Dark,
+    /// );
+    /// let missing = theme.missing_scopes();
+    ///
+    /// assert!(missing.contains(&"comment"));
+    /// assert!(!missing.contains(&"keyword.function"));
+    /// ```
+    pub fn missing_scopes(&self) -> Vec<&'static str> {
+        crate::constants::HIGHLIGHT_NAMES
+            .iter()
+            .copied()
+            .filter(|scope| self.get_style(scope).is_none())
+            .collect()
+    }
+
     pub fn fg(&self) -> Option<&str> {
         self.get_style("normal").and_then(|s| s.fg.as_deref())
     }
@@ -727,6 +1409,154 @@ impl Theme {
         self.get_style("normal").and_then(|s| s.bg.as_deref())
     }
 
+    /// Get the background color as a CSS `rgba()` value with the given opacity.
+    ///
+    /// `alpha` is clamped between `0.0` (transparent) and `1.0` (opaque). Returns `None`
+    /// if the theme has no background or it isn't a `#rrggbb` color.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::themes;
+    ///
+    /// let theme = themes::get("dracula").unwrap();
+    /// assert_eq!(theme.bg_rgba(0.8).as_deref(), Some("rgba(40, 42, 54, 0.8)"));
+    /// ```
+    pub fn bg_rgba(&self, alpha: f32) -> Option<String> {
+        let (r, g, b) = hex_to_rgb(self.bg()?)?;
+        Some(format!("rgba({r}, {g}, {b}, {})", alpha.clamp(0.0, 1.0)))
+    }
+
+    /// Detect whether the theme is light or dark from the luminance of its background.
+    ///
+    /// Uses the relative luminance of the `normal` background color. Falls back to the
+    /// declared `appearance` when the theme has no background or it isn't a `#rrggbb`
+    /// color. Useful to validate or correct the `appearance` of custom themes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::themes::{self, Appearance};
+    ///
+    /// let theme = themes::get("github_light").unwrap();
+    /// assert_eq!(theme.detect_appearance(), Appearance::Light);
+    ///
+    /// let json = r##"{"name": "mislabeled", "appearance": "dark", "revision": "1",
+    ///     "highlights": {"normal": {"fg": "#24292f", "bg": "#ffffff"}}}"##;
+    /// let theme = themes::from_json(json).unwrap();
+    /// assert_eq!(theme.detect_appearance(), Appearance::Light);
+    /// ```
+    pub fn detect_appearance(&self) -> Appearance {
+        // 0.179 is where black and white text contrast equally.
+        match self
+            .bg()
+            .and_then(relative_luminance)
+            .map(|luminance| luminance > 0.179)
+        {
+            Some(true) => Appearance::Light,
+            Some(false) => Appearance::Dark,
+            None => self.appearance,
+        }
+    }
+
+    /// Colors for editor UI elements beyond syntax tokens.
+    ///
+    /// Reads the `selection`, `cursor`, `highlighted` and `line_number` highlights,
+    /// extracted from Neovim's `Visual`, `Cursor`, `CursorLine` and `LineNr` groups.
+    /// Themes that don't carry a group fall back to the closest token color: the
+    /// selection uses the `highlighted` background, the cursor the `normal` foreground
+    /// and line numbers the `comment` foreground.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::themes;
+    ///
+    /// let theme = themes::get("dracula").unwrap();
+    /// let ui = theme.ui_colors();
+    /// assert_eq!(ui.cursor.as_deref(), theme.fg());
+    /// ```
+    pub fn ui_colors(&self) -> UiColors {
+        let color = |scope: &str, bg: bool| {
+            self.highlights.get(scope).and_then(|style| {
+                if bg {
+                    style.bg.clone()
+                } else {
+                    style.fg.clone()
+                }
+            })
+        };
+
+        let cursor_line_bg = color("highlighted", true);
+
+        UiColors {
+            selection_bg: color("selection", true).or_else(|| cursor_line_bg.clone()),
+            cursor: color("cursor", true).or_else(|| color("normal", false)),
+            cursor_line_bg,
+            line_number_fg: color("line_number", false).or_else(|| color("comment", false)),
+        }
+    }
+
+    /// Derive a variant of the theme by shifting every color in HSL space.
+    ///
+    /// `lightness_delta` and `saturation_delta` are added to the lightness and
+    /// saturation of each `fg` and `bg` color, both ranging from `0.0` to `1.0`, and the
+    /// results are clamped to that range. Negative deltas darken or desaturate, e.g.
+    /// `adjust(0.0, -0.2)` for a softer variant. Colors that aren't `#rrggbb` are kept
+    /// as is, and so are the name and other metadata.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lumis::themes;
+    ///
+    /// let theme = themes::get("dracula").unwrap();
+    /// let darker = theme.adjust(-0.05, 0.0);
+    ///
+    /// assert_eq!(theme.bg(), Some("#282a36"));
+    /// assert_eq!(darker.bg(), Some("#1d1f27"));
+    /// ```
+    pub fn adjust(&self, lightness_delta: f32, saturation_delta: f32) -> Theme {
+        fn adjust_color(hex: &str, lightness_delta: f32, saturation_delta: f32) -> Option<String> {
+            if !hex.starts_with('#') {
+                return None;
+            }
+
+            let (r, g, b) = hex_to_rgb(hex)?;
+            let (h, s, l) = rgb_to_hsl(r, g, b);
+            let s = (s + saturation_delta).clamp(0.0, 1.0);
+            let l = (l + lightness_delta).clamp(0.0, 1.0);
+            let (r, g, b) = hsl_to_rgb(h, s, l);
+
+            Some(format!("#{r:02x}{g:02x}{b:02x}"))
+        }
+
+        let adjust = |color: &Option<String>| {
+            color.as_ref().map(|color| {
+                adjust_color(color, lightness_delta, saturation_delta)
+                    .unwrap_or_else(|| color.clone())
+            })
+        };
+
+        let highlights = self
+            .highlights
+            .iter()
+            .map(|(scope, style)| {
+                let style = Style {
+                    fg: adjust(&style.fg),
+                    bg: adjust(&style.bg),
+                    ..style.clone()
+                };
+                (scope.clone(), style)
+            })
+            .collect();
+
+        Theme {
+            highlights,
+            ..self.clone()
+        }
+    }
+
     pub fn pre_style(&self, separator: &str) -> Option<String> {
         let mut rules = Vec::new();
 
@@ -746,6 +1576,85 @@ impl Theme {
     }
 }
 
+// Parse a `#rrggbb` or `rrggbb` color, see `ansi::hex_to_rgb`.
+pub(crate) fn hex_to_rgb(hex: &str) -> Option<(u8, u8, u8)> {
+    let hex = hex.trim_start_matches('#');
+
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+    let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+    let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+
+    Some((r, g, b))
+}
+
+// WCAG relative luminance of a `#rrggbb` color, from 0 (black) to 1 (white).
+pub(crate) fn relative_luminance(hex: &str) -> Option<f64> {
+    let (r, g, b) = hex_to_rgb(hex)?;
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    Some(0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b))
+}
+
+// WCAG contrast ratio between two `#rrggbb` colors, from 1 to 21.
+#[cfg(feature = "highlight")]
+pub(crate) fn contrast_ratio(a: &str, b: &str) -> Option<f64> {
+    let (a, b) = (relative_luminance(a)?, relative_luminance(b)?);
+    Some((a.max(b) + 0.05) / (a.min(b) + 0.05))
+}
+
+// Convert RGB to hue (in degrees), saturation and lightness (from 0 to 1).
+fn rgb_to_hsl(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let delta = max - min;
+
+    if delta == 0.0 {
+        return (0.0, 0.0, l);
+    }
+
+    let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    (h, s, l)
+}
+
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((h / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let m = l - c / 2.0;
+
+    let (r, g, b) = match h {
+        h if h < 60.0 => (c, x, 0.0),
+        h if h < 120.0 => (x, c, 0.0),
+        h if h < 180.0 => (0.0, c, x),
+        h if h < 240.0 => (0.0, x, c),
+        h if h < 300.0 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    let channel = |v: f32| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b))
+}
+
 impl Style {
     pub fn css(&self, enable_italic: bool, separator: &str) -> String {
         let mut rules = Vec::new();
@@ -864,11 +1773,91 @@ pub fn available_themes() -> impl Iterator<Item = &'static Theme> {
     ALL_THEMES.iter().copied()
 }
 
+/// All built-in themes, fully loaded and sorted by name.
+///
+/// Like [`available_themes`], but as a slice that can be indexed, split into pages or
+/// iterated several times, e.g. to render a theme gallery without calling [`get`] for
+/// each name. Themes are parsed on first access and then shared.
+///
+/// # Examples
+///
+/// ```rust
+/// use lumis::themes::{self, Appearance};
+///
+/// let themes = themes::all();
+/// assert!(themes.windows(2).all(|pair| pair[0].name < pair[1].name));
+///
+/// for theme in themes.iter().filter(|theme| theme.appearance == Appearance::Light) {
+///     println!("{} ({}), background {:?}", theme.name, theme.appearance, theme.bg());
+/// }
+/// ```
+pub fn all() -> &'static [&'static Theme] {
+    &ALL_THEMES
+}
+
+static DEFAULT_THEME: RwLock<Option<Theme>> = RwLock::new(None);
+
+/// Set the process-wide default theme, or clear it with `None`.
+///
+/// Formatters created with `Default` or a builder that doesn't set a theme
+/// ([`HtmlInline`](crate::HtmlInline), [`Terminal`](crate::Terminal) and
+/// [`Svg`](crate::Svg)) use this theme instead of rendering without colors. Setting
+/// `.theme(None)` explicitly still disables it.
+///
+/// The default is stored behind a lock, so it can be set and read from any thread. The
+/// theme is cloned into each formatter when it's built: changing the default later
+/// doesn't affect existing formatters.
+///
+/// # Example
+///
+/// ```rust
+/// use lumis::{highlight, themes, HtmlInlineBuilder, languages::Language};
+///
+/// themes::set_default(themes::get("dracula").ok());
+///
+/// let formatter = HtmlInlineBuilder::new().lang(Language::Rust).build().unwrap();
+/// let html = highlight("fn main() {}", formatter);
+/// assert!(html.contains("background-color: #282a36;"));
+///
+/// themes::set_default(None);
+/// assert_eq!(themes::default(), None);
+/// ```
+pub fn set_default(theme: Option<Theme>) {
+    *DEFAULT_THEME
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = theme;
+}
+
+/// Get the process-wide default theme set with [`set_default`], if any.
+pub fn default() -> Option<Theme> {
+    DEFAULT_THEME
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::path::Path;
 
+    #[test]
+    fn test_get_normalizes_name() {
+        for name in [
+            "catppuccin_mocha",
+            "catppuccin-mocha",
+            "Catppuccin Mocha",
+            " CATPPUCCIN_MOCHA ",
+        ] {
+            assert_eq!(get(name).unwrap().name, "catppuccin_mocha", "{name}");
+        }
+
+        assert_eq!(
+            get("catppuccin.mocha").unwrap_err().to_string(),
+            "theme 'catppuccin.mocha' not found"
+        );
+    }
+
     #[test]
     fn test_available_themes() {
         let themes: Vec<_> = available_themes().collect();
@@ -898,6 +1887,15 @@ mod tests {
         assert_eq!(ALL_THEMES.len(), 117);
     }
 
+    #[test]
+    fn test_all() {
+        let themes = all();
+
+        assert_eq!(themes.len(), available_themes().count());
+        assert!(themes.windows(2).all(|pair| pair[0].name < pair[1].name));
+        assert!(std::ptr::eq(themes[0], available_themes().next().unwrap()));
+    }
+
     #[test]
     fn test_get_by_name() {
         let theme = get("github_light").expect("Theme not found");
@@ -941,6 +1939,303 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_from_scope_map() {
+        let keyword = Style {
+            fg: Some("#0000ff".to_string()),
+            bold: true,
+            ..Default::default()
+        };
+        let theme = Theme::from_scope_map(
+            HashMap::from([("keyword".to_string(), keyword.clone())]),
+            Appearance::Light,
+        );
+
+        assert_eq!(theme.appearance, Appearance::Light);
+        assert_eq!(theme.get_style("keyword.function.rust"), Some(&keyword));
+        assert_eq!(theme.get_style("string"), None);
+        assert_eq!(theme.bg(), None);
+    }
+
+    #[test]
+    fn test_missing_scopes() {
+        use crate::constants::{scope_index, HIGHLIGHT_NAMES};
+
+        let theme = Theme::from_scope_map(
+            HashMap::from([
+                ("keyword".to_string(), Style::default()),
+                ("string".to_string(), Style::default()),
+            ]),
+            Appearance::Dark,
+        );
+        let missing = theme.missing_scopes();
+
+        assert!(missing.contains(&"comment"));
+        assert!(missing.contains(&"function.call"));
+        assert!(!missing.iter().any(|scope| scope.starts_with("keyword")));
+        assert!(!missing.iter().any(|scope| scope.starts_with("string")));
+        assert!(missing
+            .windows(2)
+            .all(|pair| scope_index(pair[0]) < scope_index(pair[1])));
+
+        let empty = Theme::from_scope_map(HashMap::new(), Appearance::Dark);
+        assert_eq!(empty.missing_scopes().len(), HIGHLIGHT_NAMES.len());
+    }
+
+    #[test]
+    fn test_from_vscode_json() {
+        let json = r##"{
+            // Line comment with "quotes"
+            "name": "Test Theme",
+            "colors": {
+                "editor.foreground": "#ccc",
+                "editor.background": "#000000",
+                "editor.selectionBackground": "#ffffff80", /* 50% white */
+                "editorLineNumber.foreground": null,
+            },
+            "tokenColors": [
+                { "settings": { "foreground": "#111111" } },
+                { "scope": "keyword, storage", "settings": { "foreground": "#ff0000" } },
+                { "scope": ["keyword.control.import"], "settings": { "foreground": "#00ff00", "fontStyle": "bold underline" } },
+                { "scope": "keyword", "settings": { "fontStyle": "italic" } },
+                { "scope": "meta.tag string", "settings": { "foreground": "#0000ff" } },
+                { "scope": "string", "settings": { "foreground": "#abcdef", "fontStyle": "" } },
+            ],
+        }"##;
+
+        let theme = from_vscode_json(json).unwrap();
+
+        assert_eq!(theme.name, "Test Theme");
+        assert_eq!(theme.revision, "vscode");
+        assert_eq!(theme.appearance, Appearance::Dark);
+        assert_eq!(theme.fg(), Some("#cccccc"));
+        assert_eq!(theme.bg(), Some("#000000"));
+        assert_eq!(theme.highlights["selection"].bg.as_deref(), Some("#808080"));
+        assert!(!theme.highlights.contains_key("line_number"));
+
+        let keyword = &theme.highlights["keyword"];
+        assert_eq!(keyword.fg.as_deref(), Some("#ff0000"));
+        assert!(keyword.italic);
+
+        let import = &theme.highlights["keyword.import"];
+        assert_eq!(import.fg.as_deref(), Some("#00ff00"));
+        assert!(import.bold);
+        assert!(!import.italic);
+        assert_eq!(import.text_decoration.underline, UnderlineStyle::Solid);
+
+        let string = &theme.highlights["string"];
+        assert_eq!(string.fg.as_deref(), Some("#abcdef"));
+        assert!(!string.italic);
+
+        assert!(!theme.highlights.contains_key("function"));
+
+        assert!(matches!(
+            from_vscode_json("{ \"tokenColors\": 1 }"),
+            Err(ThemeError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn test_highlight_line_css() {
+        let highlighted = Style {
+            fg: Some("#ffffff".to_string()),
+            bg: Some("#44475a".to_string()),
+            ..Default::default()
+        };
+        let theme = Theme::from_scope_map(
+            HashMap::from([("highlighted".to_string(), highlighted)]),
+            Appearance::Dark,
+        );
+
+        assert_eq!(
+            theme.highlight_line_css("hl bg-yellow"),
+            ".line.hl.bg-yellow {\n  color: #ffffff;\n  background-color: #44475a;\n}\n"
+        );
+
+        let theme = Theme::from_scope_map(HashMap::new(), Appearance::Dark);
+        assert_eq!(theme.highlight_line_css("highlighted"), "");
+    }
+
+    #[test]
+    fn test_legend_html() {
+        let theme = Theme::from_scope_map(
+            HashMap::from([
+                (
+                    "normal".to_string(),
+                    Style {
+                        fg: Some("#f8f8f2".to_string()),
+                        bg: Some("#282a36".to_string()),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "comment".to_string(),
+                    Style {
+                        fg: Some("#6272a4".to_string()),
+                        italic: true,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "markup.<b>".to_string(),
+                    Style {
+                        bold: true,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "string".to_string(),
+                    Style {
+                        fg: Some("red\"><script>".to_string()),
+                        ..Default::default()
+                    },
+                ),
+                ("unstyled".to_string(), Style::default()),
+            ]),
+            Appearance::Dark,
+        );
+
+        let html = theme.legend_html();
+
+        assert!(html.starts_with(r#"<div class="lumis-legend" style="display: grid;"#));
+        assert!(
+            html.contains("font-family: monospace; color: #f8f8f2; background-color: #282a36;\">")
+        );
+        assert!(html.contains(
+            r#"vertical-align: middle; background-color: #6272a4;"></span><span style="color: #6272a4; font-style: italic;">comment</span></div>"#
+        ));
+        assert!(html.find(">comment<").unwrap() < html.find(">normal<").unwrap());
+        assert!(html.contains(
+            r#"vertical-align: middle;"></span><span style="font-weight: bold;">markup.&lt;b&gt;</span>"#
+        ));
+        assert!(!html.contains("unstyled"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("background-color: red&quot;&gt;&lt;script&gt;;\"></span>"));
+        assert!(html.ends_with("</div></div>"));
+    }
+
+    #[test]
+    fn test_adjust() {
+        let style = |fg: &str| Style {
+            fg: Some(fg.to_string()),
+            bold: true,
+            ..Default::default()
+        };
+        let theme = Theme::from_scope_map(
+            HashMap::from([
+                ("keyword".to_string(), style("#ff5555")),
+                ("string".to_string(), style("#808080")),
+                ("comment".to_string(), style("NONE")),
+            ]),
+            Appearance::Dark,
+        );
+
+        assert_eq!(theme.adjust(0.0, 0.0).highlights, theme.highlights);
+
+        let adjusted = theme.adjust(0.1, -1.0);
+        assert_eq!(adjusted.get_style("keyword"), Some(&style("#c4c4c4")));
+        assert_eq!(adjusted.get_style("string"), Some(&style("#9a9a9a")));
+        assert_eq!(adjusted.get_style("comment"), Some(&style("NONE")));
+
+        let adjusted = theme.adjust(1.0, 0.0);
+        assert_eq!(adjusted.get_style("keyword"), Some(&style("#ffffff")));
+    }
+
+    #[test]
+    fn test_bg_rgba() {
+        let theme = get("github_light").unwrap();
+        assert_eq!(
+            theme.bg_rgba(0.5).as_deref(),
+            Some("rgba(255, 255, 255, 0.5)")
+        );
+        assert_eq!(
+            theme.bg_rgba(2.0).as_deref(),
+            Some("rgba(255, 255, 255, 1)")
+        );
+
+        let theme = Theme::from_scope_map(HashMap::new(), Appearance::Dark);
+        assert_eq!(theme.bg_rgba(0.5), None);
+    }
+
+    #[test]
+    fn test_ui_colors() {
+        let json = r##"{"name": "test", "appearance": "dark", "revision": "1", "highlights": {
+            "normal": {"fg": "#f8f8f2", "bg": "#282a36"},
+            "comment": {"fg": "#6272a4"},
+            "highlighted": {"bg": "#44475a"},
+            "selection": {"bg": "#3e4452"},
+            "cursor": {"fg": "#282a36", "bg": "#50fa7b"},
+            "line_number": {"fg": "#5c6370"}}}"##;
+        let theme = from_json(json).unwrap();
+
+        assert_eq!(
+            theme.ui_colors(),
+            UiColors {
+                selection_bg: Some("#3e4452".to_string()),
+                cursor: Some("#50fa7b".to_string()),
+                cursor_line_bg: Some("#44475a".to_string()),
+                line_number_fg: Some("#5c6370".to_string()),
+            }
+        );
+
+        let theme = get("dracula").unwrap();
+        let ui = theme.ui_colors();
+        assert_eq!(ui.selection_bg, ui.cursor_line_bg);
+        assert_eq!(ui.cursor.as_deref(), theme.fg());
+        assert_eq!(
+            ui.line_number_fg.as_deref(),
+            theme.get_style("comment").and_then(|s| s.fg.as_deref())
+        );
+
+        assert_eq!(Theme::default().ui_colors(), UiColors::default());
+    }
+
+    #[test]
+    fn test_detect_appearance() {
+        let mismatched: Vec<_> = ALL_THEMES
+            .iter()
+            .filter(|theme| theme.detect_appearance() != theme.appearance)
+            .map(|theme| theme.name.as_str())
+            .collect();
+        assert!(mismatched.is_empty(), "{mismatched:?}");
+
+        let dark_bg = Theme {
+            appearance: Appearance::Light,
+            highlights: BTreeMap::from([(
+                "normal".to_string(),
+                Style {
+                    bg: Some("#282a36".to_string()),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        assert_eq!(dark_bg.detect_appearance(), Appearance::Dark);
+
+        let no_colors = Theme {
+            appearance: Appearance::Light,
+            ..Default::default()
+        };
+        assert_eq!(no_colors.detect_appearance(), Appearance::Light);
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let themes = load_dir(manifest_dir.join("themes")).unwrap();
+
+        assert_eq!(themes.len(), ALL_THEMES.len());
+        assert!(themes.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        for (name, theme) in &themes {
+            assert_eq!(name, &theme.name);
+        }
+
+        assert!(matches!(
+            load_dir(manifest_dir.join("missing")),
+            Err(ThemeError::FileNotFound(_))
+        ));
+    }
+
     #[test]
     fn test_style_css() {
         let style = Style {
@@ -989,6 +2284,37 @@ pre.lumis {
         assert_eq!(theme.css(true), expected);
     }
 
+    #[test]
+    fn test_theme_css_with_prefix() {
+        let json = r#"{"name": "test", "appearance": "dark", "revision": "3e976b4", "highlights": {"normal": {"fg": "red", "bg": "green"}, "keyword": {"fg": "blue", "italic": true}, "tag.attribute": {"bg": "gray", "bold": true}}}"#;
+        let theme = from_json(json).unwrap();
+
+        let expected = r#".hl-keyword {
+  color: blue;
+  font-style: italic;
+}
+.hl-normal {
+  color: red;
+  background-color: green;
+}
+.hl-tag-attribute {
+  background-color: gray;
+  font-weight: bold;
+}
+"#;
+
+        assert_eq!(theme.css_with_prefix("hl-", true, false), expected);
+        assert!(theme
+            .css_with_prefix("hl-", true, true)
+            .starts_with("/* test\n * revision: 3e976b4\n */\n\npre.lumis {"));
+        assert!(theme.css_with_prefix("hl-", true, true).ends_with(expected));
+        assert_eq!(theme.css_with_prefix("", true, true), theme.css(true));
+        assert_eq!(theme.css_with_prefix("", false, true), theme.css(false));
+        assert!(!theme
+            .css_with_prefix("hl-", false, false)
+            .contains("font-style: italic;"));
+    }
+
     // Tests for specialized capture groups (issue #287)
     // https://github.com/leandrocp/lumis/issues/287
 
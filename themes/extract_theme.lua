@@ -2,6 +2,16 @@ local regular_groups = {
 	"Normal",
 	"Comment",
 	"CursorLine",
+	"Visual",
+	"Cursor",
+	"LineNr",
+}
+
+-- Editor UI groups renamed to the keys read by Theme::ui_colors
+local renamed_groups = {
+	cursorline = "highlighted",
+	visual = "selection",
+	linenr = "line_number",
 }
 
 local treesitter_groups = {
@@ -310,9 +320,7 @@ local function extract_colorscheme_colors(theme)
 
 		if next(style) ~= nil then
 			local key = string.lower(string.gsub(group, "@", ""))
-			if key == "cursorline" then
-				key = "highlighted"
-			end
+			key = renamed_groups[key] or key
 			highlights[key] = style
 		end
 	end